@@ -6,9 +6,10 @@ use jupiter_amm_interface::{
 
 use hypertree::{get_helper, get_mut_helper};
 use manifest::{
-    quantities::{BaseAtoms, QuoteAtoms, WrapperU64},
+    quantities::{BaseAtoms, QuoteAtoms, QuoteAtomsPerBaseAtom, WrapperU64},
     state::{
-        DynamicAccount, GlobalFixed, GlobalValue, MarketFixed, MarketValue, GLOBAL_FIXED_SIZE,
+        DynamicAccount, GlobalFixed, GlobalValue, MarketFixed, MarketStatus, MarketTradingConfig,
+        MarketValue, GLOBAL_FIXED_SIZE,
     },
     validation::{
         get_global_address, get_global_vault_address, get_vault_address,
@@ -67,6 +68,41 @@ impl ManifestMarket {
     pub fn get_quote_global_address(&self) -> Pubkey {
         get_global_address(self.market.get_quote_mint()).0
     }
+
+    /// Tick, lot and minimum size that resting orders must respect. None if
+    /// the market does not restrict them.
+    pub fn get_trading_config(&self) -> Option<MarketTradingConfig> {
+        self.market.get_trading_config().copied()
+    }
+
+    /// Round a price to a valid tick for this market.
+    pub fn round_price_to_tick(
+        &self,
+        price: QuoteAtomsPerBaseAtom,
+        round_up: bool,
+    ) -> Result<QuoteAtomsPerBaseAtom> {
+        match self.get_trading_config() {
+            Some(trading_config) => Ok(price
+                .checked_round_to_tick(trading_config.get_tick_size(), round_up)
+                .map_err(|_| Error::msg("Price overflow rounding to tick"))?),
+            None => Ok(price),
+        }
+    }
+
+    /// Round base atoms to a valid lot for this market.
+    pub fn round_base_atoms_to_lot(&self, base_atoms: u64, round_up: bool) -> u64 {
+        let lot_size: u64 = self
+            .get_trading_config()
+            .map_or(0, |trading_config| trading_config.get_lot_size().as_u64());
+        if lot_size == 0 {
+            return base_atoms;
+        }
+        if round_up {
+            base_atoms.div_ceil(lot_size).saturating_mul(lot_size)
+        } else {
+            base_atoms / lot_size * lot_size
+        }
+    }
 }
 
 impl Amm for ManifestMarket {
@@ -163,7 +199,8 @@ impl Amm for ManifestMarket {
         // filling an order completely, or reversing into a coalescing order. Or
         // there just might not be reversible orders on the book.
         let can_expand = market.has_two_free_blocks();
-        if !can_expand {
+        // Swaps fail on paused and cancel only markets.
+        if !can_expand || market.get_market_status() != MarketStatus::Active {
             return Ok(Quote {
                 out_amount: 0,
                 ..Quote::default()
@@ -346,7 +383,8 @@ mod test {
         quantities::{BaseAtoms, GlobalAtoms},
         state::{
             constants::NO_EXPIRATION_LAST_VALID_SLOT, AddOrderToMarketArgs, OrderType,
-            GLOBAL_BLOCK_SIZE, MARKET_BLOCK_SIZE, MARKET_FIXED_SIZE,
+            ReversePriceBounds, SelfTradeBehavior, GLOBAL_BLOCK_SIZE, MARKET_BLOCK_SIZE,
+            MARKET_FIXED_SIZE,
         },
         validation::{MintAccountInfo, Signer},
     };
@@ -504,6 +542,11 @@ mod test {
                 order_type: OrderType::Global,
                 global_trade_accounts_opts: &[None, quote_global_trade_accounts],
                 current_slot: None,
+                self_trade_behavior: SelfTradeBehavior::Allow,
+                client_order_id: 0,
+                reverse_price_bounds: ReversePriceBounds::default(),
+                iceberg_display_base_atoms: BaseAtoms::ZERO,
+                event_queue_opt: &None,
             })
             .unwrap();
 
@@ -520,6 +563,11 @@ mod test {
                 order_type: OrderType::Limit,
                 global_trade_accounts_opts: &[None, None],
                 current_slot: None,
+                self_trade_behavior: SelfTradeBehavior::Allow,
+                client_order_id: 0,
+                reverse_price_bounds: ReversePriceBounds::default(),
+                iceberg_display_base_atoms: BaseAtoms::ZERO,
+                event_queue_opt: &None,
             })
             .unwrap();
 
@@ -29,5 +29,6 @@ pub fn mock_place_order(
         order_index: nondet(),
         base_atoms_traded: nondet(),
         quote_atoms_traded: nondet(),
+        taker_fee_atoms: 0,
     })
 }
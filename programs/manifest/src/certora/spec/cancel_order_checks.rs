@@ -50,7 +50,8 @@ pub fn cancel_order_by_index_no_revert<const IS_BID: bool>() {
         &mut market_info.try_borrow_mut_data().unwrap();
     let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
     let order_index: DataIndex = maker_order_index;
-    let result: ProgramResult = dynamic_account.cancel_order_by_index(order_index, &[None, None]);
+    let result: ProgramResult =
+        dynamic_account.cancel_order_by_index(order_index, &[None, None], &None);
     cvt_assert!(result.is_ok());
 
     cvt_vacuity_check!();
@@ -12,7 +12,10 @@ use crate::{
         withdraw::{process_withdraw_core, WithdrawParams},
     },
     quantities::{BaseAtoms, QuoteAtoms},
-    state::{get_helper_order, AddOrderToMarketArgs, DynamicAccount, MarketRefMut, RestingOrder},
+    state::{
+        get_helper_order, AddOrderToMarketArgs, DynamicAccount, MarketRefMut, RestingOrder,
+        ReversePriceBounds,
+    },
 };
 use hypertree::DataIndex;
 
@@ -280,6 +283,11 @@ fn rest_remaining_check<const IS_BID: bool>() {
         order_type: state::OrderType::Limit,
         global_trade_accounts_opts: &[None, None],
         current_slot: Some(nondet()),
+        self_trade_behavior: state::SelfTradeBehavior::Allow,
+        client_order_id: 0,
+        reverse_price_bounds: ReversePriceBounds::default(),
+        iceberg_display_base_atoms: BaseAtoms::ZERO,
+        event_queue_opt: &None,
     };
 
     let remaining_base_atoms_arg: BaseAtoms = nondet();
@@ -432,7 +440,7 @@ pub fn cancel_order_check<const IS_BID: bool>() {
             &mut market_info.try_borrow_mut_data().unwrap();
         let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
         dynamic_account
-            .cancel_order(trader_index, order_sequence_number, &[None, None])
+            .cancel_order(trader_index, order_sequence_number, &[None, None], &None)
             .unwrap();
     };
 
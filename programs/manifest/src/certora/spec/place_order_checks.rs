@@ -13,7 +13,7 @@ use crate::{
     quantities::{BaseAtoms, QuoteAtomsPerBaseAtom},
     state::{
         market::market_helpers::{AddOrderStatus, AddOrderToMarketInnerResult, AddSingleOrderCtx},
-        AddOrderToMarketArgs, DynamicAccount, MarketRefMut,
+        AddOrderToMarketArgs, DynamicAccount, MarketRefMut, ReversePriceBounds,
     },
 };
 use hypertree::DataIndex;
@@ -31,6 +31,11 @@ pub fn place_single_order_nondet_inputs<const IS_BID: bool>(
         order_type: state::OrderType::Limit,
         global_trade_accounts_opts: &[None, None],
         current_slot: Some(nondet()),
+        self_trade_behavior: state::SelfTradeBehavior::Allow,
+        client_order_id: 0,
+        reverse_price_bounds: ReversePriceBounds::default(),
+        iceberg_display_base_atoms: BaseAtoms::ZERO,
+        event_queue_opt: &None,
     };
     let remaining_base_atoms: BaseAtoms = nondet();
     let now_slot: u32 = nondet();
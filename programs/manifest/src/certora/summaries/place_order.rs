@@ -98,5 +98,6 @@ pub fn place_fully_match_order_with_same_base_and_quote(
         order_index,
         base_atoms_traded,
         quote_atoms_traded,
+        taker_fee_atoms: 0,
     })
 }
@@ -186,6 +186,7 @@ macro_rules! rest_remaining {
                 $order_sequence_number,
                 $total_base_atoms_traded,
                 $total_quote_atoms_traded,
+                0,
             )
             .unwrap()
     }};
@@ -201,7 +202,7 @@ macro_rules! cancel_order_by_index {
             &mut $market_acc_info.try_borrow_mut_data().unwrap();
         let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
         dynamic_account
-            .cancel_order_by_index($order_index, &[None, None])
+            .cancel_order_by_index($order_index, &[None, None], &None)
             .unwrap();
     }};
 }
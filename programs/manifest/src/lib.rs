@@ -18,12 +18,33 @@ pub mod certora;
 
 use hypertree::trace;
 use program::{
-    batch_update::process_batch_update, claim_seat::process_claim_seat,
-    create_market::process_create_market, deposit::process_deposit,
-    expand_market::process_expand_market, global_add_trader::process_global_add_trader,
-    global_clean::process_global_clean, global_create::process_global_create,
-    global_deposit::process_global_deposit, global_evict::process_global_evict,
-    global_withdraw::process_global_withdraw, process_swap, withdraw::process_withdraw,
+    batch_update::{process_batch_update, process_batch_update_v2},
+    cancel_all::process_cancel_all,
+    claim_seat::process_claim_seat,
+    clean_expired::process_clean_expired,
+    clear_seat_delegate::process_clear_seat_delegate,
+    collect_fees::process_collect_fees,
+    consume_events::process_consume_events,
+    create_event_queue::process_create_event_queue,
+    create_market::process_create_market,
+    deposit::{process_deposit, process_deposit_for},
+    expand_market::process_expand_market,
+    get_book::process_get_book,
+    global_add_trader::process_global_add_trader,
+    global_clean::process_global_clean,
+    global_create::process_global_create,
+    global_deposit::process_global_deposit,
+    global_evict::process_global_evict,
+    global_withdraw::process_global_withdraw,
+    place_grid::process_place_grid,
+    process_swap,
+    rebalance::process_rebalance,
+    set_market_status::process_set_market_status,
+    set_membership::process_set_membership,
+    set_seat_delegate::process_set_seat_delegate,
+    swap_route::process_swap_route,
+    transfer_market_authority::process_transfer_market_authority,
+    withdraw::{process_withdraw, process_withdraw_to},
     ManifestInstruction,
 };
 use solana_program::{
@@ -137,6 +158,57 @@ pub fn process_instruction(
         ManifestInstruction::BatchUpdate => {
             process_batch_update(program_id, accounts, data)?;
         }
+        ManifestInstruction::BatchUpdateV2 => {
+            process_batch_update_v2(program_id, accounts, data)?;
+        }
+        ManifestInstruction::CancelAll => {
+            process_cancel_all(program_id, accounts, data)?;
+        }
+        ManifestInstruction::CollectFees => {
+            process_collect_fees(program_id, accounts, data)?;
+        }
+        ManifestInstruction::SetMarketStatus => {
+            process_set_market_status(program_id, accounts, data)?;
+        }
+        ManifestInstruction::TransferMarketAuthority => {
+            process_transfer_market_authority(program_id, accounts, data)?;
+        }
+        ManifestInstruction::SwapRoute => {
+            process_swap_route(program_id, accounts, data)?;
+        }
+        ManifestInstruction::PlaceGrid => {
+            process_place_grid(program_id, accounts, data)?;
+        }
+        ManifestInstruction::SetSeatDelegate => {
+            process_set_seat_delegate(program_id, accounts, data)?;
+        }
+        ManifestInstruction::ClearSeatDelegate => {
+            process_clear_seat_delegate(program_id, accounts, data)?;
+        }
+        ManifestInstruction::CreateEventQueue => {
+            process_create_event_queue(program_id, accounts, data)?;
+        }
+        ManifestInstruction::ConsumeEvents => {
+            process_consume_events(program_id, accounts, data)?;
+        }
+        ManifestInstruction::SetMembership => {
+            process_set_membership(program_id, accounts, data)?;
+        }
+        ManifestInstruction::DepositFor => {
+            process_deposit_for(program_id, accounts, data)?;
+        }
+        ManifestInstruction::WithdrawTo => {
+            process_withdraw_to(program_id, accounts, data)?;
+        }
+        ManifestInstruction::Rebalance => {
+            process_rebalance(program_id, accounts, data)?;
+        }
+        ManifestInstruction::CleanExpired => {
+            process_clean_expired(program_id, accounts, data)?;
+        }
+        ManifestInstruction::GetBook => {
+            process_get_book(program_id, accounts, data)?;
+        }
         ManifestInstruction::GlobalCreate => {
             process_global_create(program_id, accounts, data)?;
         }
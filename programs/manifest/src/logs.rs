@@ -68,6 +68,76 @@ pub struct WithdrawLog {
     pub amount_atoms: u64,
 }
 
+/// Emitted when a payer funds the seat of another trader.
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
+pub struct DepositForLog {
+    pub market: Pubkey,
+    pub payer: Pubkey,
+    pub trader: Pubkey,
+    pub mint: Pubkey,
+    pub amount_atoms: u64,
+}
+
+/// Emitted when a trader withdraws to a token account owned by recipient.
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
+pub struct WithdrawToLog {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount_atoms: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
+pub struct CollectFeesLog {
+    pub market: Pubkey,
+    pub fee_recipient: Pubkey,
+    pub mint: Pubkey,
+    pub amount_atoms: u64,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
+pub struct MarketStatusLog {
+    pub market: Pubkey,
+    pub authority: Pubkey,
+    pub status: u8,
+    pub _padding: [u8; 7],
+}
+
+/// Emitted when an authority is set at creation, transferred or renounced.
+/// Renounced authorities are the default pubkey.
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
+pub struct MarketAuthorityLog {
+    pub market: Pubkey,
+    pub old_authority: Pubkey,
+    pub new_authority: Pubkey,
+}
+
+/// Emitted when a seat delegate is set or cleared. Cleared delegates are the
+/// default pubkey.
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
+pub struct SeatDelegateLog {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub delegate: Pubkey,
+}
+
+/// Emitted when the gate authority grants or revokes a membership.
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
+pub struct MembershipLog {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub is_member: PodBool,
+    pub _padding: [u8; 7],
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
 pub struct FillLog {
@@ -83,7 +153,14 @@ pub struct FillLog {
     pub taker_sequence_number: u64,
     pub taker_is_buy: PodBool,
     pub is_maker_global: PodBool,
-    pub _padding: [u8; 14],
+    pub _padding: [u8; 6],
+    pub maker_client_order_id: u64,
+    pub taker_client_order_id: u64,
+    /// Taken from what the taker received. Base atoms when taker_is_buy,
+    /// otherwise quote atoms.
+    pub taker_fee_atoms: u64,
+    /// Paid to the maker out of the taker fee in the same token.
+    pub maker_rebate_atoms: u64,
 }
 
 #[repr(C)]
@@ -115,6 +192,7 @@ pub struct PlaceOrderLogV2 {
     pub order_type: OrderType,
     pub is_bid: PodBool,
     pub _padding: [u8; 6],
+    pub client_order_id: u64,
 }
 
 #[repr(C)]
@@ -125,6 +203,21 @@ pub struct CancelOrderLog {
     pub order_sequence_number: u64,
 }
 
+/// An iceberg display slice was filled and replenished from the reserve. The
+/// order continues under the new sequence number.
+#[repr(C)]
+#[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
+pub struct IcebergRefillLog {
+    pub market: Pubkey,
+    pub trader: Pubkey,
+    pub price: QuoteAtomsPerBaseAtom,
+    pub base_atoms: BaseAtoms,
+    pub previous_order_sequence_number: u64,
+    pub order_sequence_number: u64,
+    pub order_index: u32,
+    pub _padding: [u8; 4],
+}
+
 #[repr(C)]
 #[derive(Clone, Copy, Zeroable, Pod, ShankAccount)]
 pub struct GlobalCreateLog {
@@ -207,10 +300,13 @@ const CREATE_MARKET_LOG_DISCRIMINANT: [u8; 8] = [33, 31, 11, 6, 133, 143, 39, 71
 const CLAIM_SEAT_LOG_DISCRIMINANT: [u8; 8] = [129, 77, 152, 210, 218, 144, 163, 56];
 const DEPOSIT_LOG_DISCRIMINANT: [u8; 8] = [23, 214, 24, 34, 52, 104, 109, 188];
 const WITHDRAW_LOG_DISCRIMINANT: [u8; 8] = [112, 218, 111, 63, 18, 95, 136, 35];
+const DEPOSIT_FOR_LOG_DISCRIMINANT: [u8; 8] = [102, 105, 207, 183, 190, 88, 142, 128];
+const WITHDRAW_TO_LOG_DISCRIMINANT: [u8; 8] = [239, 75, 5, 21, 14, 177, 224, 164];
 const FILL_LOG_DISCRIMINANT: [u8; 8] = [58, 230, 242, 3, 75, 113, 4, 169];
 const PLACE_ORDER_LOG_DISCRIMINANT: [u8; 8] = [157, 118, 247, 213, 47, 19, 164, 120];
 const PLACE_ORDER_LOG_V2_DISCRIMINANT: [u8; 8] = [189, 97, 159, 235, 136, 5, 1, 141];
 const CANCEL_ORDER_LOG_DISCRIMINANT: [u8; 8] = [22, 65, 71, 33, 244, 235, 255, 215];
+const ICEBERG_REFILL_LOG_DISCRIMINANT: [u8; 8] = [157, 143, 12, 212, 25, 151, 95, 205];
 const GLOBAL_CREATE_LOG_DISCRIMINANT: [u8; 8] = [188, 25, 199, 77, 26, 15, 142, 193];
 const GLOBAL_ADD_TRADER_LOG_DISCRIMINANT: [u8; 8] = [129, 246, 90, 94, 87, 186, 242, 7];
 const GLOBAL_CLAIM_SEAT_LOG_DISCRIMINANT: [u8; 8] = [164, 46, 227, 175, 3, 143, 73, 86];
@@ -218,6 +314,11 @@ const GLOBAL_DEPOSIT_LOG_DISCRIMINANT: [u8; 8] = [16, 26, 72, 1, 145, 232, 182,
 const GLOBAL_WITHDRAW_LOG_DISCRIMINANT: [u8; 8] = [206, 118, 67, 64, 124, 109, 157, 201];
 const GLOBAL_EVICT_LOG_DISCRIMINANT: [u8; 8] = [250, 180, 155, 38, 98, 223, 82, 223];
 const GLOBAL_CLEANUP_LOG_DISCRIMINANT: [u8; 8] = [193, 249, 115, 186, 42, 126, 196, 82];
+const COLLECT_FEES_LOG_DISCRIMINANT: [u8; 8] = [43, 109, 212, 139, 191, 4, 76, 12];
+const MARKET_STATUS_LOG_DISCRIMINANT: [u8; 8] = [12, 196, 81, 19, 248, 229, 14, 224];
+const MARKET_AUTHORITY_LOG_DISCRIMINANT: [u8; 8] = [5, 216, 36, 161, 27, 55, 151, 248];
+const SEAT_DELEGATE_LOG_DISCRIMINANT: [u8; 8] = [254, 55, 224, 160, 88, 58, 150, 58];
+const MEMBERSHIP_LOG_DISCRIMINANT: [u8; 8] = [86, 236, 160, 201, 95, 113, 219, 191];
 
 discriminant!(
     CreateMarketLog,
@@ -230,8 +331,43 @@ discriminant!(
     test_claim_seat_log
 );
 discriminant!(DepositLog, DEPOSIT_LOG_DISCRIMINANT, test_deposit_log);
+discriminant!(
+    DepositForLog,
+    DEPOSIT_FOR_LOG_DISCRIMINANT,
+    test_deposit_for_log
+);
 discriminant!(WithdrawLog, WITHDRAW_LOG_DISCRIMINANT, test_withdraw_log);
+discriminant!(
+    WithdrawToLog,
+    WITHDRAW_TO_LOG_DISCRIMINANT,
+    test_withdraw_to_log
+);
 discriminant!(FillLog, FILL_LOG_DISCRIMINANT, test_fill_log);
+discriminant!(
+    CollectFeesLog,
+    COLLECT_FEES_LOG_DISCRIMINANT,
+    test_collect_fees_log
+);
+discriminant!(
+    MarketStatusLog,
+    MARKET_STATUS_LOG_DISCRIMINANT,
+    test_market_status_log
+);
+discriminant!(
+    MarketAuthorityLog,
+    MARKET_AUTHORITY_LOG_DISCRIMINANT,
+    test_market_authority_log
+);
+discriminant!(
+    SeatDelegateLog,
+    SEAT_DELEGATE_LOG_DISCRIMINANT,
+    test_seat_delegate_log
+);
+discriminant!(
+    MembershipLog,
+    MEMBERSHIP_LOG_DISCRIMINANT,
+    test_membership_log
+);
 discriminant!(
     PlaceOrderLog,
     PLACE_ORDER_LOG_DISCRIMINANT,
@@ -247,6 +383,11 @@ discriminant!(
     CANCEL_ORDER_LOG_DISCRIMINANT,
     test_cancel_order
 );
+discriminant!(
+    IcebergRefillLog,
+    ICEBERG_REFILL_LOG_DISCRIMINANT,
+    test_iceberg_refill_log
+);
 discriminant!(
     GlobalCreateLog,
     GLOBAL_CREATE_LOG_DISCRIMINANT,
@@ -48,6 +48,36 @@ pub enum ManifestError {
     InvalidEvict = 20,
     #[error("Tried to clean order that was not eligible to be cleaned")]
     InvalidClean = 21,
+    #[error("Order would match against a resting order from the same trader")]
+    WouldSelfTrade = 22,
+    #[error("Fill or kill order could not be fully matched")]
+    FillOrKillNotFilled = 23,
+    #[error("Price is not a multiple of the market tick size")]
+    PriceNotOnTick = 24,
+    #[error("Base atoms is not a multiple of the market lot size")]
+    SizeNotOnLot = 25,
+    #[error("Market is paused")]
+    MarketPaused = 26,
+    #[error("Market is cancel only")]
+    MarketCancelOnly = 27,
+    #[error("Signer is not the market authority")]
+    IncorrectMarketAuthority = 28,
+    #[error("Invalid seat delegate")]
+    InvalidSeatDelegate = 29,
+    #[error("Event queue is full")]
+    EventQueueFull = 30,
+    #[error("Market event queue was not included")]
+    MissingEventQueue = 31,
+    #[error("Permissioned market requires the gate authority or a membership")]
+    MissingMembership = 32,
+    #[error("Trader is cancel only")]
+    TraderCancelOnly = 33,
+    #[error("Signer is not the market gate authority")]
+    IncorrectGateAuthority = 34,
+    #[error("Invalid native SOL swap")]
+    InvalidNativeSol = 35,
+    #[error("Transfer hook mints are not supported")]
+    TransferHookNotSupported = 36,
 }
 
 impl From<ManifestError> for ProgramError {
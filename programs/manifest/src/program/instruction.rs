@@ -26,6 +26,8 @@ pub enum ManifestInstruction {
     ClaimSeat = 1,
 
     /// Deposit
+    ///
+    /// Extra account metas for transfer hook mints go after the mint.
     #[account(0, writable, signer, name = "payer", desc = "Payer")]
     #[account(1, writable, name = "market", desc = "Account holding all market state")]
     #[account(2, writable, name = "trader_token", desc = "Trader token account")]
@@ -35,6 +37,8 @@ pub enum ManifestInstruction {
     Deposit = 2,
 
     /// Withdraw
+    ///
+    /// Extra account metas for transfer hook mints go after the mint.
     #[account(0, writable, signer, name = "payer", desc = "Payer")]
     #[account(1, writable, name = "market", desc = "Account holding all market state")]
     #[account(2, writable, name = "trader_token", desc = "Trader token account")]
@@ -44,6 +48,11 @@ pub enum ManifestInstruction {
     Withdraw = 3,
 
     /// Places an order using funds in a wallet instead of on deposit
+    ///
+    /// Extra account metas for transfer hook mints go after the global
+    /// accounts. Native SOL swaps pass the native sol address, seeds are
+    /// [b'native-sol', owner], as the trader account for the native mint and
+    /// need that mint included.
     #[account(0, writable, signer, name = "payer", desc = "Payer")]
     #[account(1, writable, name = "market", desc = "Account holding all market state")]
     #[account(2, name = "system_program", desc = "System program")]
@@ -146,7 +155,9 @@ pub enum ManifestInstruction {
     /// Places an order using funds in a wallet instead of on deposit. Separates
     /// the owner of the token accounts and the payer. This allows routers to
     /// swap and have intermediate hops go through PDAs, rather than all token
-    /// accounts owned by the user.
+    /// accounts owned by the user. Extra account metas for transfer hook
+    /// mints go after the global accounts. Native SOL swaps are paid by the
+    /// payer and unwrap to the owner, accounts are the same as for Swap.
     #[account(0, writable, signer, name = "payer", desc = "Payer")]
     #[account(1, writable, signer, name = "owner", desc = "Owner")]
     #[account(2, writable, name = "market", desc = "Account holding all market state")]
@@ -162,6 +173,174 @@ pub enum ManifestInstruction {
     #[account(12, writable, optional, name = "global", desc = "Global account")]
     #[account(13, writable, optional, name = "global_vault", desc = "Global vault")]
     SwapV2 = 13,
+
+    /// Cancels all of a trader's orders, optionally filtered by side and order
+    /// type. Accounts are the same as BatchUpdate. Global accounts are needed
+    /// to get back gas prepayments on global orders.
+    #[account(0, writable, signer, name = "payer", desc = "Payer")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, name = "system_program", desc = "System program")]
+    #[account(3, optional, name = "base_mint", desc = "Mint for the base global account")]
+    #[account(4, optional, writable, name = "base_global", desc = "Base global account")]
+    #[account(5, optional, name = "base_global_vault", desc = "Base global vault")]
+    #[account(6, optional, name = "base_market_vault", desc = "Base market vault")]
+    #[account(7, optional, name = "base_token_program", desc = "Token program(22)")]
+    #[account(8, optional, name = "quote_mint", desc = "Mint for this global account")]
+    #[account(9, optional, writable, name = "quote_global", desc = "Quote global account")]
+    #[account(10, optional, name = "quote_global_vault", desc = "Quote global vault")]
+    #[account(11, optional, name = "quote_market_vault", desc = "Quote market vault")]
+    #[account(12, optional, name = "quote_token_program", desc = "Token program(22)")]
+    CancelAll = 14,
+
+    /// Send collected market fees to the fee recipient. Permissionless.
+    #[account(0, writable, signer, name = "payer", desc = "Payer")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, writable, name = "fee_recipient_token", desc = "Token account owned by the fee recipient")]
+    #[account(3, writable, name = "vault", desc = "Vault PDA, seeds are [b'vault', market, mint]")]
+    #[account(4, name = "token_program", desc = "Token program(22), should be the version that aligns with the token being used")]
+    #[account(5, name = "mint", desc = "Required for token22 transfer_checked")]
+    CollectFees = 15,
+
+    /// Pause, resume or make a market cancel only. Market authority only.
+    #[account(0, signer, name = "authority", desc = "Market authority")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    SetMarketStatus = 16,
+
+    /// Transfer the market authority, or renounce it by transferring to the default pubkey.
+    #[account(0, signer, name = "authority", desc = "Market authority")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    TransferMarketAuthority = 17,
+
+    /// Exact in swap through two markets that share a mint, e.g. SOL/USDC then
+    /// BONK/USDC. Intermediate tokens go directly between the market vaults.
+    #[account(0, writable, signer, name = "payer", desc = "Payer")]
+    #[account(1, signer, name = "owner", desc = "Owner of the trader token accounts")]
+    #[account(2, name = "system_program", desc = "System program")]
+    #[account(3, writable, name = "first_market", desc = "Market for the first hop")]
+    #[account(4, writable, name = "second_market", desc = "Market for the second hop")]
+    #[account(5, name = "in_mint", desc = "Mint sent into the first market")]
+    #[account(6, name = "intermediate_mint", desc = "Mint shared by both markets")]
+    #[account(7, name = "out_mint", desc = "Mint received from the second market")]
+    #[account(8, writable, name = "trader_in", desc = "Trader in token account")]
+    #[account(9, writable, name = "trader_intermediate", desc = "Trader intermediate token account, receives what the second market could not use")]
+    #[account(10, writable, name = "trader_out", desc = "Trader out token account")]
+    #[account(11, writable, name = "first_in_vault", desc = "First market vault for the in mint")]
+    #[account(12, writable, name = "first_intermediate_vault", desc = "First market vault for the intermediate mint")]
+    #[account(13, writable, name = "second_intermediate_vault", desc = "Second market vault for the intermediate mint")]
+    #[account(14, writable, name = "second_out_vault", desc = "Second market vault for the out mint")]
+    #[account(15, name = "token_program_in", desc = "Token program(22) for the in mint")]
+    #[account(16, name = "token_program_intermediate", desc = "Token program(22) for the intermediate mint")]
+    #[account(17, name = "token_program_out", desc = "Token program(22) for the out mint")]
+    SwapRoute = 18,
+
+    /// Place a ladder of reverse orders generated from grid parameters.
+    #[account(0, writable, signer, name = "payer", desc = "Payer")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, name = "system_program", desc = "System program")]
+    PlaceGrid = 19,
+
+    /// Set a key that may place and cancel orders for the seat but never
+    /// withdraw. Replaces any existing delegate.
+    #[account(0, writable, signer, name = "owner", desc = "Seat owner")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, name = "system_program", desc = "System program")]
+    SetSeatDelegate = 20,
+
+    /// Remove the seat delegate.
+    #[account(0, writable, signer, name = "owner", desc = "Seat owner")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, name = "system_program", desc = "System program")]
+    ClearSeatDelegate = 21,
+
+    /// Attach an event queue to the market. Only the market authority.
+    /// Fills fail while the queue is full or not included, cancels do not.
+    #[account(0, writable, signer, name = "authority", desc = "Market authority")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, writable, name = "event_queue", desc = "Zeroed account owned by manifest, sized for the capacity")]
+    #[account(3, name = "system_program", desc = "System program")]
+    CreateEventQueue = 22,
+
+    /// Mark the oldest events in the event queue as consumed. Permissionless.
+    #[account(0, writable, name = "event_queue", desc = "Market event queue")]
+    ConsumeEvents = 23,
+
+    /// Grant or revoke a membership on a permissioned market. Only the gate
+    /// authority. Revoking moves an existing seat to cancel only.
+    #[account(0, writable, signer, name = "gate_authority", desc = "Market gate authority")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, writable, name = "membership", desc = "Membership PDA of market and trader")]
+    #[account(3, name = "system_program", desc = "System program")]
+    SetMembership = 24,
+
+    /// Deposit into the seat of another trader. The payer owns the token
+    /// account and the trader does not sign.
+    ///
+    /// Extra account metas for transfer hook mints go after the mint.
+    #[account(0, writable, signer, name = "payer", desc = "Payer")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, writable, name = "trader_token", desc = "Payer token account")]
+    #[account(3, writable, name = "vault", desc = "Vault PDA, seeds are [b'vault', market, mint]")]
+    #[account(4, name = "token_program", desc = "Token program(22), should be the version that aligns with the token being used")]
+    #[account(5, name = "mint", desc = "Required for token22 transfer_checked")]
+    DepositFor = 25,
+
+    /// Withdraw from the seat of the signer to a token account owned by
+    /// anyone.
+    ///
+    /// Extra account metas for transfer hook mints go after the mint.
+    #[account(0, writable, signer, name = "payer", desc = "Trader")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, writable, name = "recipient_token", desc = "Recipient token account")]
+    #[account(3, writable, name = "vault", desc = "Vault PDA, seeds are [b'vault', market, mint]")]
+    #[account(4, name = "token_program", desc = "Token program(22), should be the version that aligns with the token being used")]
+    #[account(5, name = "mint", desc = "Required for token22 transfer_checked")]
+    WithdrawTo = 26,
+
+    /// Batch update where the seat owner and the payer are separate. The payer
+    /// covers expansions and global gas deposits so that PDAs that own seats
+    /// do not need to hold SOL. Optional accounts are the same as BatchUpdate.
+    #[account(0, writable, signer, name = "payer", desc = "Payer")]
+    #[account(1, signer, name = "owner", desc = "Seat owner or its delegate")]
+    #[account(2, writable, name = "market", desc = "Account holding all market state")]
+    #[account(3, name = "system_program", desc = "System program")]
+    BatchUpdateV2 = 27,
+
+    /// Deposit, batch update and then withdraw in one instruction. Deposits
+    /// and withdraws of zero are skipped and the withdraw amount can be all
+    /// that is left on the seat.
+    ///
+    /// Optionally followed by the global and global vault for either side,
+    /// then extra account metas for transfer hook mints. The event queue can
+    /// optionally go last.
+    #[account(0, writable, signer, name = "payer", desc = "Seat owner")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    #[account(2, name = "system_program", desc = "System program")]
+    #[account(3, writable, name = "trader_base", desc = "Trader base token account")]
+    #[account(4, writable, name = "base_vault", desc = "Base vault PDA, seeds are [b'vault', market, base_mint]")]
+    #[account(5, name = "token_program_base", desc = "Token program(22) for the base mint")]
+    #[account(6, name = "base_mint", desc = "Base mint")]
+    #[account(7, writable, name = "trader_quote", desc = "Trader quote token account")]
+    #[account(8, writable, name = "quote_vault", desc = "Quote vault PDA, seeds are [b'vault', market, quote_mint]")]
+    #[account(9, name = "token_program_quote", desc = "Token program(22) for the quote mint")]
+    #[account(10, name = "quote_mint", desc = "Quote mint")]
+    #[account(11, optional, writable, name = "base_global", desc = "Base global account")]
+    #[account(12, optional, writable, name = "base_global_vault", desc = "Base global vault")]
+    #[account(13, optional, writable, name = "quote_global", desc = "Quote global account")]
+    #[account(14, optional, writable, name = "quote_global_vault", desc = "Quote global vault")]
+    Rebalance = 28,
+
+    /// Remove expired or empty regular orders from the book and return their
+    /// funds to the owner seats. Permissionless.
+    ///
+    /// The event queue can optionally go last.
+    #[account(0, signer, name = "payer", desc = "Payer")]
+    #[account(1, writable, name = "market", desc = "Account holding all market state")]
+    CleanExpired = 29,
+
+    /// Read only. Sets the top price levels on each side as return data so
+    /// that other programs can read the book without deserializing the market.
+    #[account(0, name = "market", desc = "Account holding all market state")]
+    GetBook = 30,
 }
 
 impl ManifestInstruction {
@@ -172,7 +351,7 @@ impl ManifestInstruction {
 
 #[test]
 fn test_instruction_serialization() {
-    let num_instructions: u8 = 13;
+    let num_instructions: u8 = 30;
     for i in 0..=255 {
         let instruction: ManifestInstruction = match ManifestInstruction::try_from(i) {
             Ok(j) => {
@@ -77,6 +77,40 @@ pub fn batch_update_instruction(
     }
 }
 
+/// Same as batch_update_instruction, but the payer covers expansions and
+/// global gas while the owner signs for the seat.
+#[cfg(not(feature = "certora"))]
+#[allow(clippy::too_many_arguments)]
+pub fn batch_update_v2_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    owner: &Pubkey,
+    trader_index_hint: Option<DataIndex>,
+    cancels: Vec<CancelOrderParams>,
+    orders: Vec<PlaceOrderParams>,
+    base_mint_opt: Option<Pubkey>,
+    base_mint_token_program_opt: Option<Pubkey>,
+    quote_mint_opt: Option<Pubkey>,
+    quote_mint_token_program_opt: Option<Pubkey>,
+) -> Instruction {
+    let mut instruction: Instruction = batch_update_instruction(
+        market,
+        payer,
+        trader_index_hint,
+        cancels,
+        orders,
+        base_mint_opt,
+        base_mint_token_program_opt,
+        quote_mint_opt,
+        quote_mint_token_program_opt,
+    );
+    instruction
+        .accounts
+        .insert(1, AccountMeta::new_readonly(*owner, true));
+    instruction.data[0] = ManifestInstruction::BatchUpdateV2 as u8;
+    instruction
+}
+
 #[cfg(feature = "certora")]
 pub fn batch_update_instruction(
     _market: &Pubkey,
@@ -0,0 +1,58 @@
+use crate::{
+    program::{cancel_all::CancelAllParams, ManifestInstruction},
+    state::OrderType,
+    validation::{get_global_address, get_global_vault_address, get_vault_address},
+};
+use borsh::BorshSerialize;
+use hypertree::DataIndex;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+// Global accounts are only needed to reclaim the gas prepayment on global
+// orders. They are given as (mint, token_program) for each side.
+#[allow(clippy::too_many_arguments)]
+pub fn cancel_all_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    trader_index_hint: Option<DataIndex>,
+    is_bid: Option<bool>,
+    order_type: Option<OrderType>,
+    base_global_mint_opt: Option<(Pubkey, Pubkey)>,
+    quote_global_mint_opt: Option<(Pubkey, Pubkey)>,
+) -> Instruction {
+    let mut account_metas: Vec<AccountMeta> = vec![
+        AccountMeta::new(*payer, true),
+        AccountMeta::new(*market, false),
+        AccountMeta::new_readonly(system_program::id(), false),
+    ];
+    for (mint, token_program) in [base_global_mint_opt, quote_global_mint_opt]
+        .into_iter()
+        .flatten()
+    {
+        let (global, _) = get_global_address(&mint);
+        let (global_vault, _) = get_global_vault_address(&mint);
+        let (market_vault, _) = get_vault_address(market, &mint);
+        account_metas.extend([
+            AccountMeta::new_readonly(mint, false),
+            AccountMeta::new(global, false),
+            AccountMeta::new(global_vault, false),
+            AccountMeta::new(market_vault, false),
+            AccountMeta::new_readonly(token_program, false),
+        ]);
+    }
+
+    Instruction {
+        program_id: crate::id(),
+        accounts: account_metas,
+        data: [
+            ManifestInstruction::CancelAll.to_vec(),
+            CancelAllParams::new(trader_index_hint, is_bid, order_type)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
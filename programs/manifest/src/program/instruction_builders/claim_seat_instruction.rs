@@ -16,3 +16,19 @@ pub fn claim_seat_instruction(market: &Pubkey, payer: &Pubkey) -> Instruction {
         data: [ManifestInstruction::ClaimSeat.to_vec()].concat(),
     }
 }
+
+/// Claim seat on a permissioned market. The gate credential is either the gate
+/// authority, which then also has to sign, or the trader membership.
+pub fn claim_seat_with_gate_credential_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    gate_credential: &Pubkey,
+    is_gate_authority: bool,
+) -> Instruction {
+    let mut instruction: Instruction = claim_seat_instruction(market, payer);
+    instruction.accounts.push(AccountMeta::new_readonly(
+        *gate_credential,
+        is_gate_authority,
+    ));
+    instruction
+}
@@ -0,0 +1,29 @@
+use crate::program::{clean_expired::CleanExpiredParams, ManifestInstruction};
+use borsh::BorshSerialize;
+use hypertree::DataIndex;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+pub fn clean_expired_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    order_indices: Vec<DataIndex>,
+    num_to_scan_per_side: u32,
+) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new(*market, false),
+        ],
+        data: [
+            ManifestInstruction::CleanExpired.to_vec(),
+            CleanExpiredParams::new(order_indices, num_to_scan_per_side)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
@@ -0,0 +1,29 @@
+use crate::{program::ManifestInstruction, validation::get_vault_address};
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// Sends all collected fees in mint to the fee recipient token account.
+pub fn collect_fees_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    fee_recipient_token_account: &Pubkey,
+    token_program: Pubkey,
+) -> Instruction {
+    let (vault_address, _) = get_vault_address(market, mint);
+
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new(*fee_recipient_token_account, false),
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: ManifestInstruction::CollectFees.to_vec(),
+    }
+}
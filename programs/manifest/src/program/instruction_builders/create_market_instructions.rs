@@ -1,6 +1,10 @@
 use crate::{
-    program::ManifestInstruction, state::MarketFixed, validation::get_vault_address, ProgramError,
+    program::{create_market::CreateMarketParams, ManifestInstruction},
+    state::MarketFixed,
+    validation::get_vault_address,
+    ProgramError,
 };
+use borsh::BorshSerialize;
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
@@ -33,6 +37,34 @@ pub fn create_market_instruction(
     base_mint: &Pubkey,
     quote_mint: &Pubkey,
     market_creator: &Pubkey,
+) -> Instruction {
+    create_market_instruction_with_data(market, base_mint, quote_mint, market_creator, vec![])
+}
+
+/// Same as create_market_instruction but with the fees, trading limits and
+/// authorities set in params.
+pub fn create_market_with_params_instruction(
+    market: &Pubkey,
+    base_mint: &Pubkey,
+    quote_mint: &Pubkey,
+    market_creator: &Pubkey,
+    params: CreateMarketParams,
+) -> Instruction {
+    create_market_instruction_with_data(
+        market,
+        base_mint,
+        quote_mint,
+        market_creator,
+        params.try_to_vec().unwrap(),
+    )
+}
+
+fn create_market_instruction_with_data(
+    market: &Pubkey,
+    base_mint: &Pubkey,
+    quote_mint: &Pubkey,
+    market_creator: &Pubkey,
+    params_data: Vec<u8>,
 ) -> Instruction {
     let (base_vault, _) = get_vault_address(market, base_mint);
     let (quote_vault, _) = get_vault_address(market, quote_mint);
@@ -49,6 +81,6 @@ pub fn create_market_instruction(
             AccountMeta::new_readonly(spl_token::id(), false),
             AccountMeta::new_readonly(spl_token_2022::id(), false),
         ],
-        data: [ManifestInstruction::CreateMarket.to_vec()].concat(),
+        data: [ManifestInstruction::CreateMarket.to_vec(), params_data].concat(),
     }
 }
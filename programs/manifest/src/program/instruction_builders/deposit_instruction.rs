@@ -1,5 +1,8 @@
 use crate::{
-    program::{deposit::DepositParams, ManifestInstruction},
+    program::{
+        deposit::{DepositForParams, DepositParams},
+        ManifestInstruction,
+    },
     validation::get_vault_address,
 };
 use borsh::BorshSerialize;
@@ -39,3 +42,34 @@ pub fn deposit_instruction(
         .concat(),
     }
 }
+
+pub fn deposit_for_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    trader: &Pubkey,
+    mint: &Pubkey,
+    amount_atoms: u64,
+    payer_token_account: &Pubkey,
+    token_program: Pubkey,
+) -> Instruction {
+    let (vault_address, _) = get_vault_address(market, mint);
+
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new(*payer_token_account, false),
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: [
+            ManifestInstruction::DepositFor.to_vec(),
+            DepositForParams::new(*trader, amount_atoms)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
@@ -0,0 +1,61 @@
+use crate::{
+    program::{consume_events::ConsumeEventsParams, ManifestInstruction},
+    state::{EVENT_QUEUE_FIXED_SIZE, MARKET_EVENT_SIZE},
+};
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_instruction, system_program,
+    sysvar::rent::Rent,
+};
+
+/// Creates the account sized for capacity events and attaches it to the
+/// market.
+pub fn create_event_queue_instructions(
+    market: &Pubkey,
+    event_queue: &Pubkey,
+    authority: &Pubkey,
+    capacity: u32,
+) -> Vec<Instruction> {
+    let space: usize = EVENT_QUEUE_FIXED_SIZE + capacity as usize * MARKET_EVENT_SIZE;
+    vec![
+        system_instruction::create_account(
+            authority,
+            event_queue,
+            Rent::default().minimum_balance(space),
+            space as u64,
+            &crate::id(),
+        ),
+        create_event_queue_instruction(market, event_queue, authority),
+    ]
+}
+
+pub fn create_event_queue_instruction(
+    market: &Pubkey,
+    event_queue: &Pubkey,
+    authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new(*authority, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new(*event_queue, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: ManifestInstruction::CreateEventQueue.to_vec(),
+    }
+}
+
+pub fn consume_events_instruction(event_queue: &Pubkey, limit: u64) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![AccountMeta::new(*event_queue, false)],
+        data: [
+            ManifestInstruction::ConsumeEvents.to_vec(),
+            ConsumeEventsParams::new(limit).try_to_vec().unwrap(),
+        ]
+        .concat(),
+    }
+}
@@ -0,0 +1,18 @@
+use crate::program::{get_book::GetBookParams, ManifestInstruction};
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+pub fn get_book_instruction(market: &Pubkey, num_levels: u8) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![AccountMeta::new_readonly(*market, false)],
+        data: [
+            ManifestInstruction::GetBook.to_vec(),
+            GetBookParams::new(num_levels).try_to_vec().unwrap(),
+        ]
+        .concat(),
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{
+    program::{
+        set_market_status::SetMarketStatusParams,
+        transfer_market_authority::TransferMarketAuthorityParams, ManifestInstruction,
+    },
+    state::MarketStatus,
+};
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+pub fn set_market_status_instruction(
+    market: &Pubkey,
+    authority: &Pubkey,
+    status: MarketStatus,
+) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*market, false),
+        ],
+        data: [
+            ManifestInstruction::SetMarketStatus.to_vec(),
+            SetMarketStatusParams::new(status).try_to_vec().unwrap(),
+        ]
+        .concat(),
+    }
+}
+
+pub fn transfer_market_authority_instruction(
+    market: &Pubkey,
+    authority: &Pubkey,
+    new_authority: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*authority, true),
+            AccountMeta::new(*market, false),
+        ],
+        data: [
+            ManifestInstruction::TransferMarketAuthority.to_vec(),
+            TransferMarketAuthorityParams::new(*new_authority)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
+
+/// Permanently removes the market authority. The market must be active.
+pub fn renounce_market_authority_instruction(market: &Pubkey, authority: &Pubkey) -> Instruction {
+    transfer_market_authority_instruction(market, authority, &Pubkey::default())
+}
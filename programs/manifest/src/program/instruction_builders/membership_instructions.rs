@@ -0,0 +1,35 @@
+use crate::{
+    program::{set_membership::SetMembershipParams, ManifestInstruction},
+    validation::get_membership_address,
+};
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+pub fn set_membership_instruction(
+    market: &Pubkey,
+    gate_authority: &Pubkey,
+    trader: &Pubkey,
+    is_member: bool,
+) -> Instruction {
+    let (membership, _membership_bump) = get_membership_address(market, trader);
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new(*gate_authority, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new(membership, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: [
+            ManifestInstruction::SetMembership.to_vec(),
+            SetMembershipParams::new(*trader, is_member)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
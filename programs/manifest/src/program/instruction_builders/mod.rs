@@ -1,29 +1,53 @@
 pub mod batch_update_instruction;
+pub mod cancel_all_instruction;
 pub mod claim_seat_instruction;
+pub mod clean_expired_instruction;
+pub mod collect_fees_instruction;
 pub mod create_market_instructions;
 pub mod deposit_instruction;
+pub mod event_queue_instructions;
 pub mod expand_market_instruction;
+pub mod get_book_instruction;
 pub mod global_add_trader_instruction;
 pub mod global_clean_instruction;
 pub mod global_create_instruction;
 pub mod global_deposit_instruction;
 pub mod global_evict_instruction;
 pub mod global_withdraw_instruction;
+pub mod market_authority_instructions;
+pub mod membership_instructions;
+pub mod place_grid_instruction;
+#[cfg(not(feature = "certora"))]
+pub mod rebalance_instruction;
+pub mod seat_delegate_instructions;
 pub mod swap_instruction;
+pub mod swap_route_instruction;
 pub mod swap_v2_instruction;
 pub mod withdraw_instruction;
 
 pub use batch_update_instruction::*;
+pub use cancel_all_instruction::*;
 pub use claim_seat_instruction::*;
+pub use clean_expired_instruction::*;
+pub use collect_fees_instruction::*;
 pub use create_market_instructions::*;
 pub use deposit_instruction::*;
+pub use event_queue_instructions::*;
 pub use expand_market_instruction::*;
+pub use get_book_instruction::*;
 pub use global_add_trader_instruction::*;
 pub use global_clean_instruction::*;
 pub use global_create_instruction::*;
 pub use global_deposit_instruction::*;
 pub use global_evict_instruction::*;
 pub use global_withdraw_instruction::*;
+pub use market_authority_instructions::*;
+pub use membership_instructions::*;
+pub use place_grid_instruction::*;
+#[cfg(not(feature = "certora"))]
+pub use rebalance_instruction::*;
+pub use seat_delegate_instructions::*;
 pub use swap_instruction::*;
+pub use swap_route_instruction::*;
 pub use swap_v2_instruction::*;
 pub use withdraw_instruction::*;
@@ -0,0 +1,54 @@
+use crate::program::{
+    place_grid::{GridStepType, PlaceGridParams},
+    ManifestInstruction,
+};
+use borsh::BorshSerialize;
+use hypertree::DataIndex;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+#[allow(clippy::too_many_arguments)]
+pub fn place_grid_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    trader_index_hint: Option<DataIndex>,
+    start_price_mantissa: u32,
+    start_price_exponent: i8,
+    step_type: GridStepType,
+    step_mantissa: u32,
+    step_exponent: i8,
+    num_levels: u16,
+    num_bid_levels: u16,
+    base_atoms_per_level: u64,
+    spread: u16,
+) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: [
+            ManifestInstruction::PlaceGrid.to_vec(),
+            PlaceGridParams::new(
+                trader_index_hint,
+                start_price_mantissa,
+                start_price_exponent,
+                step_type,
+                step_mantissa,
+                step_exponent,
+                num_levels,
+                num_bid_levels,
+                base_atoms_per_level,
+                spread,
+            )
+            .try_to_vec()
+            .unwrap(),
+        ]
+        .concat(),
+    }
+}
@@ -0,0 +1,124 @@
+use crate::{
+    program::{
+        batch_update::{BatchUpdateParams, CancelOrderParams, PlaceOrderParams},
+        rebalance::RebalanceParams,
+        ManifestInstruction,
+    },
+    validation::{get_global_address, get_global_vault_address, get_vault_address},
+};
+use borsh::BorshSerialize;
+use hypertree::DataIndex;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+// Withdraw amounts can be WITHDRAW_ALL_ATOMS to empty that side of the seat.
+#[allow(clippy::too_many_arguments)]
+pub fn rebalance_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    trader_base_token_account: &Pubkey,
+    base_mint: &Pubkey,
+    base_token_program: Pubkey,
+    trader_quote_token_account: &Pubkey,
+    quote_mint: &Pubkey,
+    quote_token_program: Pubkey,
+    base_deposit_atoms: u64,
+    quote_deposit_atoms: u64,
+    trader_index_hint: Option<DataIndex>,
+    cancels: Vec<CancelOrderParams>,
+    orders: Vec<PlaceOrderParams>,
+    base_withdraw_atoms: u64,
+    quote_withdraw_atoms: u64,
+) -> Instruction {
+    let (base_vault, _) = get_vault_address(market, base_mint);
+    let (quote_vault, _) = get_vault_address(market, quote_mint);
+
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new(*trader_base_token_account, false),
+            AccountMeta::new(base_vault, false),
+            AccountMeta::new_readonly(base_token_program, false),
+            AccountMeta::new_readonly(*base_mint, false),
+            AccountMeta::new(*trader_quote_token_account, false),
+            AccountMeta::new(quote_vault, false),
+            AccountMeta::new_readonly(quote_token_program, false),
+            AccountMeta::new_readonly(*quote_mint, false),
+        ],
+        data: [
+            ManifestInstruction::Rebalance.to_vec(),
+            RebalanceParams::new(
+                base_deposit_atoms,
+                quote_deposit_atoms,
+                base_withdraw_atoms,
+                quote_withdraw_atoms,
+                BatchUpdateParams::new(trader_index_hint, cancels, orders),
+            )
+            .try_to_vec()
+            .unwrap(),
+        ]
+        .concat(),
+    }
+}
+
+/// Same as rebalance_instruction but with the global accounts for the sides
+/// whose global orders are placed, cancelled or matched. Extra account metas
+/// for transfer hook mints go after these.
+#[allow(clippy::too_many_arguments)]
+pub fn rebalance_with_globals_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    trader_base_token_account: &Pubkey,
+    base_mint: &Pubkey,
+    base_token_program: Pubkey,
+    trader_quote_token_account: &Pubkey,
+    quote_mint: &Pubkey,
+    quote_token_program: Pubkey,
+    base_deposit_atoms: u64,
+    quote_deposit_atoms: u64,
+    trader_index_hint: Option<DataIndex>,
+    cancels: Vec<CancelOrderParams>,
+    orders: Vec<PlaceOrderParams>,
+    base_withdraw_atoms: u64,
+    quote_withdraw_atoms: u64,
+    include_base_global: bool,
+    include_quote_global: bool,
+) -> Instruction {
+    let mut instruction: Instruction = rebalance_instruction(
+        market,
+        payer,
+        trader_base_token_account,
+        base_mint,
+        base_token_program,
+        trader_quote_token_account,
+        quote_mint,
+        quote_token_program,
+        base_deposit_atoms,
+        quote_deposit_atoms,
+        trader_index_hint,
+        cancels,
+        orders,
+        base_withdraw_atoms,
+        quote_withdraw_atoms,
+    );
+    for (include_global, mint) in [
+        (include_base_global, base_mint),
+        (include_quote_global, quote_mint),
+    ] {
+        if include_global {
+            let (global, _) = get_global_address(mint);
+            let (global_vault, _) = get_global_vault_address(mint);
+            instruction.accounts.extend([
+                AccountMeta::new(global, false),
+                AccountMeta::new(global_vault, false),
+            ]);
+        }
+    }
+    instruction
+}
@@ -0,0 +1,56 @@
+use crate::program::{
+    clear_seat_delegate::ClearSeatDelegateParams, set_seat_delegate::SetSeatDelegateParams,
+    ManifestInstruction,
+};
+use borsh::BorshSerialize;
+use hypertree::DataIndex;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+pub fn set_seat_delegate_instruction(
+    market: &Pubkey,
+    owner: &Pubkey,
+    trader_index_hint: Option<DataIndex>,
+    delegate: &Pubkey,
+) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: [
+            ManifestInstruction::SetSeatDelegate.to_vec(),
+            SetSeatDelegateParams::new(trader_index_hint, *delegate)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
+
+pub fn clear_seat_delegate_instruction(
+    market: &Pubkey,
+    owner: &Pubkey,
+    trader_index_hint: Option<DataIndex>,
+) -> Instruction {
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new(*owner, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+        ],
+        data: [
+            ManifestInstruction::ClearSeatDelegate.to_vec(),
+            ClearSeatDelegateParams::new(trader_index_hint)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
@@ -0,0 +1,66 @@
+use crate::{
+    program::{swap_route::SwapRouteParams, ManifestInstruction},
+    validation::get_vault_address,
+};
+use borsh::BorshSerialize;
+use solana_program::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+};
+
+/// Swaps in_atoms of in_mint on first_market for the intermediate mint, then
+/// all of that on second_market for out_mint.
+#[allow(clippy::too_many_arguments)]
+pub fn swap_route_instruction(
+    first_market: &Pubkey,
+    second_market: &Pubkey,
+    payer: &Pubkey,
+    owner: &Pubkey,
+    in_mint: &Pubkey,
+    intermediate_mint: &Pubkey,
+    out_mint: &Pubkey,
+    trader_in_account: &Pubkey,
+    trader_intermediate_account: &Pubkey,
+    trader_out_account: &Pubkey,
+    in_atoms: u64,
+    min_out_atoms: u64,
+    token_program_in: Pubkey,
+    token_program_intermediate: Pubkey,
+    token_program_out: Pubkey,
+) -> Instruction {
+    let (first_in_vault, _) = get_vault_address(first_market, in_mint);
+    let (first_intermediate_vault, _) = get_vault_address(first_market, intermediate_mint);
+    let (second_intermediate_vault, _) = get_vault_address(second_market, intermediate_mint);
+    let (second_out_vault, _) = get_vault_address(second_market, out_mint);
+
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new_readonly(solana_program::system_program::id(), false),
+            AccountMeta::new(*first_market, false),
+            AccountMeta::new(*second_market, false),
+            AccountMeta::new_readonly(*in_mint, false),
+            AccountMeta::new_readonly(*intermediate_mint, false),
+            AccountMeta::new_readonly(*out_mint, false),
+            AccountMeta::new(*trader_in_account, false),
+            AccountMeta::new(*trader_intermediate_account, false),
+            AccountMeta::new(*trader_out_account, false),
+            AccountMeta::new(first_in_vault, false),
+            AccountMeta::new(first_intermediate_vault, false),
+            AccountMeta::new(second_intermediate_vault, false),
+            AccountMeta::new(second_out_vault, false),
+            AccountMeta::new_readonly(token_program_in, false),
+            AccountMeta::new_readonly(token_program_intermediate, false),
+            AccountMeta::new_readonly(token_program_out, false),
+        ],
+        data: [
+            ManifestInstruction::SwapRoute.to_vec(),
+            SwapRouteParams::new(in_atoms, min_out_atoms)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
@@ -1,12 +1,15 @@
 use crate::{
     program::{swap::SwapParams, ManifestInstruction},
-    validation::{get_global_address, get_global_vault_address, get_vault_address},
+    validation::{
+        get_global_address, get_global_vault_address, get_native_sol_address, get_vault_address,
+    },
 };
 use borsh::BorshSerialize;
 use solana_program::{
     instruction::{AccountMeta, Instruction},
     pubkey::Pubkey,
 };
+use spl_token::native_mint;
 
 #[allow(clippy::too_many_arguments)]
 pub fn swap_v2_instruction(
@@ -24,12 +27,63 @@ pub fn swap_v2_instruction(
     token_program_base: Pubkey,
     token_program_quote: Pubkey,
     include_global: bool,
+) -> Instruction {
+    swap_v2_instruction_with_params(
+        market,
+        payer,
+        owner,
+        base_mint,
+        quote_mint,
+        trader_base_account,
+        trader_quote_account,
+        SwapParams::new(in_atoms, out_atoms, is_base_in, is_exact_in),
+        token_program_base,
+        token_program_quote,
+        include_global,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn swap_v2_instruction_with_params(
+    market: &Pubkey,
+    payer: &Pubkey,
+    owner: &Pubkey,
+    base_mint: &Pubkey,
+    quote_mint: &Pubkey,
+    trader_base_account: &Pubkey,
+    trader_quote_account: &Pubkey,
+    params: SwapParams,
+    token_program_base: Pubkey,
+    token_program_quote: Pubkey,
+    include_global: bool,
 ) -> Instruction {
     let (vault_base_account, _) = get_vault_address(market, base_mint);
     let (vault_quote_account, _) = get_vault_address(market, quote_mint);
+    // Native SOL swaps move lamports on the payer and owner and go through
+    // the owner's native sol address instead of a wrapped SOL account.
+    let native_sol: bool = params.native_sol;
+    let (native_sol_account, _) = get_native_sol_address(owner);
+    let trader_base_account: &Pubkey = if native_sol && *base_mint == native_mint::id() {
+        &native_sol_account
+    } else {
+        trader_base_account
+    };
+    let trader_quote_account: &Pubkey = if native_sol && *quote_mint == native_mint::id() {
+        &native_sol_account
+    } else {
+        trader_quote_account
+    };
     let mut account_metas: Vec<AccountMeta> = vec![
-        AccountMeta::new_readonly(*payer, true),
-        AccountMeta::new_readonly(*owner, true),
+        AccountMeta {
+            pubkey: *payer,
+            is_signer: true,
+            is_writable: native_sol,
+        },
+        AccountMeta {
+            pubkey: *owner,
+            is_signer: true,
+            is_writable: native_sol,
+        },
         AccountMeta::new(*market, false),
         AccountMeta::new_readonly(solana_program::system_program::id(), false),
         AccountMeta::new(*trader_base_account, false),
@@ -38,17 +92,21 @@ pub fn swap_v2_instruction(
         AccountMeta::new(vault_quote_account, false),
         AccountMeta::new_readonly(token_program_base, false),
     ];
-    if token_program_base == spl_token_2022::id() {
+    if token_program_base == spl_token_2022::id() || native_sol {
         account_metas.push(AccountMeta::new_readonly(*base_mint, false))
     }
-    if token_program_base != token_program_quote {
+    if token_program_base != token_program_quote || native_sol {
         account_metas.push(AccountMeta::new_readonly(token_program_quote, false))
     }
-    if token_program_quote == spl_token_2022::id() {
+    if token_program_quote == spl_token_2022::id() || native_sol {
         account_metas.push(AccountMeta::new(*quote_mint, false))
     }
     if include_global {
-        let global_mint: &Pubkey = if is_base_in { quote_mint } else { base_mint };
+        let global_mint: &Pubkey = if params.is_base_in {
+            quote_mint
+        } else {
+            base_mint
+        };
         let (global, _) = get_global_address(global_mint);
         let (global_vault, _) = get_global_vault_address(global_mint);
         account_metas.push(AccountMeta::new(global, false));
@@ -60,9 +118,7 @@ pub fn swap_v2_instruction(
         accounts: account_metas,
         data: [
             ManifestInstruction::Swap.to_vec(),
-            SwapParams::new(in_atoms, out_atoms, is_base_in, is_exact_in)
-                .try_to_vec()
-                .unwrap(),
+            params.try_to_vec().unwrap(),
         ]
         .concat(),
     }
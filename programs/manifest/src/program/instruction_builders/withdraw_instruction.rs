@@ -39,3 +39,34 @@ pub fn withdraw_instruction(
         .concat(),
     }
 }
+
+pub fn withdraw_to_instruction(
+    market: &Pubkey,
+    payer: &Pubkey,
+    mint: &Pubkey,
+    amount_atoms: u64,
+    recipient_token_account: &Pubkey,
+    token_program: Pubkey,
+    trader_index_hint: Option<DataIndex>,
+) -> Instruction {
+    let (vault_address, _) = get_vault_address(market, mint);
+
+    Instruction {
+        program_id: crate::id(),
+        accounts: vec![
+            AccountMeta::new_readonly(*payer, true),
+            AccountMeta::new(*market, false),
+            AccountMeta::new(*recipient_token_account, false),
+            AccountMeta::new(vault_address, false),
+            AccountMeta::new_readonly(token_program, false),
+            AccountMeta::new_readonly(*mint, false),
+        ],
+        data: [
+            ManifestInstruction::WithdrawTo.to_vec(),
+            WithdrawParams::new(amount_atoms, trader_index_hint)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    }
+}
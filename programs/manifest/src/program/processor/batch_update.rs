@@ -1,20 +1,23 @@
-use std::cell::RefMut;
+use std::cell::{Ref, RefMut};
 
 use crate::{
-    logs::{emit_stack, CancelOrderLog, PlaceOrderLog},
-    program::get_trader_index_with_hint,
+    logs::{emit_stack, CancelOrderLog, PlaceOrderLog, PlaceOrderLogV2},
+    program::{
+        batch_expand_market, get_dynamic_account, get_trader_index_with_hint_allow_delegate,
+    },
     quantities::{BaseAtoms, PriceConversionError, QuoteAtomsPerBaseAtom, WrapperU64},
     require,
     state::{
         utils::{get_now_slot, try_to_pay_all_global_gas_prepayment},
-        AddOrderToMarketArgs, AddOrderToMarketResult, MarketRefMut, OrderType, RestingOrder,
-        MARKET_BLOCK_SIZE,
+        AddOrderToMarketArgs, AddOrderToMarketResult, EventQueueFixed, MarketFixed, MarketRef,
+        MarketRefMut, OrderType, RestingOrder, ReversePriceBounds, SelfTradeBehavior,
+        MARKET_BLOCK_SIZE, MAX_ICEBERG_DISPLAY_BASE_ATOMS,
     },
-    validation::loaders::BatchUpdateContext,
+    validation::{loaders::BatchUpdateContext, ManifestAccountInfo, Signer},
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 
-use hypertree::{get_helper, trace, DataIndex, PodBool, RBNode};
+use hypertree::{get_helper, trace, DataIndex, PodBool, RBNode, NIL};
 use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
     pubkey::Pubkey,
@@ -34,6 +37,11 @@ use {
 pub struct CancelOrderParams {
     order_sequence_number: u64,
     order_index_hint: Option<DataIndex>,
+    /// When set, the order is found by client_order_id and
+    /// order_sequence_number is ignored. Not part of the legacy layout.
+    /// Serialized after the orders by BatchUpdateParams.
+    #[borsh_skip]
+    client_order_id: Option<u64>,
 }
 
 impl CancelOrderParams {
@@ -41,12 +49,24 @@ impl CancelOrderParams {
         CancelOrderParams {
             order_sequence_number,
             order_index_hint: None,
+            client_order_id: None,
         }
     }
     pub fn new_with_hint(order_sequence_number: u64, order_index_hint: Option<DataIndex>) -> Self {
         CancelOrderParams {
             order_sequence_number,
             order_index_hint,
+            client_order_id: None,
+        }
+    }
+    pub fn new_with_client_order_id(
+        client_order_id: u64,
+        order_index_hint: Option<DataIndex>,
+    ) -> Self {
+        CancelOrderParams {
+            order_sequence_number: 0,
+            order_index_hint,
+            client_order_id: Some(client_order_id),
         }
     }
     pub fn order_sequence_number(&self) -> u64 {
@@ -55,6 +75,9 @@ impl CancelOrderParams {
     pub fn order_index_hint(&self) -> Option<DataIndex> {
         self.order_index_hint
     }
+    pub fn client_order_id(&self) -> Option<u64> {
+        self.client_order_id
+    }
 }
 
 #[derive(Debug, BorshDeserialize, BorshSerialize, Clone)]
@@ -65,6 +88,16 @@ pub struct PlaceOrderParams {
     is_bid: bool,
     last_valid_slot: u32,
     order_type: OrderType,
+    // Not part of the legacy layout. Serialized after the orders by
+    // BatchUpdateParams so that payloads from older clients still decode.
+    #[borsh_skip]
+    reverse_price_bounds: ReversePriceBounds,
+    #[borsh_skip]
+    iceberg_display_base_atoms: u64,
+    #[borsh_skip]
+    client_order_id: u64,
+    #[borsh_skip]
+    self_trade_behavior: SelfTradeBehavior,
 }
 
 impl PlaceOrderParams {
@@ -83,8 +116,28 @@ impl PlaceOrderParams {
             is_bid,
             order_type,
             last_valid_slot,
+            self_trade_behavior: SelfTradeBehavior::Allow,
+            client_order_id: 0,
+            reverse_price_bounds: ReversePriceBounds::default(),
+            iceberg_display_base_atoms: 0,
         }
     }
+    pub fn with_self_trade_behavior(mut self, self_trade_behavior: SelfTradeBehavior) -> Self {
+        self.self_trade_behavior = self_trade_behavior;
+        self
+    }
+    pub fn with_client_order_id(mut self, client_order_id: u64) -> Self {
+        self.client_order_id = client_order_id;
+        self
+    }
+    pub fn with_reverse_price_bounds(mut self, reverse_price_bounds: ReversePriceBounds) -> Self {
+        self.reverse_price_bounds = reverse_price_bounds;
+        self
+    }
+    pub fn with_iceberg_display_base_atoms(mut self, iceberg_display_base_atoms: u64) -> Self {
+        self.iceberg_display_base_atoms = iceberg_display_base_atoms;
+        self
+    }
     pub fn base_atoms(&self) -> u64 {
         self.base_atoms
     }
@@ -107,9 +160,22 @@ impl PlaceOrderParams {
     pub fn order_type(&self) -> OrderType {
         self.order_type
     }
+    pub fn self_trade_behavior(&self) -> SelfTradeBehavior {
+        self.self_trade_behavior
+    }
+    pub fn client_order_id(&self) -> u64 {
+        self.client_order_id
+    }
+    pub fn reverse_price_bounds(&self) -> ReversePriceBounds {
+        self.reverse_price_bounds
+    }
+    pub fn iceberg_display_base_atoms(&self) -> u64 {
+        self.iceberg_display_base_atoms
+    }
 }
 
-#[derive(BorshDeserialize, BorshSerialize)]
+#[derive(BorshDeserialize)]
+#[cfg_attr(feature = "certora", derive(BorshSerialize))]
 pub struct BatchUpdateParams {
     /// Optional hint for what index the trader's ClaimedSeat is at.
     pub trader_index_hint: Option<DataIndex>,
@@ -137,8 +203,141 @@ impl BatchUpdateParams {
             orders,
         }
     }
+
+    /// Reads the legacy layout followed by optional trailing sections. In
+    /// order these are reverse price bounds, iceberg display sizes and client
+    /// order ids with one entry per order, then client order ids with one
+    /// entry per cancel and self trade behaviors with one entry per order. A
+    /// section is only present when all the ones before it are.
+    #[cfg(not(feature = "certora"))]
+    pub fn try_from_versioned_slice(data: &[u8]) -> Result<Self, ProgramError> {
+        let buf: &mut &[u8] = &mut &data[..];
+        let mut params: BatchUpdateParams = BatchUpdateParams::deserialize(buf)?;
+        if !buf.is_empty() {
+            let reverse_price_bounds: Vec<ReversePriceBounds> = Vec::deserialize(buf)?;
+            if reverse_price_bounds.len() != params.orders.len() {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            for (order, bounds) in params.orders.iter_mut().zip(reverse_price_bounds) {
+                order.reverse_price_bounds = bounds;
+            }
+        }
+        if !buf.is_empty() {
+            let iceberg_display_base_atoms: Vec<u64> = Vec::deserialize(buf)?;
+            if iceberg_display_base_atoms.len() != params.orders.len() {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            for (order, display) in params.orders.iter_mut().zip(iceberg_display_base_atoms) {
+                order.iceberg_display_base_atoms = display;
+            }
+        }
+        if !buf.is_empty() {
+            let client_order_ids: Vec<u64> = Vec::deserialize(buf)?;
+            if client_order_ids.len() != params.orders.len() {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            for (order, client_order_id) in params.orders.iter_mut().zip(client_order_ids) {
+                order.client_order_id = client_order_id;
+            }
+        }
+        if !buf.is_empty() {
+            let client_order_ids: Vec<Option<u64>> = Vec::deserialize(buf)?;
+            if client_order_ids.len() != params.cancels.len() {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            for (cancel, client_order_id) in params.cancels.iter_mut().zip(client_order_ids) {
+                cancel.client_order_id = client_order_id;
+            }
+        }
+        if !buf.is_empty() {
+            let self_trade_behaviors: Vec<SelfTradeBehavior> = Vec::deserialize(buf)?;
+            if self_trade_behaviors.len() != params.orders.len() {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            for (order, self_trade_behavior) in params.orders.iter_mut().zip(self_trade_behaviors) {
+                order.self_trade_behavior = self_trade_behavior;
+            }
+        }
+        if !buf.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+        Ok(params)
+    }
+}
+
+#[cfg(not(feature = "certora"))]
+impl BorshSerialize for BatchUpdateParams {
+    fn serialize<W: std::io::Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.trader_index_hint.serialize(writer)?;
+        self.cancels.serialize(writer)?;
+        self.orders.serialize(writer)?;
+        // Only append the trailing sections when used so the data matches the
+        // legacy layout otherwise. Sections are positional, so every one
+        // before the last used section is written as well.
+        let sections_used: [bool; 5] = [
+            self.orders
+                .iter()
+                .any(|order| order.reverse_price_bounds.is_bounded()),
+            self.orders
+                .iter()
+                .any(|order| order.iceberg_display_base_atoms != 0),
+            self.orders.iter().any(|order| order.client_order_id != 0),
+            self.cancels
+                .iter()
+                .any(|cancel| cancel.client_order_id.is_some()),
+            self.orders
+                .iter()
+                .any(|order| order.self_trade_behavior != SelfTradeBehavior::Allow),
+        ];
+        let num_sections: usize = sections_used
+            .iter()
+            .rposition(|used| *used)
+            .map_or(0, |last_used| last_used + 1);
+        if num_sections > 0 {
+            self.orders
+                .iter()
+                .map(|order| order.reverse_price_bounds)
+                .collect::<Vec<ReversePriceBounds>>()
+                .serialize(writer)?;
+        }
+        if num_sections > 1 {
+            self.orders
+                .iter()
+                .map(|order| order.iceberg_display_base_atoms)
+                .collect::<Vec<u64>>()
+                .serialize(writer)?;
+        }
+        if num_sections > 2 {
+            self.orders
+                .iter()
+                .map(|order| order.client_order_id)
+                .collect::<Vec<u64>>()
+                .serialize(writer)?;
+        }
+        if num_sections > 3 {
+            self.cancels
+                .iter()
+                .map(|cancel| cancel.client_order_id)
+                .collect::<Vec<Option<u64>>>()
+                .serialize(writer)?;
+        }
+        if num_sections > 4 {
+            self.orders
+                .iter()
+                .map(|order| order.self_trade_behavior)
+                .collect::<Vec<SelfTradeBehavior>>()
+                .serialize(writer)?;
+        }
+        Ok(())
+    }
 }
 
+/// Vector of tuples of (order_sequence_number, DataIndex) for placed orders.
+#[cfg(not(feature = "certora"))]
+pub(crate) type BatchUpdateResult = Vec<(u64, DataIndex)>;
+#[cfg(feature = "certora")]
+pub(crate) type BatchUpdateResult = NoResizableVec<(u64, DataIndex)>;
+
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct BatchUpdateReturn {
     /// Vector of tuples of (order_sequence_number, DataIndex)
@@ -153,6 +352,8 @@ pub enum MarketDataTreeNodeType {
     #[default]
     ClaimedSeat = 1,
     RestingOrder = 2,
+    // Market config that does not fit in MarketFixed. Never in a tree.
+    Extension = 3,
 }
 
 pub(crate) fn process_batch_update(
@@ -160,8 +361,30 @@ pub(crate) fn process_batch_update(
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
+    process_batch_update_with_layout(program_id, accounts, data, false)
+}
+
+/// Same as BatchUpdate but the seat owner is a separate signer after the
+/// payer.
+pub(crate) fn process_batch_update_v2(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    process_batch_update_with_layout(program_id, accounts, data, true)
+}
+
+fn process_batch_update_with_layout(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+    has_separate_owner: bool,
+) -> ProgramResult {
+    #[cfg(not(feature = "certora"))]
+    let params: BatchUpdateParams = BatchUpdateParams::try_from_versioned_slice(data)?;
+    #[cfg(feature = "certora")]
     let params: BatchUpdateParams = BatchUpdateParams::try_from_slice(data)?;
-    process_batch_update_core(program_id, accounts, params)
+    process_batch_update_core(program_id, accounts, params, has_separate_owner)
 }
 
 #[cfg(not(feature = "certora"))]
@@ -170,11 +393,13 @@ fn batch_cancel_order(
     trader_index: DataIndex,
     order_sequence_number: u64,
     global_trade_accounts_opts: &[Option<GlobalTradeAccounts>; 2],
+    event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
 ) -> ProgramResult {
     dynamic_account.cancel_order(
         trader_index,
         order_sequence_number,
         &global_trade_accounts_opts,
+        event_queue_opt,
     )
 }
 
@@ -184,6 +409,7 @@ fn batch_cancel_order(
     trader_index: DataIndex,
     order_sequence_number: u64,
     global_trade_accounts_opts: &[Option<GlobalTradeAccounts>; 2],
+    _event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
 ) -> ProgramResult {
     mock_cancel_order(
         &dynamic_account,
@@ -214,16 +440,84 @@ pub(crate) fn process_batch_update_core(
     _program_id: &Pubkey,
     accounts: &[AccountInfo],
     params: BatchUpdateParams,
+    has_separate_owner: bool,
 ) -> ProgramResult {
-    let batch_update_context: BatchUpdateContext = BatchUpdateContext::load(accounts)?;
+    let batch_update_context: BatchUpdateContext =
+        BatchUpdateContext::load(accounts, has_separate_owner)?;
 
     let BatchUpdateContext {
         market,
         payer,
+        owner,
         global_trade_accounts_opts,
+        event_queue_opt,
         ..
     } = batch_update_context;
 
+    expand_market_for_orders(&payer, &market, params.orders.len())?;
+
+    let result: BatchUpdateResult = {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        // Trader differs from the owner when a seat delegate is trading.
+        let (trader_index, trader) = get_trader_index_with_hint_allow_delegate(
+            params.trader_index_hint,
+            &dynamic_account,
+            &owner,
+        )?;
+        batch_update_on_market(
+            &mut dynamic_account,
+            market.key,
+            payer.key,
+            owner.key,
+            trader_index,
+            trader,
+            &global_trade_accounts_opts,
+            &event_queue_opt,
+            params,
+        )?
+    };
+    expand_market_if_needed(&payer, &market)?;
+    set_batch_update_return_data(result);
+
+    Ok(())
+}
+
+/// Expands the market so that every order in a batch update has a block
+/// without releasing the market borrow in between, plus the usual spare.
+pub(crate) fn expand_market_for_orders<'a, 'info>(
+    payer: &Signer<'a, 'info>,
+    market: &ManifestAccountInfo<'a, 'info, MarketFixed>,
+    num_orders: usize,
+) -> ProgramResult {
+    let blocks_missing: u32 = {
+        let market_data: &Ref<&mut [u8]> = &market.try_borrow_data()?;
+        let dynamic_account: MarketRef = get_dynamic_account(market_data);
+        dynamic_account
+            .free_blocks_short_of_n(num_orders as u32 + 1)
+            .unwrap_or(0)
+    };
+    if blocks_missing > 0 {
+        batch_expand_market(payer, market, blocks_missing)?;
+    }
+    Ok(())
+}
+
+/// Runs the cancels and then the places of a batch update against an already
+/// borrowed market for the given seat. The market has to be expanded for the
+/// orders beforehand. Returns the placed orders.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn batch_update_on_market<'a, 'info>(
+    dynamic_account: &mut MarketRefMut,
+    market: &Pubkey,
+    payer: &Pubkey,
+    owner: &Pubkey,
+    trader_index: DataIndex,
+    trader: Pubkey,
+    global_trade_accounts_opts: &[Option<GlobalTradeAccounts<'a, 'info>>; 2],
+    event_queue_opt: &Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
+    params: BatchUpdateParams,
+) -> Result<BatchUpdateResult, ProgramError> {
     let BatchUpdateParams {
         trader_index_hint,
         cancels,
@@ -234,75 +528,89 @@ pub(crate) fn process_batch_update_core(
 
     trace!("batch_update trader_index_hint:{trader_index_hint:?} cancels:{cancels:?} orders:{orders:?}");
 
-    let trader_index: DataIndex = {
-        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
-
-        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
-        let trader_index: DataIndex =
-            get_trader_index_with_hint(trader_index_hint, &dynamic_account, &payer)?;
-
-        for cancel_order_params in cancels {
-            // Hinted is preferred because that is O(1) to find and O(log n) to
-            // remove. Without the hint, we lookup by order_sequence_number and
-            // that is O(n) lookup and O(log n) delete.
-            match cancel_order_params.order_index_hint() {
+    for cancel_order_params in cancels {
+        dynamic_account.assert_market_not_paused()?;
+        // Hinted is preferred because that is O(1) to find and O(log n) to
+        // remove. Without the hint, we lookup by order_sequence_number or
+        // client_order_id and that is O(n) lookup and O(log n) delete.
+        let order_sequence_number: u64 = match cancel_order_params.order_index_hint() {
+            None => match cancel_order_params.client_order_id() {
                 None => {
                     // Cancels must succeed otherwise we fail the tx.
                     batch_cancel_order(
-                        &mut dynamic_account,
+                        dynamic_account,
                         trader_index,
                         cancel_order_params.order_sequence_number(),
                         &global_trade_accounts_opts,
+                        &event_queue_opt,
                     )?;
+                    cancel_order_params.order_sequence_number()
                 }
-                Some(hinted_cancel_index) => {
-                    // Simple sanity check on the hint given. Make sure that it
-                    // aligns with block boundaries. We do a check that it is an
-                    // order owned by the payer inside the handler.
-                    require!(
-                        hinted_cancel_index % (MARKET_BLOCK_SIZE as DataIndex) == 0,
-                        crate::program::ManifestError::WrongIndexHintParams,
-                        "Invalid cancel hint index {}",
-                        hinted_cancel_index,
-                    )?;
-                    require!(
-                        get_helper::<RBNode<RestingOrder>>(
-                            &dynamic_account.dynamic,
-                            hinted_cancel_index,
-                        )
-                        .get_payload_type()
-                            == MarketDataTreeNodeType::RestingOrder as u8,
-                        crate::program::ManifestError::WrongIndexHintParams,
-                        "Invalid cancel hint index {}",
-                        hinted_cancel_index,
-                    )?;
-                    let order: &RestingOrder =
-                        dynamic_account.get_order_by_index(hinted_cancel_index);
-                    require!(
-                        trader_index == order.get_trader_index(),
-                        crate::program::ManifestError::WrongIndexHintParams,
-                        "Invalid cancel hint index {}",
+                Some(client_order_id) => dynamic_account.cancel_order_by_client_order_id(
+                    trader_index,
+                    client_order_id,
+                    &global_trade_accounts_opts,
+                    &event_queue_opt,
+                )?,
+            },
+            Some(hinted_cancel_index) => {
+                // Simple sanity check on the hint given. Make sure that it
+                // aligns with block boundaries. We do a check that it is an
+                // order owned by the payer inside the handler.
+                require!(
+                    hinted_cancel_index % (MARKET_BLOCK_SIZE as DataIndex) == 0,
+                    crate::program::ManifestError::WrongIndexHintParams,
+                    "Invalid cancel hint index {}",
+                    hinted_cancel_index,
+                )?;
+                require!(
+                    get_helper::<RBNode<RestingOrder>>(
+                        &dynamic_account.dynamic,
                         hinted_cancel_index,
-                    )?;
-                    require!(
+                    )
+                    .get_payload_type()
+                        == MarketDataTreeNodeType::RestingOrder as u8,
+                    crate::program::ManifestError::WrongIndexHintParams,
+                    "Invalid cancel hint index {}",
+                    hinted_cancel_index,
+                )?;
+                let order: &RestingOrder = dynamic_account.get_order_by_index(hinted_cancel_index);
+                require!(
+                    trader_index == order.get_trader_index(),
+                    crate::program::ManifestError::WrongIndexHintParams,
+                    "Invalid cancel hint index {}",
+                    hinted_cancel_index,
+                )?;
+                match cancel_order_params.client_order_id() {
+                    None => require!(
                         cancel_order_params.order_sequence_number() == order.get_sequence_number(),
                         crate::program::ManifestError::WrongIndexHintParams,
                         "Invalid cancel hint sequence number index {}",
                         hinted_cancel_index,
-                    )?;
-                    dynamic_account
-                        .cancel_order_by_index(hinted_cancel_index, &global_trade_accounts_opts)?;
-                }
-            };
-
-            emit_stack(CancelOrderLog {
-                market: *market.key,
-                trader: *payer.key,
-                order_sequence_number: cancel_order_params.order_sequence_number(),
-            })?;
-        }
-        trader_index
-    };
+                    )?,
+                    Some(client_order_id) => require!(
+                        client_order_id == order.get_client_order_id(),
+                        crate::program::ManifestError::WrongIndexHintParams,
+                        "Invalid cancel hint client order id index {}",
+                        hinted_cancel_index,
+                    )?,
+                };
+                let order_sequence_number: u64 = order.get_sequence_number();
+                dynamic_account.cancel_order_by_index(
+                    hinted_cancel_index,
+                    &global_trade_accounts_opts,
+                    &event_queue_opt,
+                )?;
+                order_sequence_number
+            }
+        };
+
+        emit_stack(CancelOrderLog {
+            market: *market,
+            trader,
+            order_sequence_number,
+        })?;
+    }
 
     // Formal verification does not cover global gas prepayment.
     #[cfg(not(feature = "certora"))]
@@ -312,32 +620,62 @@ pub(crate) fn process_batch_update_core(
 
     // Result is a vector of (order_sequence_number, data_index)
     #[cfg(not(feature = "certora"))]
-    let mut result: Vec<(u64, DataIndex)> = Vec::with_capacity(orders.len());
+    let mut result: BatchUpdateResult = Vec::with_capacity(orders.len());
     #[cfg(feature = "certora")]
-    let mut result = NoResizableVec::<(u64, DataIndex)>::new(10);
+    let mut result: BatchUpdateResult = NoResizableVec::<(u64, DataIndex)>::new(10);
     for place_order_params in orders {
         {
             let base_atoms: BaseAtoms = BaseAtoms::new(place_order_params.base_atoms());
             let price: QuoteAtomsPerBaseAtom = place_order_params.try_price()?;
             let order_type: OrderType = place_order_params.order_type();
             let last_valid_slot: u32 = place_order_params.last_valid_slot();
-
-            // Need to reborrow every iteration so we can borrow later for expanding.
-            let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
-            let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+            let reverse_price_bounds: ReversePriceBounds =
+                place_order_params.reverse_price_bounds();
+            if order_type.is_reversible() && reverse_price_bounds.is_bounded() {
+                require!(
+                    reverse_price_bounds.contains(price)?,
+                    crate::program::ManifestError::InvalidPlaceOrderFromWalletParams,
+                    "Reverse order price {} is outside its bounds",
+                    price,
+                )?;
+            }
+            // Global orders are backed by the owner global seat, which is not
+            // the trader when a delegate is trading.
+            require!(
+                trader == *owner || order_type != OrderType::Global,
+                crate::program::ManifestError::InvalidSeatDelegate,
+                "Seat delegates cannot place global orders",
+            )?;
+            let iceberg_display_base_atoms: u64 = place_order_params.iceberg_display_base_atoms();
+            if order_type == OrderType::Iceberg {
+                require!(
+                    iceberg_display_base_atoms > 0
+                        && iceberg_display_base_atoms <= MAX_ICEBERG_DISPLAY_BASE_ATOMS,
+                    crate::program::ManifestError::InvalidPlaceOrderFromWalletParams,
+                    "Invalid iceberg display size {}",
+                    iceberg_display_base_atoms,
+                )?;
+            }
+
+            dynamic_account.assert_market_active()?;
 
             let add_order_to_market_result: AddOrderToMarketResult = batch_place_order(
-                &mut dynamic_account,
+                dynamic_account,
                 AddOrderToMarketArgs {
-                    market: *market.key,
+                    market: *market,
                     trader_index,
                     num_base_atoms: base_atoms,
                     price,
                     is_bid: place_order_params.is_bid(),
                     last_valid_slot,
                     order_type,
-                    global_trade_accounts_opts: &global_trade_accounts_opts,
+                    global_trade_accounts_opts,
                     current_slot,
+                    self_trade_behavior: place_order_params.self_trade_behavior(),
+                    client_order_id: place_order_params.client_order_id(),
+                    reverse_price_bounds,
+                    iceberg_display_base_atoms: BaseAtoms::new(iceberg_display_base_atoms),
+                    event_queue_opt,
                 },
             )?;
 
@@ -347,34 +685,188 @@ pub(crate) fn process_batch_update_core(
                 ..
             } = add_order_to_market_result;
 
-            emit_stack(PlaceOrderLog {
-                market: *market.key,
-                trader: *payer.key,
-                base_atoms,
-                price,
-                order_type,
-                is_bid: PodBool::from(place_order_params.is_bid()),
-                _padding: [0; 6],
-                order_sequence_number,
-                order_index,
-                last_valid_slot,
-            })?;
+            // The V2 log carries the payer, the client order id and the final
+            // price. Keep the original log otherwise for existing indexers.
+            if order_type == OrderType::PostOnlySlide
+                || place_order_params.client_order_id() != 0
+                || payer != owner
+            {
+                // The order may have been repriced, so log where it actually
+                // rests.
+                let final_price: QuoteAtomsPerBaseAtom = if order_index != NIL {
+                    dynamic_account.get_order_by_index(order_index).get_price()
+                } else {
+                    price
+                };
+                emit_stack(PlaceOrderLogV2 {
+                    market: *market,
+                    trader,
+                    payer: *payer,
+                    base_atoms,
+                    price: final_price,
+                    order_type,
+                    is_bid: PodBool::from(place_order_params.is_bid()),
+                    _padding: [0; 6],
+                    order_sequence_number,
+                    order_index,
+                    last_valid_slot,
+                    client_order_id: place_order_params.client_order_id(),
+                })?;
+            } else {
+                emit_stack(PlaceOrderLog {
+                    market: *market,
+                    trader,
+                    base_atoms,
+                    price,
+                    order_type,
+                    is_bid: PodBool::from(place_order_params.is_bid()),
+                    _padding: [0; 6],
+                    order_sequence_number,
+                    order_index,
+                    last_valid_slot,
+                })?;
+            }
             result.push((order_sequence_number, order_index));
         }
-        expand_market_if_needed(&payer, &market)?;
     }
 
-    // Formal verification does not cover return values.
-    #[cfg(not(feature = "certora"))]
-    {
-        let mut buffer: Vec<u8> = Vec::with_capacity(
-            std::mem::size_of::<BatchUpdateReturn>()
-                + result.len() * 2 * std::mem::size_of::<u64>(),
+    Ok(result)
+}
+
+#[cfg(not(feature = "certora"))]
+pub(crate) fn set_batch_update_return_data(result: BatchUpdateResult) {
+    let mut buffer: Vec<u8> = Vec::with_capacity(
+        std::mem::size_of::<BatchUpdateReturn>() + result.len() * 2 * std::mem::size_of::<u64>(),
+    );
+    let return_data: BatchUpdateReturn = BatchUpdateReturn { orders: result };
+    return_data.serialize(&mut buffer).unwrap();
+    solana_program::program::set_return_data(&buffer[..]);
+}
+
+// Formal verification does not cover return values.
+#[cfg(feature = "certora")]
+pub(crate) fn set_batch_update_return_data(_result: BatchUpdateResult) {}
+
+#[cfg(all(test, not(feature = "certora")))]
+mod test {
+    use super::*;
+
+    // Layout from before any of the trailing sections existed.
+    #[derive(BorshSerialize)]
+    struct LegacyCancelOrderParams {
+        order_sequence_number: u64,
+        order_index_hint: Option<DataIndex>,
+    }
+
+    #[derive(BorshSerialize)]
+    struct LegacyPlaceOrderParams {
+        base_atoms: u64,
+        price_mantissa: u32,
+        price_exponent: i8,
+        is_bid: bool,
+        last_valid_slot: u32,
+        order_type: OrderType,
+    }
+
+    #[derive(BorshSerialize)]
+    struct LegacyBatchUpdateParams {
+        trader_index_hint: Option<DataIndex>,
+        cancels: Vec<LegacyCancelOrderParams>,
+        orders: Vec<LegacyPlaceOrderParams>,
+    }
+
+    fn legacy_params() -> LegacyBatchUpdateParams {
+        LegacyBatchUpdateParams {
+            trader_index_hint: Some(1),
+            cancels: vec![LegacyCancelOrderParams {
+                order_sequence_number: 2,
+                order_index_hint: Some(3),
+            }],
+            orders: vec![LegacyPlaceOrderParams {
+                base_atoms: 4,
+                price_mantissa: 5,
+                price_exponent: -6,
+                is_bid: true,
+                last_valid_slot: 7,
+                order_type: OrderType::PostOnly,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_decode_legacy() {
+        let data: Vec<u8> = legacy_params().try_to_vec().unwrap();
+        let params: BatchUpdateParams = BatchUpdateParams::try_from_versioned_slice(&data).unwrap();
+
+        assert_eq!(params.trader_index_hint, Some(1));
+        assert_eq!(params.cancels.len(), 1);
+        assert_eq!(params.cancels[0].order_sequence_number(), 2);
+        assert_eq!(params.cancels[0].order_index_hint(), Some(3));
+        assert_eq!(params.cancels[0].client_order_id(), None);
+        assert_eq!(params.orders.len(), 1);
+        let order: &PlaceOrderParams = &params.orders[0];
+        assert_eq!(order.base_atoms(), 4);
+        assert!(order.is_bid());
+        assert_eq!(order.last_valid_slot(), 7);
+        assert_eq!(order.order_type(), OrderType::PostOnly);
+        assert!(!order.reverse_price_bounds().is_bounded());
+        assert_eq!(order.iceberg_display_base_atoms(), 0);
+        assert_eq!(order.client_order_id(), 0);
+        assert_eq!(order.self_trade_behavior(), SelfTradeBehavior::Allow);
+    }
+
+    #[test]
+    fn test_encode_matches_legacy() {
+        let params: BatchUpdateParams = BatchUpdateParams::new(
+            Some(1),
+            vec![CancelOrderParams::new_with_hint(2, Some(3))],
+            vec![PlaceOrderParams::new(
+                4,
+                5,
+                -6,
+                true,
+                OrderType::PostOnly,
+                7,
+            )],
+        );
+        assert_eq!(
+            params.try_to_vec().unwrap(),
+            legacy_params().try_to_vec().unwrap()
         );
-        let return_data: BatchUpdateReturn = BatchUpdateReturn { orders: result };
-        return_data.serialize(&mut buffer).unwrap();
-        solana_program::program::set_return_data(&buffer[..]);
     }
 
-    Ok(())
+    #[test]
+    fn test_round_trip_trailing_sections() {
+        let params: BatchUpdateParams = BatchUpdateParams::new(
+            None,
+            vec![
+                CancelOrderParams::new(1),
+                CancelOrderParams::new_with_client_order_id(2, None),
+            ],
+            vec![
+                PlaceOrderParams::new(3, 4, 0, false, OrderType::Limit, 0).with_client_order_id(5),
+                PlaceOrderParams::new(6, 7, 0, true, OrderType::Limit, 0)
+                    .with_self_trade_behavior(SelfTradeBehavior::CancelMaker),
+            ],
+        );
+        let data: Vec<u8> = params.try_to_vec().unwrap();
+        let decoded: BatchUpdateParams =
+            BatchUpdateParams::try_from_versioned_slice(&data).unwrap();
+        assert_eq!(decoded.cancels[0].client_order_id(), None);
+        assert_eq!(decoded.cancels[1].client_order_id(), Some(2));
+        assert_eq!(decoded.orders[0].client_order_id(), 5);
+        assert_eq!(
+            decoded.orders[0].self_trade_behavior(),
+            SelfTradeBehavior::Allow
+        );
+        assert_eq!(
+            decoded.orders[1].self_trade_behavior(),
+            SelfTradeBehavior::CancelMaker
+        );
+
+        // A section that does not match the number of orders is rejected.
+        let mut bad_data: Vec<u8> = legacy_params().try_to_vec().unwrap();
+        bad_data.extend(Vec::<ReversePriceBounds>::new().try_to_vec().unwrap());
+        assert!(BatchUpdateParams::try_from_versioned_slice(&bad_data).is_err());
+    }
 }
@@ -0,0 +1,85 @@
+use std::cell::RefMut;
+
+use crate::{
+    logs::{emit_stack, CancelOrderLog},
+    program::get_trader_index_with_hint_allow_delegate,
+    state::{MarketRefMut, OrderType},
+    validation::loaders::BatchUpdateContext,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{trace, DataIndex};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+use super::shared::get_mut_dynamic_account;
+
+#[derive(Debug, BorshDeserialize, BorshSerialize, Clone)]
+pub struct CancelAllParams {
+    /// Optional hint for what index the trader's ClaimedSeat is at.
+    pub trader_index_hint: Option<DataIndex>,
+    /// Only cancel bids when true or asks when false. Both sides when None.
+    pub is_bid: Option<bool>,
+    /// Only cancel orders of this type. All types when None.
+    pub order_type: Option<OrderType>,
+}
+
+impl CancelAllParams {
+    pub fn new(
+        trader_index_hint: Option<DataIndex>,
+        is_bid: Option<bool>,
+        order_type: Option<OrderType>,
+    ) -> Self {
+        CancelAllParams {
+            trader_index_hint,
+            is_bid,
+            order_type,
+        }
+    }
+}
+
+pub(crate) fn process_cancel_all(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    // Same accounts as BatchUpdate so that global gas prepayments can be
+    // returned to the payer.
+    let batch_update_context: BatchUpdateContext = BatchUpdateContext::load(accounts, false)?;
+    let BatchUpdateContext {
+        market,
+        owner,
+        global_trade_accounts_opts,
+        event_queue_opt,
+        ..
+    } = batch_update_context;
+
+    let CancelAllParams {
+        trader_index_hint,
+        is_bid,
+        order_type,
+    } = CancelAllParams::try_from_slice(data)?;
+    trace!("cancel_all trader_index_hint:{trader_index_hint:?} is_bid:{is_bid:?} order_type:{order_type:?}");
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+    dynamic_account.assert_market_not_paused()?;
+    let (trader_index, trader) =
+        get_trader_index_with_hint_allow_delegate(trader_index_hint, &dynamic_account, &owner)?;
+
+    let cancelled_orders: Vec<(u64, DataIndex)> = dynamic_account.cancel_all_orders(
+        trader_index,
+        is_bid,
+        order_type,
+        &global_trade_accounts_opts,
+        &event_queue_opt,
+    )?;
+
+    for (order_sequence_number, _) in cancelled_orders {
+        emit_stack(CancelOrderLog {
+            market: *market.key,
+            trader,
+            order_sequence_number,
+        })?;
+    }
+
+    Ok(())
+}
@@ -7,7 +7,7 @@ use crate::{
 };
 use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
 
-use super::shared::{expand_market_if_needed, get_mut_dynamic_account};
+use super::shared::{assert_gate_passed, expand_market_if_needed, get_mut_dynamic_account};
 
 #[cfg(feature = "certora")]
 use early_panic::early_panic;
@@ -19,7 +19,18 @@ pub(crate) fn process_claim_seat(
     _data: &[u8],
 ) -> ProgramResult {
     let claim_seat_context: ClaimSeatContext = ClaimSeatContext::load(accounts)?;
-    let ClaimSeatContext { market, payer, .. } = claim_seat_context;
+    let ClaimSeatContext {
+        market,
+        payer,
+        gate_credential_opt,
+        ..
+    } = claim_seat_context;
+
+    {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        assert_gate_passed(&dynamic_account, market.key, payer.key, gate_credential_opt)?;
+    }
 
     process_claim_seat_internal(&market, &payer)?;
 
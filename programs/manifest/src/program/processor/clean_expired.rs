@@ -0,0 +1,71 @@
+use std::cell::RefMut;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{trace, DataIndex};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+use crate::{
+    logs::{emit_stack, CancelOrderLog},
+    program::get_mut_dynamic_account,
+    state::{utils::get_now_slot, MarketRefMut},
+    validation::loaders::CleanExpiredContext,
+};
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct CleanExpiredParams {
+    /// Orders to check. Ones that are gone or not eligible are skipped.
+    pub order_indices: Vec<DataIndex>,
+    /// Also checks this many of the best orders on each side.
+    pub num_to_scan_per_side: u32,
+}
+
+impl CleanExpiredParams {
+    pub fn new(order_indices: Vec<DataIndex>, num_to_scan_per_side: u32) -> Self {
+        CleanExpiredParams {
+            order_indices,
+            num_to_scan_per_side,
+        }
+    }
+}
+
+pub(crate) fn process_clean_expired(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let clean_expired_context: CleanExpiredContext = CleanExpiredContext::load(accounts)?;
+    let CleanExpiredParams {
+        order_indices,
+        num_to_scan_per_side,
+    } = CleanExpiredParams::try_from_slice(data)?;
+    trace!(
+        "clean_expired order_indices:{order_indices:?} num_to_scan_per_side:{num_to_scan_per_side}"
+    );
+
+    let CleanExpiredContext {
+        market,
+        event_queue_opt,
+        ..
+    } = clean_expired_context;
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+
+    let removed_orders: Vec<(DataIndex, u64)> = dynamic_account.clean_expired_orders(
+        &order_indices,
+        num_to_scan_per_side,
+        get_now_slot(),
+        &event_queue_opt,
+    )?;
+
+    // Seats are never released, so the trader index still points at the owner.
+    for (trader_index, order_sequence_number) in removed_orders {
+        emit_stack(CancelOrderLog {
+            market: *market.key,
+            trader: *dynamic_account.get_trader_key_by_index(trader_index),
+            order_sequence_number,
+        })?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,50 @@
+use std::cell::RefMut;
+
+use crate::{
+    logs::{emit_stack, SeatDelegateLog},
+    program::{get_mut_dynamic_account, get_trader_index_with_hint},
+    state::MarketRefMut,
+    validation::loaders::SeatDelegateContext,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{trace, DataIndex};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ClearSeatDelegateParams {
+    /// Optional hint for what index the trader's ClaimedSeat is at.
+    pub trader_index_hint: Option<DataIndex>,
+}
+
+impl ClearSeatDelegateParams {
+    pub fn new(trader_index_hint: Option<DataIndex>) -> Self {
+        ClearSeatDelegateParams { trader_index_hint }
+    }
+}
+
+pub(crate) fn process_clear_seat_delegate(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let seat_delegate_context: SeatDelegateContext = SeatDelegateContext::load(accounts)?;
+    let ClearSeatDelegateParams { trader_index_hint } =
+        ClearSeatDelegateParams::try_from_slice(data)?;
+    trace!("clear_seat_delegate trader_index_hint:{trader_index_hint:?}");
+
+    let SeatDelegateContext { owner, market, .. } = seat_delegate_context;
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+    let trader_index: DataIndex =
+        get_trader_index_with_hint(trader_index_hint, &dynamic_account, &owner)?;
+    dynamic_account.clear_seat_delegate(trader_index)?;
+
+    emit_stack(SeatDelegateLog {
+        market: *market.key,
+        trader: *owner.key,
+        delegate: Pubkey::default(),
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,88 @@
+use std::cell::RefMut;
+
+use super::withdraw::{
+    spl_token_2022_transfer_from_vault_to_trader_fixed, spl_token_transfer_from_vault_to_trader,
+};
+use crate::{
+    logs::{emit_stack, CollectFeesLog},
+    program::get_mut_dynamic_account,
+    state::MarketRefMut,
+    validation::loaders::CollectFeesContext,
+};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+pub(crate) fn process_collect_fees(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let collect_fees_context: CollectFeesContext = CollectFeesContext::load(accounts)?;
+
+    let CollectFeesContext {
+        market,
+        payer: _payer,
+        fee_recipient_token,
+        vault,
+        token_program,
+        mint,
+    } = collect_fees_context;
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+
+    // Validation verifies that the mint is either base or quote.
+    let is_base: bool =
+        &fee_recipient_token.try_borrow_data()?[0..32] == dynamic_account.get_base_mint().as_ref();
+
+    let amount_atoms: u64 = dynamic_account.collect_fees(is_base)?;
+
+    let mint_key: &Pubkey = if is_base {
+        dynamic_account.get_base_mint()
+    } else {
+        dynamic_account.get_quote_mint()
+    };
+
+    let bump: u8 = if is_base {
+        dynamic_account.fixed.get_base_vault_bump()
+    } else {
+        dynamic_account.fixed.get_quote_vault_bump()
+    };
+
+    if *vault.owner == spl_token_2022::id() {
+        spl_token_2022_transfer_from_vault_to_trader_fixed(
+            &token_program,
+            Some(mint),
+            mint_key,
+            &vault,
+            &fee_recipient_token,
+            amount_atoms,
+            if is_base {
+                dynamic_account.fixed.get_base_mint_decimals()
+            } else {
+                dynamic_account.fixed.get_quote_mint_decimals()
+            },
+            market.key,
+            bump,
+            &[],
+        )?;
+    } else {
+        spl_token_transfer_from_vault_to_trader(
+            &token_program,
+            &vault,
+            &fee_recipient_token,
+            amount_atoms,
+            market.key,
+            bump,
+            mint_key,
+        )?;
+    }
+
+    emit_stack(CollectFeesLog {
+        market: *market.key,
+        fee_recipient: fee_recipient_token.get_owner(),
+        mint: *mint_key,
+        amount_atoms,
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use std::cell::RefMut;
+
+use crate::{
+    program::get_mut_dynamic_account, state::EventQueueRefMut,
+    validation::loaders::ConsumeEventsContext,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::trace;
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ConsumeEventsParams {
+    /// Maximum number of events to consume, oldest first.
+    pub limit: u64,
+}
+
+impl ConsumeEventsParams {
+    pub fn new(limit: u64) -> Self {
+        ConsumeEventsParams { limit }
+    }
+}
+
+/// Permissionless crank that frees space in the event queue. Readers are
+/// expected to have processed the events before they are consumed.
+pub(crate) fn process_consume_events(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let consume_events_context: ConsumeEventsContext = ConsumeEventsContext::load(accounts)?;
+    let ConsumeEventsParams { limit } = ConsumeEventsParams::try_from_slice(data)?;
+    trace!("consume_events limit:{limit}");
+
+    let ConsumeEventsContext { event_queue } = consume_events_context;
+
+    let event_queue_data: &mut RefMut<&mut [u8]> = &mut event_queue.try_borrow_mut_data()?;
+    let mut dynamic_account: EventQueueRefMut = get_mut_dynamic_account(event_queue_data);
+    let num_consumed: u64 = dynamic_account.consume_events(limit);
+    trace!("consume_events consumed:{num_consumed}");
+
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use std::cell::RefMut;
+
+use crate::{
+    program::{expand_market_if_needed, get_mut_dynamic_account, ManifestError},
+    require,
+    state::{EventQueueFixed, MarketRefMut},
+    validation::loaders::CreateEventQueueContext,
+};
+use hypertree::{get_mut_helper, trace};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+/// Attaches an event queue to the market. The queue account is created by the
+/// client beforehand and its size sets the capacity.
+pub(crate) fn process_create_event_queue(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    _data: &[u8],
+) -> ProgramResult {
+    let create_event_queue_context: CreateEventQueueContext =
+        CreateEventQueueContext::load(accounts)?;
+    let CreateEventQueueContext {
+        authority,
+        market,
+        event_queue,
+        ..
+    } = create_event_queue_context;
+    trace!(
+        "create_event_queue market:{} event_queue:{}",
+        market.key,
+        event_queue.key
+    );
+
+    {
+        let capacity: u32 = EventQueueFixed::get_capacity_for_account_size(event_queue.data_len());
+        require!(
+            capacity > 0,
+            ManifestError::InvalidMarketParameters,
+            "Invalid event queue size {}",
+            event_queue.data_len(),
+        )?;
+        let event_queue_bytes: &mut [u8] = &mut event_queue.try_borrow_mut_data()?[..];
+        *get_mut_helper::<EventQueueFixed>(event_queue_bytes, 0_u32) =
+            EventQueueFixed::new_empty(market.key, capacity);
+    }
+
+    {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.init_event_queue_config(authority.key, event_queue.key)?;
+    }
+
+    // The config took the free block.
+    expand_market_if_needed(&authority, &market)?;
+
+    Ok(())
+}
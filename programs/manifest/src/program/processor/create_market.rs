@@ -1,13 +1,21 @@
-use std::{cell::Ref, mem::size_of};
+use std::{
+    cell::{Ref, RefMut},
+    mem::size_of,
+};
 
 use crate::{
-    logs::{emit_stack, CreateMarketLog},
-    program::{expand_market_if_needed, invoke},
+    logs::{emit_stack, CreateMarketLog, MarketAuthorityLog},
+    program::{expand_market_if_needed, get_mut_dynamic_account, invoke, ManifestError},
+    quantities::{BaseAtoms, QuoteAtomsPerBaseAtom, WrapperU64},
     require,
-    state::MarketFixed,
+    state::{
+        MarketAuthorityConfig, MarketFeeConfig, MarketFixed, MarketGateConfig, MarketRefMut,
+        MarketTradingConfig, FEE_DENOMINATOR,
+    },
     utils::create_account,
     validation::{get_vault_address, loaders::CreateMarketContext},
 };
+use borsh::{BorshDeserialize, BorshSerialize};
 use hypertree::{get_mut_helper, trace};
 use solana_program::{
     account_info::AccountInfo, entrypoint::ProgramResult, program_pack::Pack, pubkey::Pubkey,
@@ -22,12 +30,96 @@ use spl_token_2022::{
     state::{Account, Mint},
 };
 
+/// Optional params for CreateMarket. Markets created without instruction data
+/// have no fees and no tick, lot or minimum size.
+#[derive(BorshDeserialize, BorshSerialize, Default)]
+pub struct CreateMarketParams {
+    pub fee_recipient: Pubkey,
+    pub taker_fee_mantissa: u32,
+    pub maker_rebate_mantissa: u32,
+    pub tick_size_mantissa: u32,
+    pub tick_size_exponent: i8,
+    pub lot_size_base_atoms: u64,
+    pub min_base_atoms: u64,
+    pub market_authority: Option<Pubkey>,
+    /// Permissioned markets only let traders with a membership from the gate
+    /// authority claim seats and swap.
+    pub gate_authority: Option<Pubkey>,
+}
+
+impl CreateMarketParams {
+    pub fn with_fees(
+        mut self,
+        fee_recipient: Pubkey,
+        taker_fee_mantissa: u32,
+        maker_rebate_mantissa: u32,
+    ) -> Self {
+        self.fee_recipient = fee_recipient;
+        self.taker_fee_mantissa = taker_fee_mantissa;
+        self.maker_rebate_mantissa = maker_rebate_mantissa;
+        self
+    }
+
+    pub fn with_tick_size(mut self, tick_size_mantissa: u32, tick_size_exponent: i8) -> Self {
+        self.tick_size_mantissa = tick_size_mantissa;
+        self.tick_size_exponent = tick_size_exponent;
+        self
+    }
+
+    pub fn with_lot_size(mut self, lot_size_base_atoms: u64) -> Self {
+        self.lot_size_base_atoms = lot_size_base_atoms;
+        self
+    }
+
+    pub fn with_min_base_atoms(mut self, min_base_atoms: u64) -> Self {
+        self.min_base_atoms = min_base_atoms;
+        self
+    }
+
+    pub fn with_market_authority(mut self, market_authority: Pubkey) -> Self {
+        self.market_authority = Some(market_authority);
+        self
+    }
+
+    pub fn with_gate_authority(mut self, gate_authority: Pubkey) -> Self {
+        self.gate_authority = Some(gate_authority);
+        self
+    }
+}
+
 pub(crate) fn process_create_market(
     _program_id: &Pubkey,
     accounts: &[AccountInfo],
-    _data: &[u8],
+    data: &[u8],
 ) -> ProgramResult {
     trace!("process_create_market accs={accounts:?}");
+    let params: CreateMarketParams = if data.is_empty() {
+        CreateMarketParams::default()
+    } else {
+        CreateMarketParams::try_from_slice(data)?
+    };
+    let CreateMarketParams {
+        fee_recipient,
+        taker_fee_mantissa,
+        maker_rebate_mantissa,
+        tick_size_mantissa,
+        tick_size_exponent,
+        lot_size_base_atoms,
+        min_base_atoms,
+        market_authority,
+        gate_authority,
+    } = params;
+    require!(
+        maker_rebate_mantissa <= taker_fee_mantissa && taker_fee_mantissa as u64 <= FEE_DENOMINATOR,
+        ManifestError::InvalidMarketParameters,
+        "Invalid fees taker:{} maker rebate:{}",
+        taker_fee_mantissa,
+        maker_rebate_mantissa,
+    )?;
+    let tick_size: QuoteAtomsPerBaseAtom = QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(
+        tick_size_mantissa,
+        tick_size_exponent,
+    )?;
     let create_market_context: CreateMarketContext = CreateMarketContext::load(accounts)?;
 
     let CreateMarketContext {
@@ -44,7 +136,7 @@ pub(crate) fn process_create_market(
 
     require!(
         base_mint.info.key != quote_mint.info.key,
-        crate::program::ManifestError::InvalidMarketParameters,
+        ManifestError::InvalidMarketParameters,
         "Base and quote must be different",
     )?;
 
@@ -184,6 +276,48 @@ pub(crate) fn process_create_market(
         })?;
     }
 
+    if taker_fee_mantissa > 0 {
+        // The fee config takes a block for the life of the market.
+        expand_market_if_needed(&payer, &market)?;
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.init_fee_config(MarketFeeConfig::new(
+            fee_recipient,
+            taker_fee_mantissa,
+            maker_rebate_mantissa,
+        ))?;
+    }
+
+    if tick_size_mantissa > 0 || lot_size_base_atoms > 0 || min_base_atoms > 0 {
+        expand_market_if_needed(&payer, &market)?;
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.init_trading_config(MarketTradingConfig::new(
+            tick_size,
+            BaseAtoms::new(lot_size_base_atoms),
+            BaseAtoms::new(min_base_atoms),
+        ))?;
+    }
+
+    if let Some(market_authority) = market_authority {
+        expand_market_if_needed(&payer, &market)?;
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.init_authority_config(MarketAuthorityConfig::new(market_authority))?;
+        emit_stack(MarketAuthorityLog {
+            market: *market.key,
+            old_authority: Pubkey::default(),
+            new_authority: market_authority,
+        })?;
+    }
+
+    if let Some(gate_authority) = gate_authority {
+        expand_market_if_needed(&payer, &market)?;
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.init_gate_config(MarketGateConfig::new(gate_authority))?;
+    }
+
     // Leave a free block on the market so takers can use and leave it.
     expand_market_if_needed(&payer, &market)?;
 
@@ -1,7 +1,7 @@
 use std::cell::RefMut;
 
 use crate::{
-    logs::{emit_stack, DepositLog},
+    logs::{emit_stack, DepositForLog, DepositLog},
     state::MarketRefMut,
     validation::{
         loaders::DepositContext, MintAccountInfo, Signer, TokenAccountInfo, TokenProgram,
@@ -9,7 +9,10 @@ use crate::{
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use hypertree::DataIndex;
-use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
 
 use super::{get_trader_index_with_hint, shared::get_mut_dynamic_account};
 
@@ -33,6 +36,22 @@ impl DepositParams {
     }
 }
 
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct DepositForParams {
+    // Trader whose seat is credited. Needs to already have a seat.
+    pub trader: Pubkey,
+    pub amount_atoms: u64,
+}
+
+impl DepositForParams {
+    pub fn new(trader: Pubkey, amount_atoms: u64) -> Self {
+        DepositForParams {
+            trader,
+            amount_atoms,
+        }
+    }
+}
+
 pub(crate) fn process_deposit(
     program_id: &Pubkey,
     accounts: &[AccountInfo],
@@ -53,21 +72,90 @@ pub(crate) fn process_deposit_core(
         amount_atoms,
         trader_index_hint,
     } = params;
-    // Due to transfer fees, this might not be what you expect.
-    let mut deposited_amount_atoms: u64 = amount_atoms;
 
+    let market_data: &mut RefMut<&mut [u8]> = &mut deposit_context.market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+    dynamic_account.assert_market_active()?;
+
+    let (is_base, deposited_amount_atoms) =
+        transfer_from_payer_to_vault(&deposit_context, &dynamic_account, amount_atoms)?;
+
+    let DepositContext { market, payer, .. } = &deposit_context;
+    let trader_index: DataIndex =
+        get_trader_index_with_hint(trader_index_hint, &dynamic_account, payer)?;
+    dynamic_account.deposit(trader_index, deposited_amount_atoms, is_base)?;
+
+    emit_stack(DepositLog {
+        market: *market.key,
+        trader: *payer.key,
+        mint: if is_base {
+            *dynamic_account.get_base_mint()
+        } else {
+            *dynamic_account.get_quote_mint()
+        },
+        amount_atoms: deposited_amount_atoms,
+    })?;
+
+    Ok(())
+}
+
+/// Same as deposit, but the funds are credited to the seat of another trader.
+/// The payer signs for the token account, the trader does not need to sign.
+pub(crate) fn process_deposit_for(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let deposit_context: DepositContext = DepositContext::load(accounts)?;
+    let DepositForParams {
+        trader,
+        amount_atoms,
+    } = DepositForParams::try_from_slice(data)?;
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut deposit_context.market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+    dynamic_account.assert_market_active()?;
+
+    let (is_base, deposited_amount_atoms) =
+        transfer_from_payer_to_vault(&deposit_context, &dynamic_account, amount_atoms)?;
+
+    let DepositContext { market, payer, .. } = &deposit_context;
+    let trader_index: DataIndex = dynamic_account.get_trader_index(&trader);
+    dynamic_account.deposit(trader_index, deposited_amount_atoms, is_base)?;
+
+    emit_stack(DepositForLog {
+        market: *market.key,
+        payer: *payer.key,
+        trader,
+        mint: if is_base {
+            *dynamic_account.get_base_mint()
+        } else {
+            *dynamic_account.get_quote_mint()
+        },
+        amount_atoms: deposited_amount_atoms,
+    })?;
+
+    Ok(())
+}
+
+/// Moves amount_atoms from the payer token account into the vault. Returns
+/// whether it was base and the atoms that arrived, which can be less than
+/// amount_atoms because of token22 transfer fees.
+pub(crate) fn transfer_from_payer_to_vault(
+    deposit_context: &DepositContext,
+    dynamic_account: &MarketRefMut,
+    amount_atoms: u64,
+) -> Result<(bool, u64), ProgramError> {
     let DepositContext {
-        market,
         payer,
         trader_token,
         vault,
         token_program,
         mint,
+        transfer_hook_accounts,
+        ..
     } = deposit_context;
 
-    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
-    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
-
     // Validation already verifies that the mint is either base or quote.
     let is_base: bool =
         &trader_token.try_borrow_data()?[0..32] == dynamic_account.get_base_mint().as_ref();
@@ -75,54 +163,43 @@ pub(crate) fn process_deposit_core(
     if *vault.owner == spl_token_2022::id() {
         let before_vault_balance_atoms: u64 = vault.get_balance_atoms();
         spl_token_2022_transfer_from_trader_to_vault(
-            &token_program,
-            &trader_token,
-            Some(mint),
+            token_program,
+            trader_token,
+            Some(mint.clone()),
             if is_base {
                 dynamic_account.fixed.get_base_mint()
             } else {
                 dynamic_account.get_quote_mint()
             },
-            &vault,
-            &payer,
+            vault,
+            payer,
             amount_atoms,
             if is_base {
                 dynamic_account.fixed.get_base_mint_decimals()
             } else {
                 dynamic_account.fixed.get_quote_mint_decimals()
             },
+            transfer_hook_accounts,
         )?;
 
+        // Due to transfer fees, this might not be what you expect.
         let after_vault_balance_atoms: u64 = vault.get_balance_atoms();
-        deposited_amount_atoms = after_vault_balance_atoms
-            .checked_sub(before_vault_balance_atoms)
-            .unwrap();
+        Ok((
+            is_base,
+            after_vault_balance_atoms
+                .checked_sub(before_vault_balance_atoms)
+                .unwrap(),
+        ))
     } else {
         spl_token_transfer_from_trader_to_vault(
-            &token_program,
-            &trader_token,
-            &vault,
-            &payer,
+            token_program,
+            trader_token,
+            vault,
+            payer,
             amount_atoms,
         )?;
+        Ok((is_base, amount_atoms))
     }
-
-    let trader_index: DataIndex =
-        get_trader_index_with_hint(trader_index_hint, &dynamic_account, &payer)?;
-    dynamic_account.deposit(trader_index, deposited_amount_atoms, is_base)?;
-
-    emit_stack(DepositLog {
-        market: *market.key,
-        trader: *payer.key,
-        mint: if is_base {
-            *dynamic_account.get_base_mint()
-        } else {
-            *dynamic_account.get_quote_mint()
-        },
-        amount_atoms: deposited_amount_atoms,
-    })?;
-
-    Ok(())
 }
 
 /** Transfer from base (quote) trader to base (quote) vault using SPL Token **/
@@ -174,7 +251,23 @@ fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
     payer: &Signer<'a, 'info>,
     amount: u64,
     decimals: u8,
+    transfer_hook_accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
+    // Resolving hook accounts needs the mint extensions, so only pay for it
+    // when the caller passed some.
+    if !transfer_hook_accounts.is_empty() {
+        return spl_token_2022::onchain::invoke_transfer_checked(
+            token_program.key,
+            trader_account.as_ref().clone(),
+            mint.unwrap().as_ref().clone(),
+            vault.as_ref().clone(),
+            payer.as_ref().clone(),
+            transfer_hook_accounts,
+            amount,
+            decimals,
+            &[],
+        );
+    }
     crate::program::invoke(
         &spl_token_2022::instruction::transfer_checked(
             token_program.key,
@@ -207,6 +300,7 @@ fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
     payer: &Signer<'a, 'info>,
     amount: u64,
     _decimals: u8,
+    _transfer_hook_accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
     spl_token_2022_transfer(trader_account.info, vault.info, payer.info, amount)
 }
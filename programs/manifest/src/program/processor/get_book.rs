@@ -0,0 +1,102 @@
+use std::cell::Ref;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{trace, HyperTreeValueIteratorTrait};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+use crate::{
+    program::get_dynamic_account,
+    quantities::{QuoteAtomsPerBaseAtom, WrapperU64},
+    state::{utils::get_now_slot, BooksideReadOnly, MarketRef, RestingOrder},
+    validation::loaders::GetBookContext,
+};
+
+/// Levels per side that fit in the 1024 bytes of return data.
+pub const MAX_BOOK_LEVELS: u8 = 16;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct GetBookParams {
+    /// Levels per side. Capped at MAX_BOOK_LEVELS.
+    pub num_levels: u8,
+}
+
+impl GetBookParams {
+    pub fn new(num_levels: u8) -> Self {
+        GetBookParams { num_levels }
+    }
+}
+
+/// All resting orders at one price.
+#[derive(BorshDeserialize, BorshSerialize, Clone, Copy)]
+pub struct BookLevel {
+    pub price: QuoteAtomsPerBaseAtom,
+    pub base_atoms: u64,
+    pub num_orders: u32,
+}
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct GetBookReturn {
+    /// Best price first on both sides.
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+}
+
+pub(crate) fn process_get_book(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let get_book_context: GetBookContext = GetBookContext::load(accounts)?;
+    let GetBookParams { num_levels } = GetBookParams::try_from_slice(data)?;
+    let num_levels: usize = num_levels.min(MAX_BOOK_LEVELS) as usize;
+    trace!("get_book num_levels:{num_levels}");
+
+    let GetBookContext { market } = get_book_context;
+    let market_data: Ref<'_, &mut [u8]> = market.try_borrow_data()?;
+    let dynamic_account: MarketRef = get_dynamic_account(&market_data);
+
+    let now_slot: u32 = get_now_slot();
+    let return_data: GetBookReturn = GetBookReturn {
+        bids: get_book_levels(dynamic_account.get_bids(), num_levels, now_slot),
+        asks: get_book_levels(dynamic_account.get_asks(), num_levels, now_slot),
+    };
+
+    let mut buffer: Vec<u8> = Vec::with_capacity(
+        2 * std::mem::size_of::<u32>()
+            + (return_data.bids.len() + return_data.asks.len()) * std::mem::size_of::<BookLevel>(),
+    );
+    return_data.serialize(&mut buffer)?;
+    solana_program::program::set_return_data(&buffer[..]);
+
+    Ok(())
+}
+
+// Walks from the best order and merges orders at the same price. Expired and
+// empty orders are skipped since they cannot be matched.
+fn get_book_levels(book: BooksideReadOnly, num_levels: usize, now_slot: u32) -> Vec<BookLevel> {
+    let mut levels: Vec<BookLevel> = Vec::with_capacity(num_levels);
+    for (_, resting_order) in book.iter::<RestingOrder>() {
+        let base_atoms: u64 = resting_order.get_num_base_atoms().as_u64();
+        if resting_order.is_expired(now_slot) || base_atoms == 0 {
+            continue;
+        }
+        let price: QuoteAtomsPerBaseAtom = resting_order.get_price();
+        match levels.last_mut() {
+            Some(level) if level.price == price => {
+                level.base_atoms = level.base_atoms.saturating_add(base_atoms);
+                level.num_orders += 1;
+            }
+            _ => {
+                if levels.len() == num_levels {
+                    break;
+                }
+                levels.push(BookLevel {
+                    price,
+                    base_atoms,
+                    num_orders: 1,
+                });
+            }
+        }
+    }
+    levels
+}
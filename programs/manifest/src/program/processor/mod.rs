@@ -1,16 +1,30 @@
 pub mod batch_update;
+pub mod cancel_all;
 pub mod claim_seat;
+pub mod clean_expired;
+pub mod clear_seat_delegate;
+pub mod collect_fees;
+pub mod consume_events;
+pub mod create_event_queue;
 pub mod create_market;
 pub mod deposit;
 pub mod expand_market;
+pub mod get_book;
 pub mod global_add_trader;
 pub mod global_clean;
 pub mod global_create;
 pub mod global_deposit;
 pub mod global_evict;
 pub mod global_withdraw;
+pub mod place_grid;
+pub mod rebalance;
+pub mod set_market_status;
+pub mod set_membership;
+pub mod set_seat_delegate;
 pub mod shared;
 pub mod swap;
+pub mod swap_route;
+pub mod transfer_market_authority;
 pub mod withdraw;
 
 pub use shared::*;
@@ -0,0 +1,248 @@
+use std::cell::RefMut;
+
+use crate::{
+    logs::{emit_stack, PlaceOrderLog},
+    program::{
+        batch_expand_market, batch_update::BatchUpdateReturn, expand_market_if_needed,
+        get_trader_index_with_hint_allow_delegate, place_order, ManifestError,
+    },
+    quantities::{BaseAtoms, QuoteAtomsPerBaseAtom, WrapperU64},
+    require,
+    state::{
+        utils::get_now_slot, AddOrderToMarketArgs, AddOrderToMarketResult, MarketRefMut, OrderType,
+        ReversePriceBounds, SelfTradeBehavior,
+    },
+    validation::loaders::BatchUpdateContext,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{trace, DataIndex, PodBool};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::shared::get_mut_dynamic_account;
+
+/// Denominator for geometric steps. Same units as the Reverse order spread.
+const GEOMETRIC_STEP_DENOMINATOR: u32 = 100_000;
+
+/// Most levels a single grid can place, bounded by the compute budget.
+pub const MAX_GRID_LEVELS: u16 = 64;
+
+#[derive(Debug, BorshDeserialize, BorshSerialize, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum GridStepType {
+    /// Each level is step_mantissa * 10^step_exponent above the previous.
+    Arithmetic = 0,
+    /// Each level is (1 + step_mantissa / 100_000) times the previous.
+    Geometric = 1,
+}
+
+#[derive(Debug, BorshDeserialize, BorshSerialize, Clone)]
+pub struct PlaceGridParams {
+    /// Optional hint for what index the trader's ClaimedSeat is at.
+    pub trader_index_hint: Option<DataIndex>,
+    /// Price of the lowest level.
+    pub start_price_mantissa: u32,
+    pub start_price_exponent: i8,
+    pub step_type: GridStepType,
+    pub step_mantissa: u32,
+    /// Ignored for geometric steps.
+    pub step_exponent: i8,
+    pub num_levels: u16,
+    /// The lowest num_bid_levels levels are bids, the rest are asks.
+    pub num_bid_levels: u16,
+    pub base_atoms_per_level: u64,
+    /// Reverse order spread in units of 10^-5.
+    pub spread: u16,
+}
+
+impl PlaceGridParams {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        trader_index_hint: Option<DataIndex>,
+        start_price_mantissa: u32,
+        start_price_exponent: i8,
+        step_type: GridStepType,
+        step_mantissa: u32,
+        step_exponent: i8,
+        num_levels: u16,
+        num_bid_levels: u16,
+        base_atoms_per_level: u64,
+        spread: u16,
+    ) -> Self {
+        PlaceGridParams {
+            trader_index_hint,
+            start_price_mantissa,
+            start_price_exponent,
+            step_type,
+            step_mantissa,
+            step_exponent,
+            num_levels,
+            num_bid_levels,
+            base_atoms_per_level,
+            spread,
+        }
+    }
+
+    /// Prices of every level, lowest first.
+    pub fn try_level_prices(&self) -> Result<Vec<QuoteAtomsPerBaseAtom>, ProgramError> {
+        let mut price: QuoteAtomsPerBaseAtom =
+            QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(
+                self.start_price_mantissa,
+                self.start_price_exponent,
+            )?;
+        let arithmetic_step: QuoteAtomsPerBaseAtom = match self.step_type {
+            GridStepType::Arithmetic => QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(
+                self.step_mantissa,
+                self.step_exponent,
+            )?,
+            GridStepType::Geometric => QuoteAtomsPerBaseAtom::ZERO,
+        };
+        let mut prices: Vec<QuoteAtomsPerBaseAtom> = Vec::with_capacity(self.num_levels as usize);
+        for level in 0..self.num_levels {
+            if level > 0 {
+                price = match self.step_type {
+                    GridStepType::Arithmetic => price.checked_add(arithmetic_step)?,
+                    GridStepType::Geometric => price.checked_multiply_rational(
+                        GEOMETRIC_STEP_DENOMINATOR
+                            .checked_add(self.step_mantissa)
+                            .ok_or(ManifestError::Overflow)?,
+                        GEOMETRIC_STEP_DENOMINATOR,
+                        false,
+                    )?,
+                };
+            }
+            prices.push(price);
+        }
+        Ok(prices)
+    }
+}
+
+/// Places a ladder of reverse orders. The market is expanded up front for
+/// every level instead of once per order.
+pub(crate) fn process_place_grid(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    // Same accounts as BatchUpdate. Reverse orders are never global so the
+    // optional global accounts are not used.
+    let batch_update_context: BatchUpdateContext = BatchUpdateContext::load(accounts, false)?;
+    let BatchUpdateContext {
+        market,
+        payer,
+        owner,
+        global_trade_accounts_opts,
+        event_queue_opt,
+        ..
+    } = batch_update_context;
+
+    let params: PlaceGridParams = PlaceGridParams::try_from_slice(data)?;
+    trace!("place_grid {params:?}");
+
+    require!(
+        params.num_levels > 0 && params.num_bid_levels <= params.num_levels,
+        ManifestError::InvalidPlaceOrderFromWalletParams,
+        "Invalid grid levels {} bids of {}",
+        params.num_bid_levels,
+        params.num_levels,
+    )?;
+    require!(
+        params.num_levels <= MAX_GRID_LEVELS,
+        ManifestError::InvalidPlaceOrderFromWalletParams,
+        "Too many grid levels {} max {}",
+        params.num_levels,
+        MAX_GRID_LEVELS,
+    )?;
+    require!(
+        params.step_mantissa > 0,
+        ManifestError::InvalidPlaceOrderFromWalletParams,
+        "Grid step must be positive",
+    )?;
+    let prices: Vec<QuoteAtomsPerBaseAtom> = params.try_level_prices()?;
+
+    let (trader_index, trader, blocks_missing) = {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.assert_market_active()?;
+        let (trader_index, trader) = get_trader_index_with_hint_allow_delegate(
+            params.trader_index_hint,
+            &dynamic_account,
+            &owner,
+        )?;
+        // Only expand by what the free list cannot already cover.
+        let blocks_missing: u32 = dynamic_account
+            .free_blocks_short_of_n(params.num_levels as u32)
+            .unwrap_or(0);
+        (trader_index, trader, blocks_missing)
+    };
+
+    if blocks_missing > 0 {
+        batch_expand_market(&payer, &market, blocks_missing)?;
+    }
+
+    let current_slot: Option<u32> = Some(get_now_slot());
+    let base_atoms: BaseAtoms = BaseAtoms::new(params.base_atoms_per_level);
+    let order_type: OrderType = OrderType::Reverse;
+    // Reverse orders keep the spread where the expiration would be.
+    let last_valid_slot: u32 = params.spread as u32;
+
+    let mut result: Vec<(u64, DataIndex)> = Vec::with_capacity(prices.len());
+    for (level, price) in prices.into_iter().enumerate() {
+        {
+            let is_bid: bool = level < params.num_bid_levels as usize;
+            let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+            let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+            let AddOrderToMarketResult {
+                order_sequence_number,
+                order_index,
+                ..
+            } = place_order(
+                &mut dynamic_account,
+                AddOrderToMarketArgs {
+                    market: *market.key,
+                    trader_index,
+                    num_base_atoms: base_atoms,
+                    price,
+                    is_bid,
+                    last_valid_slot,
+                    order_type,
+                    global_trade_accounts_opts: &global_trade_accounts_opts,
+                    current_slot,
+                    self_trade_behavior: SelfTradeBehavior::Allow,
+                    client_order_id: 0,
+                    reverse_price_bounds: ReversePriceBounds::default(),
+                    iceberg_display_base_atoms: BaseAtoms::ZERO,
+                    event_queue_opt: &event_queue_opt,
+                },
+            )?;
+
+            emit_stack(PlaceOrderLog {
+                market: *market.key,
+                trader,
+                base_atoms,
+                price,
+                order_type,
+                is_bid: PodBool::from(is_bid),
+                _padding: [0; 6],
+                order_sequence_number,
+                order_index,
+                last_valid_slot,
+            })?;
+            result.push((order_sequence_number, order_index));
+        }
+        // Only needed when levels cross the book and reverse orders flip
+        // into the blocks expanded for the grid.
+        expand_market_if_needed(&payer, &market)?;
+    }
+
+    let mut buffer: Vec<u8> = Vec::with_capacity(
+        std::mem::size_of::<BatchUpdateReturn>() + result.len() * 2 * std::mem::size_of::<u64>(),
+    );
+    let return_data: BatchUpdateReturn = BatchUpdateReturn { orders: result };
+    return_data.serialize(&mut buffer)?;
+    solana_program::program::set_return_data(&buffer[..]);
+
+    Ok(())
+}
@@ -0,0 +1,196 @@
+use std::cell::RefMut;
+
+use crate::{
+    logs::{emit_stack, DepositLog, WithdrawLog},
+    program::{expand_market_if_needed, get_trader_index_with_hint},
+    quantities::WrapperU64,
+    state::MarketRefMut,
+    validation::loaders::RebalanceContext,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{trace, DataIndex};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+use super::{
+    batch_update::{
+        batch_update_on_market, expand_market_for_orders, set_batch_update_return_data,
+        BatchUpdateParams, BatchUpdateResult,
+    },
+    deposit::transfer_from_payer_to_vault,
+    shared::get_mut_dynamic_account,
+    withdraw::transfer_from_vault_to_token_account,
+};
+
+/// Withdraw amount that withdraws everything left on the seat after the batch
+/// update.
+pub const WITHDRAW_ALL_ATOMS: u64 = u64::MAX;
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct RebalanceParams {
+    /// Deposited before the batch update. Zero skips the deposit.
+    pub base_deposit_atoms: u64,
+    pub quote_deposit_atoms: u64,
+    /// Withdrawn after the batch update. Zero skips the withdraw and
+    /// WITHDRAW_ALL_ATOMS withdraws the whole seat balance.
+    pub base_withdraw_atoms: u64,
+    pub quote_withdraw_atoms: u64,
+    pub batch_update: BatchUpdateParams,
+}
+
+impl RebalanceParams {
+    pub fn new(
+        base_deposit_atoms: u64,
+        quote_deposit_atoms: u64,
+        base_withdraw_atoms: u64,
+        quote_withdraw_atoms: u64,
+        batch_update: BatchUpdateParams,
+    ) -> Self {
+        RebalanceParams {
+            base_deposit_atoms,
+            quote_deposit_atoms,
+            base_withdraw_atoms,
+            quote_withdraw_atoms,
+            batch_update,
+        }
+    }
+
+    /// Batch update params are last so they keep their versioned layout.
+    #[cfg(not(feature = "certora"))]
+    pub fn try_from_versioned_slice(data: &[u8]) -> Result<Self, ProgramError> {
+        let buf: &mut &[u8] = &mut &data[..];
+        let base_deposit_atoms: u64 = u64::deserialize(buf)?;
+        let quote_deposit_atoms: u64 = u64::deserialize(buf)?;
+        let base_withdraw_atoms: u64 = u64::deserialize(buf)?;
+        let quote_withdraw_atoms: u64 = u64::deserialize(buf)?;
+        let batch_update: BatchUpdateParams = BatchUpdateParams::try_from_versioned_slice(buf)?;
+        Ok(RebalanceParams {
+            base_deposit_atoms,
+            quote_deposit_atoms,
+            base_withdraw_atoms,
+            quote_withdraw_atoms,
+            batch_update,
+        })
+    }
+}
+
+pub(crate) fn process_rebalance(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    #[cfg(not(feature = "certora"))]
+    let params: RebalanceParams = RebalanceParams::try_from_versioned_slice(data)?;
+    #[cfg(feature = "certora")]
+    let params: RebalanceParams = RebalanceParams::try_from_slice(data)?;
+
+    let rebalance_context: RebalanceContext = RebalanceContext::load(accounts)?;
+    let RebalanceParams {
+        base_deposit_atoms,
+        quote_deposit_atoms,
+        base_withdraw_atoms,
+        quote_withdraw_atoms,
+        batch_update,
+    } = params;
+    trace!("rebalance deposits:{base_deposit_atoms},{quote_deposit_atoms} withdraws:{base_withdraw_atoms},{quote_withdraw_atoms}");
+
+    let RebalanceContext {
+        market,
+        payer,
+        global_trade_accounts_opts,
+        event_queue_opt,
+        ..
+    } = &rebalance_context;
+    expand_market_for_orders(payer, market, batch_update.orders.len())?;
+
+    // Deposit, batch update and withdraw all happen under a single borrow of
+    // the market.
+    let result: BatchUpdateResult = {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        // Cancels and withdraws still work on a cancel only market.
+        if base_deposit_atoms != 0 || quote_deposit_atoms != 0 {
+            dynamic_account.assert_market_active()?;
+        }
+        let trader_index: DataIndex =
+            get_trader_index_with_hint(batch_update.trader_index_hint, &dynamic_account, payer)?;
+
+        for (is_base, amount_atoms) in [(true, base_deposit_atoms), (false, quote_deposit_atoms)] {
+            if amount_atoms == 0 {
+                continue;
+            }
+            let (_, deposited_amount_atoms) = transfer_from_payer_to_vault(
+                &rebalance_context.deposit_context(is_base),
+                &dynamic_account,
+                amount_atoms,
+            )?;
+            dynamic_account.deposit(trader_index, deposited_amount_atoms, is_base)?;
+
+            emit_stack(DepositLog {
+                market: *market.key,
+                trader: *payer.key,
+                mint: if is_base {
+                    *dynamic_account.get_base_mint()
+                } else {
+                    *dynamic_account.get_quote_mint()
+                },
+                amount_atoms: deposited_amount_atoms,
+            })?;
+        }
+
+        let result: BatchUpdateResult = batch_update_on_market(
+            &mut dynamic_account,
+            market.key,
+            payer.key,
+            payer.key,
+            trader_index,
+            *payer.key,
+            global_trade_accounts_opts,
+            event_queue_opt,
+            batch_update,
+        )?;
+
+        for (is_base, amount_atoms) in [(true, base_withdraw_atoms), (false, quote_withdraw_atoms)]
+        {
+            let amount_atoms: u64 = if amount_atoms == WITHDRAW_ALL_ATOMS {
+                let (base_atoms, quote_atoms) = dynamic_account.get_trader_balance(payer.key);
+                if is_base {
+                    base_atoms.as_u64()
+                } else {
+                    quote_atoms.as_u64()
+                }
+            } else {
+                amount_atoms
+            };
+            if amount_atoms == 0 {
+                continue;
+            }
+            transfer_from_vault_to_token_account(
+                &rebalance_context.withdraw_context(is_base),
+                &dynamic_account,
+                amount_atoms,
+            )?;
+            dynamic_account.withdraw(trader_index, amount_atoms, is_base)?;
+
+            emit_stack(WithdrawLog {
+                market: *market.key,
+                trader: *payer.key,
+                mint: if is_base {
+                    *dynamic_account.get_base_mint()
+                } else {
+                    *dynamic_account.get_quote_mint()
+                },
+                amount_atoms,
+            })?;
+        }
+        result
+    };
+    expand_market_if_needed(payer, market)?;
+
+    // Set last so the token transfers do not clear it.
+    set_batch_update_return_data(result);
+
+    Ok(())
+}
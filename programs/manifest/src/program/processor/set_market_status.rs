@@ -0,0 +1,47 @@
+use std::cell::RefMut;
+
+use crate::{
+    logs::{emit_stack, MarketStatusLog},
+    program::get_mut_dynamic_account,
+    state::{MarketRefMut, MarketStatus},
+    validation::loaders::MarketAuthorityContext,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::trace;
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct SetMarketStatusParams {
+    pub status: MarketStatus,
+}
+
+impl SetMarketStatusParams {
+    pub fn new(status: MarketStatus) -> Self {
+        SetMarketStatusParams { status }
+    }
+}
+
+pub(crate) fn process_set_market_status(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let market_authority_context: MarketAuthorityContext = MarketAuthorityContext::load(accounts)?;
+    let SetMarketStatusParams { status } = SetMarketStatusParams::try_from_slice(data)?;
+    trace!("set_market_status status:{status:?}");
+
+    let MarketAuthorityContext { authority, market } = market_authority_context;
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+    dynamic_account.set_market_status(authority.key, status)?;
+
+    emit_stack(MarketStatusLog {
+        market: *market.key,
+        authority: *authority.key,
+        status: status.into(),
+        _padding: [0; 7],
+    })?;
+
+    Ok(())
+}
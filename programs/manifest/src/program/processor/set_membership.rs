@@ -0,0 +1,112 @@
+use std::{cell::RefMut, mem::size_of};
+
+use crate::{
+    logs::{emit_stack, MembershipLog},
+    program::{get_mut_dynamic_account, ManifestError},
+    require,
+    state::{MarketMembership, MarketRefMut},
+    utils::create_account,
+    validation::{get_membership_address, loaders::SetMembershipContext, ManifestAccountInfo},
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{get_mut_helper, trace, PodBool};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey, rent::Rent, sysvar::Sysvar,
+};
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct SetMembershipParams {
+    pub trader: Pubkey,
+    /// False revokes the membership and moves an existing seat to cancel only.
+    pub is_member: bool,
+}
+
+impl SetMembershipParams {
+    pub fn new(trader: Pubkey, is_member: bool) -> Self {
+        SetMembershipParams { trader, is_member }
+    }
+}
+
+pub(crate) fn process_set_membership(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let set_membership_context: SetMembershipContext = SetMembershipContext::load(accounts)?;
+    let SetMembershipParams { trader, is_member } = SetMembershipParams::try_from_slice(data)?;
+    trace!("set_membership trader:{trader} is_member:{is_member}");
+
+    let SetMembershipContext {
+        gate_authority,
+        market,
+        membership,
+        system_program,
+    } = set_membership_context;
+
+    {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        require!(
+            dynamic_account
+                .get_gate_config()
+                .is_some_and(|gate_config| gate_config.get_gate_authority() == gate_authority.key),
+            ManifestError::IncorrectGateAuthority,
+            "Signer {} is not the gate authority",
+            gate_authority.key,
+        )?;
+    }
+
+    let (expected_membership_key, membership_bump) = get_membership_address(market.key, &trader);
+    require!(
+        expected_membership_key == *membership.key,
+        ProgramError::InvalidSeeds,
+        "Unexpected membership address {} expected {}",
+        membership.key,
+        expected_membership_key,
+    )?;
+
+    // The first grant or revoke for a trader makes the account.
+    if membership.data_is_empty() {
+        let membership_seeds: Vec<Vec<u8>> = vec![
+            b"membership".to_vec(),
+            market.key.as_ref().to_vec(),
+            trader.as_ref().to_vec(),
+            vec![membership_bump],
+        ];
+        create_account(
+            gate_authority.as_ref(),
+            membership,
+            system_program.as_ref(),
+            &crate::id(),
+            &Rent::get()?,
+            size_of::<MarketMembership>() as u64,
+            membership_seeds,
+        )?;
+        let membership_bytes: &mut [u8] = &mut membership.try_borrow_mut_data()?[..];
+        *get_mut_helper::<MarketMembership>(membership_bytes, 0_u32) =
+            MarketMembership::new(market.key, &trader);
+    }
+
+    {
+        let membership: ManifestAccountInfo<MarketMembership> =
+            ManifestAccountInfo::<MarketMembership>::new(membership)?;
+        let membership_bytes: &mut [u8] = &mut membership.try_borrow_mut_data()?[..];
+        get_mut_helper::<MarketMembership>(membership_bytes, 0_u32).set_is_revoked(!is_member);
+    }
+
+    {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.set_seat_cancel_only(&trader, !is_member);
+    }
+
+    emit_stack(MembershipLog {
+        market: *market.key,
+        trader,
+        is_member: PodBool::from(is_member),
+        _padding: [0; 7],
+    })?;
+
+    Ok(())
+}
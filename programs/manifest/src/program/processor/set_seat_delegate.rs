@@ -0,0 +1,62 @@
+use std::cell::RefMut;
+
+use crate::{
+    logs::{emit_stack, SeatDelegateLog},
+    program::{expand_market_if_needed, get_mut_dynamic_account, get_trader_index_with_hint},
+    state::MarketRefMut,
+    validation::loaders::SeatDelegateContext,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{trace, DataIndex};
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct SetSeatDelegateParams {
+    /// Optional hint for what index the trader's ClaimedSeat is at.
+    pub trader_index_hint: Option<DataIndex>,
+    /// Replaces the existing delegate if there is one.
+    pub delegate: Pubkey,
+}
+
+impl SetSeatDelegateParams {
+    pub fn new(trader_index_hint: Option<DataIndex>, delegate: Pubkey) -> Self {
+        SetSeatDelegateParams {
+            trader_index_hint,
+            delegate,
+        }
+    }
+}
+
+pub(crate) fn process_set_seat_delegate(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let seat_delegate_context: SeatDelegateContext = SeatDelegateContext::load(accounts)?;
+    let SetSeatDelegateParams {
+        trader_index_hint,
+        delegate,
+    } = SetSeatDelegateParams::try_from_slice(data)?;
+    trace!("set_seat_delegate trader_index_hint:{trader_index_hint:?} delegate:{delegate}");
+
+    let SeatDelegateContext { owner, market, .. } = seat_delegate_context;
+
+    {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        let trader_index: DataIndex =
+            get_trader_index_with_hint(trader_index_hint, &dynamic_account, &owner)?;
+        dynamic_account.set_seat_delegate(trader_index, &delegate)?;
+
+        emit_stack(SeatDelegateLog {
+            market: *market.key,
+            trader: *owner.key,
+            delegate,
+        })?;
+    }
+
+    // The first delegate for a seat takes a block.
+    expand_market_if_needed(&owner, &market)?;
+
+    Ok(())
+}
@@ -3,24 +3,33 @@ use std::{
     mem::size_of,
 };
 
+#[cfg(not(feature = "certora"))]
+use crate::{native_sol_seeds_with_bump, utils::create_account};
 use crate::{
     require,
     state::{
         claimed_seat::ClaimedSeat, constants::MARKET_BLOCK_SIZE, DynamicAccount, GlobalFixed,
-        MarketFixed, MarketRefMut, GLOBAL_BLOCK_SIZE,
+        MarketFixed, MarketGateConfig, MarketMembership, MarketRefMut, GLOBAL_BLOCK_SIZE,
+    },
+    validation::{
+        ManifestAccount, ManifestAccountInfo, MintAccountInfo, Program, Signer, TokenAccountInfo,
+        TokenProgram,
     },
-    validation::{ManifestAccount, ManifestAccountInfo, Signer},
 };
 use bytemuck::Pod;
 use hypertree::{get_helper, get_mut_helper, DataIndex, Get, RBNode};
 #[cfg(not(feature = "certora"))]
 use solana_program::sysvar::Sysvar;
 use solana_program::{
-    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction,
+    account_info::AccountInfo, entrypoint::ProgramResult, instruction::Instruction, pubkey::Pubkey,
     sysvar::slot_history::ProgramError,
 };
+#[cfg(not(feature = "certora"))]
+use solana_program::{program::invoke_signed, program_pack::Pack, rent::Rent, system_instruction};
 
 use super::batch_update::MarketDataTreeNodeType;
+#[cfg(not(feature = "certora"))]
+use super::swap::spl_token_transfer_from_vault_to_trader;
 
 pub(crate) fn expand_market_if_needed<'a, 'info, T: ManifestAccount + Pod + Clone>(
     payer: &AccountInfo<'info>,
@@ -204,17 +213,39 @@ pub(crate) fn get_trader_index_with_hint(
     let trader_index: DataIndex = match trader_index_hint {
         None => dynamic_account.get_trader_index(payer.key),
         Some(hinted_index) => {
-            verify_trader_index_hint(hinted_index, &dynamic_account, &payer)?;
+            verify_trader_index_hint(hinted_index, &dynamic_account, &payer, false)?;
             hinted_index
         }
     };
     Ok(trader_index)
 }
 
+/// Same as get_trader_index_with_hint, but the payer may also be the seat
+/// delegate. Only for placing and cancelling orders. Delegates are not looked
+/// up, so they need to give the hint. Also returns the seat owner, which is
+/// the payer unless a delegate is trading.
+pub(crate) fn get_trader_index_with_hint_allow_delegate(
+    trader_index_hint: Option<DataIndex>,
+    dynamic_account: &MarketRefMut,
+    payer: &Signer,
+) -> Result<(DataIndex, Pubkey), ProgramError> {
+    match trader_index_hint {
+        None => Ok((dynamic_account.get_trader_index(payer.key), *payer.key)),
+        Some(hinted_index) => {
+            verify_trader_index_hint(hinted_index, &dynamic_account, &payer, true)?;
+            Ok((
+                hinted_index,
+                *dynamic_account.get_trader_key_by_index(hinted_index),
+            ))
+        }
+    }
+}
+
 fn verify_trader_index_hint(
     hinted_index: DataIndex,
     dynamic_account: &MarketRefMut,
     payer: &Signer,
+    allow_delegate: bool,
 ) -> ProgramResult {
     require!(
         hinted_index % (MARKET_BLOCK_SIZE as DataIndex) == 0,
@@ -233,7 +264,9 @@ fn verify_trader_index_hint(
     require!(
         payer
             .key
-            .eq(dynamic_account.get_trader_key_by_index(hinted_index)),
+            .eq(dynamic_account.get_trader_key_by_index(hinted_index))
+            || (allow_delegate
+                && dynamic_account.get_seat_delegate(hinted_index) == Some(payer.key)),
         crate::program::ManifestError::WrongIndexHintParams,
         "Invalid trader hint index {} did not match payer",
         hinted_index
@@ -241,6 +274,212 @@ fn verify_trader_index_hint(
     Ok(())
 }
 
+/// Checks that the trader may join a permissioned market. Passes when the
+/// market has no gate, when the gate authority signed, or when the credential
+/// is an unrevoked membership for this market and trader.
+pub(crate) fn assert_gate_passed(
+    dynamic_account: &MarketRefMut,
+    market: &Pubkey,
+    trader: &Pubkey,
+    gate_credential_opt: Option<&AccountInfo>,
+) -> ProgramResult {
+    let gate_config: &MarketGateConfig = match dynamic_account.get_gate_config() {
+        None => return Ok(()),
+        Some(gate_config) => gate_config,
+    };
+    let gate_credential: &AccountInfo =
+        gate_credential_opt.ok_or(crate::program::ManifestError::MissingMembership)?;
+    if gate_credential.is_signer && gate_credential.key == gate_config.get_gate_authority() {
+        return Ok(());
+    }
+
+    let membership: ManifestAccountInfo<MarketMembership> =
+        ManifestAccountInfo::<MarketMembership>::new(gate_credential)?;
+    let membership_fixed: Ref<MarketMembership> = membership.get_fixed()?;
+    require!(
+        membership_fixed.get_market() == market && membership_fixed.get_trader() == trader,
+        crate::program::ManifestError::MissingMembership,
+        "Membership {} is not for trader {} on market {}",
+        gate_credential.key,
+        trader,
+        market,
+    )?;
+    require!(
+        !membership_fixed.get_is_revoked(),
+        crate::program::ManifestError::MissingMembership,
+        "Membership {} is revoked",
+        gate_credential.key,
+    )?;
+    Ok(())
+}
+
+/** Wrap lamports from the payer directly into a native mint vault **/
+#[cfg(not(feature = "certora"))]
+pub(crate) fn wrap_native_sol_to_vault<'a, 'info>(
+    payer: &Signer<'a, 'info>,
+    token_program: &TokenProgram<'a, 'info>,
+    vault: &TokenAccountInfo<'a, 'info>,
+    system_program: &Program<'a, 'info>,
+    lamports: u64,
+) -> ProgramResult {
+    if lamports == 0 {
+        return Ok(());
+    }
+    invoke(
+        &system_instruction::transfer(payer.key, vault.key, lamports),
+        &[
+            payer.as_ref().clone(),
+            vault.as_ref().clone(),
+            system_program.as_ref().clone(),
+        ],
+    )?;
+    // Lamports sent to a native token account only count towards the token
+    // balance after a sync.
+    invoke(
+        &spl_token::instruction::sync_native(token_program.key, vault.key)?,
+        &[token_program.as_ref().clone(), vault.as_ref().clone()],
+    )
+}
+
+#[cfg(feature = "certora")]
+/** (Summary) Native SOL is not modeled **/
+pub(crate) fn wrap_native_sol_to_vault<'a, 'info>(
+    _payer: &Signer<'a, 'info>,
+    _token_program: &TokenProgram<'a, 'info>,
+    _vault: &TokenAccountInfo<'a, 'info>,
+    _system_program: &Program<'a, 'info>,
+    _lamports: u64,
+) -> ProgramResult {
+    Err(ProgramError::InvalidArgument)
+}
+
+/** Unwrap from a native mint vault to the owner as lamports **/
+// Token accounts cannot release lamports directly, so the atoms move through
+// a wrapped SOL account at the owner's native sol address that is created and
+// closed to the owner within the swap. The payer fronts its rent and is paid
+// back by the owner when they differ.
+#[cfg(not(feature = "certora"))]
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn unwrap_native_sol_from_vault<'a, 'info>(
+    payer: &Signer<'a, 'info>,
+    owner: &Signer<'a, 'info>,
+    token_program: &TokenProgram<'a, 'info>,
+    native_mint: &MintAccountInfo<'a, 'info>,
+    vault: &TokenAccountInfo<'a, 'info>,
+    native_sol_account: &TokenAccountInfo<'a, 'info>,
+    system_program: &Program<'a, 'info>,
+    native_sol_bump: u8,
+    amount: u64,
+    market_key: &Pubkey,
+    vault_bump: u8,
+) -> ProgramResult {
+    if amount == 0 {
+        return Ok(());
+    }
+
+    // Anyone can send lamports to the address ahead of time, which would
+    // otherwise block creating the account.
+    let prefunded_lamports: u64 = native_sol_account.lamports();
+    if prefunded_lamports > 0 {
+        invoke_signed(
+            &system_instruction::transfer(native_sol_account.key, owner.key, prefunded_lamports),
+            &[
+                native_sol_account.as_ref().clone(),
+                owner.as_ref().clone(),
+                system_program.as_ref().clone(),
+            ],
+            native_sol_seeds_with_bump!(owner.key, native_sol_bump),
+        )?;
+    }
+
+    let rent: Rent = Rent::get()?;
+    create_account(
+        payer.as_ref(),
+        native_sol_account.as_ref(),
+        system_program.as_ref(),
+        &spl_token::id(),
+        &rent,
+        spl_token::state::Account::LEN as u64,
+        vec![
+            b"native-sol".to_vec(),
+            owner.key.as_ref().to_vec(),
+            vec![native_sol_bump],
+        ],
+    )?;
+    invoke(
+        &spl_token::instruction::initialize_account3(
+            token_program.key,
+            native_sol_account.key,
+            native_mint.info.key,
+            native_sol_account.key,
+        )?,
+        &[
+            token_program.as_ref().clone(),
+            native_sol_account.as_ref().clone(),
+            native_mint.as_ref().clone(),
+        ],
+    )?;
+    spl_token_transfer_from_vault_to_trader(
+        token_program,
+        vault,
+        native_sol_account,
+        amount,
+        market_key,
+        vault_bump,
+        native_mint.info.key,
+    )?;
+    invoke_signed(
+        &spl_token::instruction::close_account(
+            token_program.key,
+            native_sol_account.key,
+            owner.key,
+            native_sol_account.key,
+            &[],
+        )?,
+        &[
+            token_program.as_ref().clone(),
+            native_sol_account.as_ref().clone(),
+            owner.as_ref().clone(),
+        ],
+        native_sol_seeds_with_bump!(owner.key, native_sol_bump),
+    )?;
+
+    if payer.key != owner.key {
+        invoke(
+            &system_instruction::transfer(
+                owner.key,
+                payer.key,
+                rent.minimum_balance(spl_token::state::Account::LEN),
+            ),
+            &[
+                owner.as_ref().clone(),
+                payer.as_ref().clone(),
+                system_program.as_ref().clone(),
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "certora")]
+/** (Summary) Native SOL is not modeled **/
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn unwrap_native_sol_from_vault<'a, 'info>(
+    _payer: &Signer<'a, 'info>,
+    _owner: &Signer<'a, 'info>,
+    _token_program: &TokenProgram<'a, 'info>,
+    _native_mint: &MintAccountInfo<'a, 'info>,
+    _vault: &TokenAccountInfo<'a, 'info>,
+    _native_sol_account: &TokenAccountInfo<'a, 'info>,
+    _system_program: &Program<'a, 'info>,
+    _native_sol_bump: u8,
+    _amount: u64,
+    _market_key: &Pubkey,
+    _vault_bump: u8,
+) -> ProgramResult {
+    Err(ProgramError::InvalidArgument)
+}
+
 // TODO: Same for invoke_signed
 
 pub fn invoke(ix: &Instruction, account_infos: &[AccountInfo<'_>]) -> ProgramResult {
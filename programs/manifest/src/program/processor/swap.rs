@@ -3,13 +3,13 @@ use std::cell::RefMut;
 use crate::{
     logs::{emit_stack, PlaceOrderLogV2},
     program::expand_market_if_needed,
-    quantities::{BaseAtoms, QuoteAtoms, QuoteAtomsPerBaseAtom, WrapperU64},
+    quantities::{BaseAtoms, PriceConversionError, QuoteAtoms, QuoteAtomsPerBaseAtom, WrapperU64},
     require,
     state::{
-        AddOrderToMarketArgs, AddOrderToMarketResult, MarketRefMut, OrderType,
-        NO_EXPIRATION_LAST_VALID_SLOT,
+        AddOrderToMarketArgs, AddOrderToMarketResult, MarketRefMut, OrderType, ReversePriceBounds,
+        SelfTradeBehavior, NO_EXPIRATION_LAST_VALID_SLOT,
     },
-    validation::loaders::SwapContext,
+    validation::{get_native_sol_address, loaders::SwapContext},
 };
 #[cfg(not(feature = "certora"))]
 use crate::{
@@ -20,7 +20,10 @@ use borsh::{BorshDeserialize, BorshSerialize};
 use hypertree::{trace, DataIndex, NIL};
 use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
 
-use super::shared::get_mut_dynamic_account;
+use super::shared::{
+    assert_gate_passed, get_mut_dynamic_account, unwrap_native_sol_from_vault,
+    wrap_native_sol_to_vault,
+};
 
 #[cfg(feature = "certora")]
 use {
@@ -39,8 +42,32 @@ pub struct SwapParams {
     pub is_base_in: bool,
     // Exact in is a technical term that doesnt actually mean exact. It is
     // desired. If not that much can be fulfilled, less will be allowed assuming
-    // the min_out/max_in is satisfied.
+    // the min_out/max_in is satisfied. On markets with a taker fee, min out is
+    // checked after the fee, while exact out is matched before the fee so the
+    // trader receives out_atoms less the fee.
     pub is_exact_in: bool,
+    // What to do when matching against the owner's own resting orders. Any
+    // funds freed from cancelled or decremented orders are returned to the
+    // owner's wallet along with the rest of the swap proceeds.
+    pub self_trade_behavior: SelfTradeBehavior,
+    // Worst price the taker is willing to trade at. Matching stops once the
+    // next resting order is beyond it and the swap settles whatever was
+    // filled. A zero mantissa means no limit.
+    pub limit_price_mantissa: u32,
+    pub limit_price_exponent: i8,
+    // Rest whatever is not matched as a limit order at the limit price
+    // instead of returning it to the wallet. The trader keeps the seat so
+    // the order can be cancelled and settled later like any other. With exact
+    // in, min out only counts the filled part, so out_atoms above what fills
+    // right away fails even though the remainder would rest.
+    pub rest_remainder: bool,
+    // Move the native mint leg as lamports. SOL in is paid by the payer
+    // straight into the vault and SOL out is unwrapped to the owner, so the
+    // trader passes their native sol address instead of a wrapped SOL account.
+    pub native_sol: bool,
+    // The last account, before the event queue, is a gate authority signer or
+    // membership used to claim the temporary seat on a permissioned market.
+    pub has_gate_credential: bool,
 }
 
 impl SwapParams {
@@ -50,7 +77,81 @@ impl SwapParams {
             out_atoms,
             is_base_in,
             is_exact_in,
+            self_trade_behavior: SelfTradeBehavior::Allow,
+            limit_price_mantissa: 0,
+            limit_price_exponent: 0,
+            rest_remainder: false,
+            native_sol: false,
+            has_gate_credential: false,
+        }
+    }
+    pub fn with_self_trade_behavior(mut self, self_trade_behavior: SelfTradeBehavior) -> Self {
+        self.self_trade_behavior = self_trade_behavior;
+        self
+    }
+    pub fn with_limit_price(mut self, price_mantissa: u32, price_exponent: i8) -> Self {
+        self.limit_price_mantissa = price_mantissa;
+        self.limit_price_exponent = price_exponent;
+        self
+    }
+
+    pub fn with_rest_remainder(mut self, rest_remainder: bool) -> Self {
+        self.rest_remainder = rest_remainder;
+        self
+    }
+
+    pub fn with_native_sol(mut self, native_sol: bool) -> Self {
+        self.native_sol = native_sol;
+        self
+    }
+
+    pub fn with_gate_credential(mut self, has_gate_credential: bool) -> Self {
+        self.has_gate_credential = has_gate_credential;
+        self
+    }
+
+    pub fn try_limit_price(&self) -> Result<Option<QuoteAtomsPerBaseAtom>, PriceConversionError> {
+        if self.limit_price_mantissa == 0 {
+            return Ok(None);
+        }
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(
+            self.limit_price_mantissa,
+            self.limit_price_exponent,
+        )
+        .map(Some)
+    }
+
+    /// Params are versioned by length. Fields after is_exact_in were appended
+    /// later and take their defaults when absent so that older clients which
+    /// serialize a shorter struct keep working.
+    pub fn try_from_versioned_slice(data: &[u8]) -> Result<Self, ProgramError> {
+        let buf: &mut &[u8] = &mut &data[..];
+        let mut params: SwapParams = SwapParams::new(
+            u64::deserialize(buf)?,
+            u64::deserialize(buf)?,
+            bool::deserialize(buf)?,
+            bool::deserialize(buf)?,
+        );
+        if !buf.is_empty() {
+            params.self_trade_behavior = SelfTradeBehavior::deserialize(buf)?;
+        }
+        if !buf.is_empty() {
+            params.limit_price_mantissa = u32::deserialize(buf)?;
+            params.limit_price_exponent = i8::deserialize(buf)?;
+        }
+        if !buf.is_empty() {
+            params.rest_remainder = bool::deserialize(buf)?;
+        }
+        if !buf.is_empty() {
+            params.native_sol = bool::deserialize(buf)?;
+        }
+        if !buf.is_empty() {
+            params.has_gate_credential = bool::deserialize(buf)?;
+        }
+        if !buf.is_empty() {
+            return Err(ProgramError::InvalidInstructionData);
         }
+        Ok(params)
     }
 }
 
@@ -59,7 +160,7 @@ pub(crate) fn process_swap(
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
-    let params = SwapParams::try_from_slice(data)?;
+    let params = SwapParams::try_from_versioned_slice(data)?;
     process_swap_core(program_id, accounts, params)
 }
 
@@ -69,12 +170,13 @@ pub(crate) fn process_swap_core(
     accounts: &[AccountInfo],
     params: SwapParams,
 ) -> ProgramResult {
-    let swap_context: SwapContext = SwapContext::load(accounts)?;
+    let swap_context: SwapContext = SwapContext::load(accounts, params.has_gate_credential)?;
 
     let SwapContext {
         market,
         payer,
         owner,
+        system_program,
         trader_base: trader_base_account,
         trader_quote: trader_quote_account,
         base_vault,
@@ -84,15 +186,21 @@ pub(crate) fn process_swap_core(
         base_mint,
         quote_mint,
         global_trade_accounts_opts,
+        transfer_hook_accounts,
+        gate_credential_opt,
+        event_queue_opt,
     } = swap_context;
 
     let (existing_seat_index, trader_index, initial_base_atoms, initial_quote_atoms) = {
         let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
         let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.assert_market_active()?;
 
-        // Claim seat if needed
+        // Claim seat if needed. Existing seats already passed the gate and
+        // rely on the cancel only flag when revoked.
         let existing_seat_index: DataIndex = dynamic_account.get_trader_index(owner.key);
         if existing_seat_index == NIL {
+            assert_gate_passed(&dynamic_account, market.key, owner.key, gate_credential_opt)?;
             dynamic_account.claim_seat(owner.key)?;
         }
         let trader_index: DataIndex = dynamic_account.get_trader_index(owner.key);
@@ -114,306 +222,457 @@ pub(crate) fn process_swap_core(
     // free block for the reverse order.
     expand_market_if_needed(&payer, &market)?;
 
-    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
-    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
-
-    let SwapParams {
-        in_atoms,
-        out_atoms,
-        is_base_in,
-        is_exact_in,
-    } = params;
-
-    trace!("swap in_atoms:{in_atoms} out_atoms:{out_atoms} is_base_in:{is_base_in} is_exact_in:{is_exact_in}");
-
-    // This check is redundant with the check that will be done within token
-    // program on deposit, but it is done here to future proof in case we later
-    // remove checked math.
-    // This actually adds a new restriction that the wallet can fully fund the
-    // swap instead of a combination of wallet and existing withdrawable
-    // balance.
-    if is_exact_in {
-        if is_base_in {
+    // Borrow in a scope so the market can be expanded after a remainder rests.
+    let order_index: DataIndex = {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+
+        let limit_price: Option<QuoteAtomsPerBaseAtom> = params.try_limit_price()?;
+        let SwapParams {
+            in_atoms,
+            out_atoms,
+            is_base_in,
+            is_exact_in,
+            self_trade_behavior,
+            rest_remainder,
+            native_sol,
+            ..
+        } = params;
+        let rest_price: Option<QuoteAtomsPerBaseAtom> = if rest_remainder {
             require!(
-                in_atoms <= trader_base_account.get_balance_atoms(),
-                ManifestError::Overflow,
-                "Insufficient base in atoms for swap has: {} requires: {}",
-                trader_base_account.get_balance_atoms(),
-                in_atoms,
+                limit_price.is_some(),
+                ManifestError::InvalidPlaceOrderFromWalletParams,
+                "Resting the swap remainder requires a limit price",
             )?;
+            limit_price
         } else {
-            require!(
-                in_atoms <= trader_quote_account.get_balance_atoms(),
-                ManifestError::Overflow,
-                "Insufficient quote in atoms for swap has: {} requires: {}",
-                trader_quote_account.get_balance_atoms(),
-                in_atoms,
-            )?;
-        }
-    }
+            None
+        };
 
-    // this is a virtual credit to ensure matching always proceeds
-    // net token transfers will be handled later
-    dynamic_account.deposit(trader_index, in_atoms, is_base_in)?;
+        trace!("swap in_atoms:{in_atoms} out_atoms:{out_atoms} is_base_in:{is_base_in} is_exact_in:{is_exact_in}");
 
-    // 4 cases:
-    // 1. Exact in base. Simplest case, just use the base atoms given.
-    // 2. Exact in quote. Search the asks for the number of base atoms in bids to match.
-    // 3. Exact out quote. Search the bids for the number of base atoms needed to match to get the right quote out.
-    // 4. Exact out base. Use the number of out atoms as the number of atoms to place_order against.
-    let base_atoms: BaseAtoms = if is_exact_in {
-        if is_base_in {
-            // input=desired(base) output=min(quote)
-            BaseAtoms::new(in_atoms)
+        let is_native_sol_base: bool =
+            native_sol && *dynamic_account.fixed.get_base_mint() == spl_token::native_mint::id();
+        let is_native_sol_quote: bool =
+            native_sol && *dynamic_account.fixed.get_quote_mint() == spl_token::native_mint::id();
+        let native_sol_bump: u8 = if native_sol {
+            require!(
+                is_native_sol_base || is_native_sol_quote,
+                ManifestError::InvalidNativeSol,
+                "Market does not trade the native mint",
+            )?;
+            let native_sol_account: &TokenAccountInfo = if is_native_sol_base {
+                &trader_base_account
+            } else {
+                &trader_quote_account
+            };
+            let (expected_native_sol_key, native_sol_bump) = get_native_sol_address(owner.key);
+            require!(
+                expected_native_sol_key == *native_sol_account.key,
+                ManifestError::InvalidNativeSol,
+                "Unexpected native sol address {} expected {}",
+                native_sol_account.key,
+                expected_native_sol_key,
+            )?;
+            native_sol_bump
         } else {
-            // input=desired(quote)* output=min(base)
-            // round down base amount to not cross quote limit
-            dynamic_account.impact_base_atoms(
-                true,
-                QuoteAtoms::new(in_atoms),
-                &global_trade_accounts_opts,
-            )?
-        }
-    } else {
-        if is_base_in {
-            // input=max(base) output=desired(quote)
-            // round up base amount to ensure not staying below quote limit
-            dynamic_account.impact_base_atoms(
-                false,
-                QuoteAtoms::new(out_atoms),
-                &global_trade_accounts_opts,
-            )?
+            0
+        };
+        let (is_native_sol_in, is_native_sol_out): (bool, bool) = if is_base_in {
+            (is_native_sol_base, is_native_sol_quote)
         } else {
-            // input=max(quote) output=desired(base)
-            BaseAtoms::new(out_atoms)
-        }
-    };
-
-    // Note that in the case of fully exhausting the book, exact in/out will not
-    // be respected. It should be treated as a desired in/out. This pushes the
-    // burden of checking the results onto the caller program.
-
-    // Example case is exact quote in. User wants exact quote in of 1_000_000
-    // and min base out of 1_000. Suppose they fully exhaust the book and get
-    // out 2_000 but that is not enough to fully use the entire 1_000_000. In
-    // this case the ix will succeed.
+            (is_native_sol_quote, is_native_sol_base)
+        };
 
-    // Another interesting case is exact quote out. Suppose the user is doing
-    // exact quote out 1_000_000 with max_base_in of 1_000. If it fully exhausts
-    // the book without using the entire max_base_in and that is still not
-    // enough for the exact quote amount, the transaction will still succeed.
+        // This check is redundant with the check that will be done within token
+        // program on deposit, but it is done here to future proof in case we later
+        // remove checked math.
+        // This actually adds a new restriction that the wallet can fully fund the
+        // swap instead of a combination of wallet and existing withdrawable
+        // balance.
+        if is_exact_in {
+            if is_native_sol_in {
+                require!(
+                    in_atoms <= payer.lamports(),
+                    ManifestError::Overflow,
+                    "Insufficient lamports for swap has: {} requires: {}",
+                    payer.lamports(),
+                    in_atoms,
+                )?;
+            } else if is_base_in {
+                require!(
+                    in_atoms <= trader_base_account.get_balance_atoms(),
+                    ManifestError::Overflow,
+                    "Insufficient base in atoms for swap has: {} requires: {}",
+                    trader_base_account.get_balance_atoms(),
+                    in_atoms,
+                )?;
+            } else {
+                require!(
+                    in_atoms <= trader_quote_account.get_balance_atoms(),
+                    ManifestError::Overflow,
+                    "Insufficient quote in atoms for swap has: {} requires: {}",
+                    trader_quote_account.get_balance_atoms(),
+                    in_atoms,
+                )?;
+            }
+        }
 
-    let price: QuoteAtomsPerBaseAtom = if is_base_in {
-        QuoteAtomsPerBaseAtom::MIN
-    } else {
-        QuoteAtomsPerBaseAtom::MAX
-    };
-    let last_valid_slot: u32 = NO_EXPIRATION_LAST_VALID_SLOT;
-    let order_type: OrderType = OrderType::ImmediateOrCancel;
-
-    trace!("swap in:{in_atoms} out:{out_atoms} base/quote:{is_base_in} in/out:{is_exact_in} base:{base_atoms} price:{price}",);
-
-    let AddOrderToMarketResult {
-        base_atoms_traded,
-        quote_atoms_traded,
-        order_sequence_number,
-        order_index,
-        ..
-    } = place_order(
-        &mut dynamic_account,
-        AddOrderToMarketArgs {
-            market: *market.key,
-            trader_index,
-            num_base_atoms: base_atoms,
-            price,
-            is_bid: !is_base_in,
-            last_valid_slot,
-            order_type,
-            global_trade_accounts_opts: &global_trade_accounts_opts,
-            current_slot: None,
-        },
-    )?;
-
-    if is_exact_in {
-        let out_atoms_traded: u64 = if is_base_in {
-            quote_atoms_traded.as_u64()
+        // this is a virtual credit to ensure matching always proceeds
+        // net token transfers will be handled later
+        dynamic_account.deposit(trader_index, in_atoms, is_base_in)?;
+
+        // 4 cases:
+        // 1. Exact in base. Simplest case, just use the base atoms given.
+        // 2. Exact in quote. Search the asks for the number of base atoms in bids to match.
+        // 3. Exact out quote. Search the bids for the number of base atoms needed to match to get the right quote out.
+        // 4. Exact out base. Use the number of out atoms as the number of atoms to place_order against.
+        let base_atoms: BaseAtoms = if is_exact_in {
+            if is_base_in {
+                // input=desired(base) output=min(quote)
+                BaseAtoms::new(in_atoms)
+            } else if let Some(rest_price) = rest_price {
+                // input=desired(quote) output=min(base)
+                // the remainder rests at the limit price so size the order there,
+                // round down base amount to not cross quote limit
+                rest_price.checked_base_for_quote(QuoteAtoms::new(in_atoms), false)?
+            } else {
+                // input=desired(quote)* output=min(base)
+                // round down base amount to not cross quote limit
+                dynamic_account.impact_base_atoms(
+                    true,
+                    QuoteAtoms::new(in_atoms),
+                    &global_trade_accounts_opts,
+                )?
+            }
         } else {
-            base_atoms_traded.as_u64()
+            if is_base_in {
+                // input=max(base) output=desired(quote)
+                if let Some(rest_price) = rest_price {
+                    // the remainder rests at the limit price so size the order
+                    // there, round up base amount to ensure not staying below
+                    // quote limit
+                    rest_price.checked_base_for_quote(QuoteAtoms::new(out_atoms), true)?
+                } else {
+                    // round up base amount to ensure not staying below quote limit
+                    dynamic_account.impact_base_atoms(
+                        false,
+                        QuoteAtoms::new(out_atoms),
+                        &global_trade_accounts_opts,
+                    )?
+                }
+            } else {
+                // input=max(quote) output=desired(base)
+                BaseAtoms::new(out_atoms)
+            }
         };
-        require!(
-            out_atoms <= out_atoms_traded,
-            ManifestError::InsufficientOut,
-            "Insufficient out atoms returned. Minimum: {} Actual: {}",
-            out_atoms,
-            out_atoms_traded
-        )?;
-    } else {
-        let in_atoms_traded = if is_base_in {
-            base_atoms_traded.as_u64()
+
+        // Note that in the case of fully exhausting the book, exact in/out will not
+        // be respected. It should be treated as a desired in/out. This pushes the
+        // burden of checking the results onto the caller program.
+
+        // Example case is exact quote in. User wants exact quote in of 1_000_000
+        // and min base out of 1_000. Suppose they fully exhaust the book and get
+        // out 2_000 but that is not enough to fully use the entire 1_000_000. In
+        // this case the ix will succeed.
+
+        // Another interesting case is exact quote out. Suppose the user is doing
+        // exact quote out 1_000_000 with max_base_in of 1_000. If it fully exhausts
+        // the book without using the entire max_base_in and that is still not
+        // enough for the exact quote amount, the transaction will still succeed.
+
+        // The same applies when a limit price is given. Matching stops at the
+        // limit and the partial fill is settled as long as min out is satisfied.
+        let price: QuoteAtomsPerBaseAtom = match limit_price {
+            Some(limit_price) => limit_price,
+            None => {
+                if is_base_in {
+                    QuoteAtomsPerBaseAtom::MIN
+                } else {
+                    QuoteAtomsPerBaseAtom::MAX
+                }
+            }
+        };
+        let last_valid_slot: u32 = NO_EXPIRATION_LAST_VALID_SLOT;
+        let order_type: OrderType = if rest_remainder {
+            OrderType::Limit
         } else {
-            quote_atoms_traded.as_u64()
+            OrderType::ImmediateOrCancel
         };
-        require!(
-            in_atoms >= in_atoms_traded,
-            ManifestError::InsufficientOut,
-            "Excessive in atoms charged. Maximum: {} Actual: {}",
-            in_atoms,
-            in_atoms_traded
+
+        trace!("swap in:{in_atoms} out:{out_atoms} base/quote:{is_base_in} in/out:{is_exact_in} base:{base_atoms} price:{price}",);
+
+        let AddOrderToMarketResult {
+            base_atoms_traded,
+            quote_atoms_traded,
+            order_sequence_number,
+            order_index,
+            taker_fee_atoms,
+        } = place_order(
+            &mut dynamic_account,
+            AddOrderToMarketArgs {
+                market: *market.key,
+                trader_index,
+                num_base_atoms: base_atoms,
+                price,
+                is_bid: !is_base_in,
+                last_valid_slot,
+                order_type,
+                global_trade_accounts_opts: &global_trade_accounts_opts,
+                current_slot: None,
+                self_trade_behavior,
+                client_order_id: 0,
+                reverse_price_bounds: ReversePriceBounds::default(),
+                iceberg_display_base_atoms: BaseAtoms::ZERO,
+                event_queue_opt: &event_queue_opt,
+            },
         )?;
-    }
 
-    let (end_base_atoms, end_quote_atoms) = dynamic_account.get_trader_balance(owner.key);
-
-    let extra_base_atoms: BaseAtoms = end_base_atoms.checked_sub(initial_base_atoms)?;
-    let extra_quote_atoms: QuoteAtoms = end_quote_atoms.checked_sub(initial_quote_atoms)?;
-
-    // Transfer tokens
-    if is_base_in {
-        // Trader is depositing base.
-
-        // In order to make the trade, we previously credited the seat with the
-        // maximum they could possibly need,
-        // The amount to take from them is repaying the full credit, minus the
-        // unused amount.
-        let initial_credit_base_atoms: BaseAtoms = BaseAtoms::new(in_atoms);
-
-        if *token_program_base.key == spl_token_2022::id() {
-            spl_token_2022_transfer_from_trader_to_vault(
-                &token_program_base,
-                &trader_base_account,
-                base_mint,
-                dynamic_account.fixed.get_base_mint(),
-                &base_vault,
-                &owner,
-                (initial_credit_base_atoms.checked_sub(extra_base_atoms)?).as_u64(),
-                dynamic_account.fixed.get_base_mint_decimals(),
+        if is_exact_in {
+            // Taker fees come out of the out token, so the minimum applies to what
+            // the trader actually receives.
+            let out_atoms_traded: u64 = if is_base_in {
+                quote_atoms_traded.as_u64()
+            } else {
+                base_atoms_traded.as_u64()
+            }
+            .checked_sub(taker_fee_atoms)
+            .ok_or(ManifestError::Overflow)?;
+            require!(
+                out_atoms <= out_atoms_traded,
+                ManifestError::InsufficientOut,
+                "Insufficient out atoms returned. Minimum: {} Actual: {}",
+                out_atoms,
+                out_atoms_traded
             )?;
         } else {
-            spl_token_transfer_from_trader_to_vault(
-                &token_program_base,
-                &trader_base_account,
-                &base_vault,
-                &owner,
-                (initial_credit_base_atoms.checked_sub(extra_base_atoms)?).as_u64(),
+            // Exact out is matched before taker fees, so the trader receives
+            // out_atoms less the fee on markets with fees. Callers that need a net
+            // amount should gross up out_atoms by the taker fee themselves.
+            let in_atoms_traded = if is_base_in {
+                base_atoms_traded.as_u64()
+            } else {
+                quote_atoms_traded.as_u64()
+            };
+            require!(
+                in_atoms >= in_atoms_traded,
+                ManifestError::InsufficientOut,
+                "Excessive in atoms charged. Maximum: {} Actual: {}",
+                in_atoms,
+                in_atoms_traded
             )?;
         }
 
-        // Give all but what started there.
-        let quote_vault_bump: u8 = dynamic_account.fixed.get_quote_vault_bump();
-        if *token_program_quote.key == spl_token_2022::id() {
-            spl_token_2022_transfer_from_vault_to_trader(
-                &token_program_quote,
-                quote_mint,
-                dynamic_account.fixed.get_quote_mint(),
-                &quote_vault,
-                &trader_quote_account,
-                extra_quote_atoms.as_u64(),
-                dynamic_account.fixed.get_quote_mint_decimals(),
-                market.key,
-                quote_vault_bump,
-            )?;
+        let (end_base_atoms, end_quote_atoms) = dynamic_account.get_trader_balance(owner.key);
+
+        let extra_base_atoms: BaseAtoms = end_base_atoms.checked_sub(initial_base_atoms)?;
+        let extra_quote_atoms: QuoteAtoms = end_quote_atoms.checked_sub(initial_quote_atoms)?;
+
+        // Transfer tokens
+        if is_base_in {
+            // Trader is depositing base.
+
+            // In order to make the trade, we previously credited the seat with the
+            // maximum they could possibly need,
+            // The amount to take from them is repaying the full credit, minus the
+            // unused amount.
+            let initial_credit_base_atoms: BaseAtoms = BaseAtoms::new(in_atoms);
+
+            if is_native_sol_in {
+                wrap_native_sol_to_vault(
+                    &Signer::new(&payer)?,
+                    &token_program_base,
+                    &base_vault,
+                    &system_program,
+                    (initial_credit_base_atoms.checked_sub(extra_base_atoms)?).as_u64(),
+                )?;
+            } else if *token_program_base.key == spl_token_2022::id() {
+                spl_token_2022_transfer_from_trader_to_vault(
+                    &token_program_base,
+                    &trader_base_account,
+                    base_mint,
+                    dynamic_account.fixed.get_base_mint(),
+                    &base_vault,
+                    &owner,
+                    (initial_credit_base_atoms.checked_sub(extra_base_atoms)?).as_u64(),
+                    dynamic_account.fixed.get_base_mint_decimals(),
+                    transfer_hook_accounts,
+                )?;
+            } else {
+                spl_token_transfer_from_trader_to_vault(
+                    &token_program_base,
+                    &trader_base_account,
+                    &base_vault,
+                    &owner,
+                    (initial_credit_base_atoms.checked_sub(extra_base_atoms)?).as_u64(),
+                )?;
+            }
+
+            // Give all but what started there.
+            let quote_vault_bump: u8 = dynamic_account.fixed.get_quote_vault_bump();
+            if is_native_sol_out {
+                unwrap_native_sol_from_vault(
+                    &Signer::new(&payer)?,
+                    &owner,
+                    &token_program_quote,
+                    quote_mint.as_ref().ok_or(ManifestError::InvalidNativeSol)?,
+                    &quote_vault,
+                    &trader_quote_account,
+                    &system_program,
+                    native_sol_bump,
+                    extra_quote_atoms.as_u64(),
+                    market.key,
+                    quote_vault_bump,
+                )?;
+            } else if *token_program_quote.key == spl_token_2022::id() {
+                spl_token_2022_transfer_from_vault_to_trader(
+                    &token_program_quote,
+                    quote_mint,
+                    dynamic_account.fixed.get_quote_mint(),
+                    &quote_vault,
+                    &trader_quote_account,
+                    extra_quote_atoms.as_u64(),
+                    dynamic_account.fixed.get_quote_mint_decimals(),
+                    market.key,
+                    quote_vault_bump,
+                    transfer_hook_accounts,
+                )?;
+            } else {
+                spl_token_transfer_from_vault_to_trader(
+                    &token_program_quote,
+                    &quote_vault,
+                    &trader_quote_account,
+                    extra_quote_atoms.as_u64(),
+                    market.key,
+                    quote_vault_bump,
+                    dynamic_account.fixed.get_quote_mint(),
+                )?;
+            }
         } else {
-            spl_token_transfer_from_vault_to_trader(
-                &token_program_quote,
-                &quote_vault,
-                &trader_quote_account,
-                extra_quote_atoms.as_u64(),
-                market.key,
-                quote_vault_bump,
-                dynamic_account.fixed.get_quote_mint(),
-            )?;
-        }
-    } else {
-        // Trader is depositing quote.
-
-        // In order to make the trade, we previously credited the seat with the
-        // maximum they could possibly need.
-        // The amount to take from them is repaying the full credit, minus the
-        // unused amount.
-        let initial_credit_quote_atoms: QuoteAtoms = QuoteAtoms::new(in_atoms);
-        if *token_program_quote.key == spl_token_2022::id() {
-            spl_token_2022_transfer_from_trader_to_vault(
-                &token_program_quote,
-                &trader_quote_account,
-                quote_mint,
-                dynamic_account.fixed.get_quote_mint(),
-                &quote_vault,
-                &owner,
-                (initial_credit_quote_atoms.checked_sub(extra_quote_atoms)?).as_u64(),
-                dynamic_account.fixed.get_quote_mint_decimals(),
-            )?;
-        } else {
-            spl_token_transfer_from_trader_to_vault(
-                &token_program_quote,
-                &trader_quote_account,
-                &quote_vault,
-                &owner,
-                (initial_credit_quote_atoms.checked_sub(extra_quote_atoms)?).as_u64(),
-            )?;
+            // Trader is depositing quote.
+
+            // In order to make the trade, we previously credited the seat with the
+            // maximum they could possibly need.
+            // The amount to take from them is repaying the full credit, minus the
+            // unused amount.
+            let initial_credit_quote_atoms: QuoteAtoms = QuoteAtoms::new(in_atoms);
+            if is_native_sol_in {
+                wrap_native_sol_to_vault(
+                    &Signer::new(&payer)?,
+                    &token_program_quote,
+                    &quote_vault,
+                    &system_program,
+                    (initial_credit_quote_atoms.checked_sub(extra_quote_atoms)?).as_u64(),
+                )?;
+            } else if *token_program_quote.key == spl_token_2022::id() {
+                spl_token_2022_transfer_from_trader_to_vault(
+                    &token_program_quote,
+                    &trader_quote_account,
+                    quote_mint,
+                    dynamic_account.fixed.get_quote_mint(),
+                    &quote_vault,
+                    &owner,
+                    (initial_credit_quote_atoms.checked_sub(extra_quote_atoms)?).as_u64(),
+                    dynamic_account.fixed.get_quote_mint_decimals(),
+                    transfer_hook_accounts,
+                )?;
+            } else {
+                spl_token_transfer_from_trader_to_vault(
+                    &token_program_quote,
+                    &trader_quote_account,
+                    &quote_vault,
+                    &owner,
+                    (initial_credit_quote_atoms.checked_sub(extra_quote_atoms)?).as_u64(),
+                )?;
+            }
+
+            // Give all but what started there.
+            let base_vault_bump: u8 = dynamic_account.fixed.get_base_vault_bump();
+            if is_native_sol_out {
+                unwrap_native_sol_from_vault(
+                    &Signer::new(&payer)?,
+                    &owner,
+                    &token_program_base,
+                    base_mint.as_ref().ok_or(ManifestError::InvalidNativeSol)?,
+                    &base_vault,
+                    &trader_base_account,
+                    &system_program,
+                    native_sol_bump,
+                    extra_base_atoms.as_u64(),
+                    market.key,
+                    base_vault_bump,
+                )?;
+            } else if *token_program_base.key == spl_token_2022::id() {
+                spl_token_2022_transfer_from_vault_to_trader(
+                    &token_program_base,
+                    base_mint,
+                    dynamic_account.get_base_mint(),
+                    &base_vault,
+                    &trader_base_account,
+                    extra_base_atoms.as_u64(),
+                    dynamic_account.fixed.get_base_mint_decimals(),
+                    market.key,
+                    base_vault_bump,
+                    transfer_hook_accounts,
+                )?;
+            } else {
+                spl_token_transfer_from_vault_to_trader(
+                    &token_program_base,
+                    &base_vault,
+                    &trader_base_account,
+                    extra_base_atoms.as_u64(),
+                    market.key,
+                    base_vault_bump,
+                    dynamic_account.get_base_mint(),
+                )?;
+            }
         }
 
-        // Give all but what started there.
-        let base_vault_bump: u8 = dynamic_account.fixed.get_base_vault_bump();
-        if *token_program_base.key == spl_token_2022::id() {
-            spl_token_2022_transfer_from_vault_to_trader(
-                &token_program_base,
-                base_mint,
-                dynamic_account.get_base_mint(),
-                &base_vault,
-                &trader_base_account,
-                extra_base_atoms.as_u64(),
-                dynamic_account.fixed.get_base_mint_decimals(),
-                market.key,
-                base_vault_bump,
-            )?;
+        // Keep the seat when the remainder rested since it holds the order funds.
+        if existing_seat_index == NIL && order_index == NIL {
+            dynamic_account.release_seat(owner.key)?;
         } else {
-            spl_token_transfer_from_vault_to_trader(
-                &token_program_base,
-                &base_vault,
-                &trader_base_account,
-                extra_base_atoms.as_u64(),
-                market.key,
-                base_vault_bump,
-                dynamic_account.get_base_mint(),
-            )?;
+            // Withdraw in case there already was a seat so it doesnt mess with their
+            // balances. Need to withdraw base and quote in case the order wasnt fully
+            // filled.
+            dynamic_account.withdraw(trader_index, extra_base_atoms.as_u64(), true)?;
+            dynamic_account.withdraw(trader_index, extra_quote_atoms.as_u64(), false)?;
         }
-    }
+        // Verify that there wasnt a reverse order that took the only spare block.
+        // A resting remainder may take it too, that is refilled below.
+        require!(
+            order_index != NIL || dynamic_account.has_free_block(),
+            ManifestError::InvalidFreeList,
+            "Cannot swap against a reverse order unless there is a free block"
+        )?;
 
-    if existing_seat_index == NIL {
-        dynamic_account.release_seat(owner.key)?;
-    } else {
-        // Withdraw in case there already was a seat so it doesnt mess with their
-        // balances. Need to withdraw base and quote in case the order wasnt fully
-        // filled.
-        dynamic_account.withdraw(trader_index, extra_base_atoms.as_u64(), true)?;
-        dynamic_account.withdraw(trader_index, extra_quote_atoms.as_u64(), false)?;
+        emit_stack(PlaceOrderLogV2 {
+            market: *market.key,
+            trader: *owner.key,
+            payer: *payer.key,
+            base_atoms,
+            price,
+            order_type,
+            is_bid: (!is_base_in).into(),
+            _padding: [0; 6],
+            client_order_id: 0,
+            order_sequence_number,
+            order_index,
+            last_valid_slot,
+        })?;
+
+        order_index
+    };
+
+    if order_index != NIL {
+        expand_market_if_needed(&payer, &market)?;
     }
-    // Verify that there wasnt a reverse order that took the only spare block.
-    require!(
-        dynamic_account.has_free_block(),
-        ManifestError::InvalidFreeList,
-        "Cannot swap against a reverse order unless there is a free block"
-    )?;
-
-    emit_stack(PlaceOrderLogV2 {
-        market: *market.key,
-        trader: *owner.key,
-        payer: *payer.key,
-        base_atoms,
-        price,
-        order_type,
-        is_bid: (!is_base_in).into(),
-        _padding: [0; 6],
-        order_sequence_number,
-        order_index,
-        last_valid_slot,
-    })?;
 
     Ok(())
 }
 
 #[cfg(not(feature = "certora"))]
-fn place_order(
+pub(crate) fn place_order(
     dynamic_account: &mut MarketRefMut,
     args: AddOrderToMarketArgs,
 ) -> Result<AddOrderToMarketResult, ProgramError> {
@@ -421,7 +680,7 @@ fn place_order(
 }
 
 #[cfg(feature = "certora")]
-fn place_order(
+pub(crate) fn place_order(
     market: &mut MarketRefMut,
     args: AddOrderToMarketArgs,
 ) -> Result<AddOrderToMarketResult, ProgramError> {
@@ -430,7 +689,7 @@ fn place_order(
 
 /** Transfer from base (quote) trader to base (quote) vault using SPL Token **/
 #[cfg(not(feature = "certora"))]
-fn spl_token_transfer_from_trader_to_vault<'a, 'info>(
+pub(crate) fn spl_token_transfer_from_trader_to_vault<'a, 'info>(
     token_program: &TokenProgram<'a, 'info>,
     trader_account: &TokenAccountInfo<'a, 'info>,
     vault: &TokenAccountInfo<'a, 'info>,
@@ -456,7 +715,7 @@ fn spl_token_transfer_from_trader_to_vault<'a, 'info>(
 }
 #[cfg(feature = "certora")]
 /** (Summary) Transfer from base (quote) trader to base (quote) vault using SPL Token **/
-fn spl_token_transfer_from_trader_to_vault<'a, 'info>(
+pub(crate) fn spl_token_transfer_from_trader_to_vault<'a, 'info>(
     _token_program: &TokenProgram<'a, 'info>,
     trader_account: &TokenAccountInfo<'a, 'info>,
     vault: &TokenAccountInfo<'a, 'info>,
@@ -468,7 +727,7 @@ fn spl_token_transfer_from_trader_to_vault<'a, 'info>(
 
 /** Transfer from base (quote) trader to base (quote) vault using SPL Token 2022 **/
 #[cfg(not(feature = "certora"))]
-fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
+pub(crate) fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
     token_program: &TokenProgram<'a, 'info>,
     trader_account: &TokenAccountInfo<'a, 'info>,
     mint: Option<MintAccountInfo<'a, 'info>>,
@@ -477,7 +736,23 @@ fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
     owner: &Signer<'a, 'info>,
     amount: u64,
     decimals: u8,
+    transfer_hook_accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
+    // Resolving hook accounts needs the mint extensions, so only pay for it
+    // when the caller passed some.
+    if !transfer_hook_accounts.is_empty() {
+        return spl_token_2022::onchain::invoke_transfer_checked(
+            token_program.key,
+            trader_account.as_ref().clone(),
+            mint.unwrap().as_ref().clone(),
+            vault.as_ref().clone(),
+            owner.as_ref().clone(),
+            transfer_hook_accounts,
+            amount,
+            decimals,
+            &[],
+        );
+    }
     invoke(
         &spl_token_2022::instruction::transfer_checked(
             token_program.key,
@@ -501,7 +776,7 @@ fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
 
 #[cfg(feature = "certora")]
 /** (Summary) Transfer from base (quote) trader to base (quote) vault using SPL Token 2022 **/
-fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
+pub(crate) fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
     _token_program: &TokenProgram<'a, 'info>,
     trader_account: &TokenAccountInfo<'a, 'info>,
     _mint: Option<MintAccountInfo<'a, 'info>>,
@@ -510,13 +785,14 @@ fn spl_token_2022_transfer_from_trader_to_vault<'a, 'info>(
     owner: &Signer<'a, 'info>,
     amount: u64,
     _decimals: u8,
+    _transfer_hook_accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
     spl_token_2022_transfer(trader_account.info, vault.info, owner.info, amount)
 }
 
 /** Transfer from base (quote) vault to base (quote) trader using SPL Token **/
 #[cfg(not(feature = "certora"))]
-fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
+pub(crate) fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
     token_program: &TokenProgram<'a, 'info>,
     vault: &TokenAccountInfo<'a, 'info>,
     trader_account: &TokenAccountInfo<'a, 'info>,
@@ -545,7 +821,7 @@ fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
 
 #[cfg(feature = "certora")]
 /** (Summary) Transfer from base (quote) vault to base (quote) trader using SPL Token **/
-fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
+pub(crate) fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
     _token_program: &TokenProgram<'a, 'info>,
     vault: &TokenAccountInfo<'a, 'info>,
     trader_account: &TokenAccountInfo<'a, 'info>,
@@ -559,7 +835,7 @@ fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
 
 /** Transfer from base (quote) vault to base (quote) trader using SPL Token 2022 **/
 #[cfg(not(feature = "certora"))]
-fn spl_token_2022_transfer_from_vault_to_trader<'a, 'info>(
+pub(crate) fn spl_token_2022_transfer_from_vault_to_trader<'a, 'info>(
     token_program: &TokenProgram<'a, 'info>,
     mint: Option<MintAccountInfo<'a, 'info>>,
     mint_pubkey: &Pubkey,
@@ -569,7 +845,23 @@ fn spl_token_2022_transfer_from_vault_to_trader<'a, 'info>(
     decimals: u8,
     market_key: &Pubkey,
     vault_bump: u8,
+    transfer_hook_accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
+    // Resolving hook accounts needs the mint extensions, so only pay for it
+    // when the caller passed some.
+    if !transfer_hook_accounts.is_empty() {
+        return spl_token_2022::onchain::invoke_transfer_checked(
+            token_program.key,
+            vault.as_ref().clone(),
+            mint.unwrap().as_ref().clone(),
+            trader_account.as_ref().clone(),
+            vault.as_ref().clone(),
+            transfer_hook_accounts,
+            amount,
+            decimals,
+            market_vault_seeds_with_bump!(market_key, mint_pubkey, vault_bump),
+        );
+    }
     solana_program::program::invoke_signed(
         &spl_token_2022::instruction::transfer_checked(
             token_program.key,
@@ -593,7 +885,7 @@ fn spl_token_2022_transfer_from_vault_to_trader<'a, 'info>(
 
 #[cfg(feature = "certora")]
 /** (Summary) Transfer from base (quote) vault to base (quote) trader using SPL Token 2022 **/
-fn spl_token_2022_transfer_from_vault_to_trader<'a, 'info>(
+pub(crate) fn spl_token_2022_transfer_from_vault_to_trader<'a, 'info>(
     _token_program: &TokenProgram<'a, 'info>,
     _mint: Option<MintAccountInfo<'a, 'info>>,
     _mint_pubkey: &Pubkey,
@@ -603,6 +895,38 @@ fn spl_token_2022_transfer_from_vault_to_trader<'a, 'info>(
     _decimals: u8,
     _market_key: &Pubkey,
     _vault_bump: u8,
+    _transfer_hook_accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
     spl_token_2022_transfer(vault.info, trader_account.info, vault.info, amount)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_decode_legacy() {
+        // Layout from before self trade prevention.
+        let data: Vec<u8> = (1_u64, 2_u64, true, false).try_to_vec().unwrap();
+        let params: SwapParams = SwapParams::try_from_versioned_slice(&data).unwrap();
+        assert_eq!(params.in_atoms, 1);
+        assert_eq!(params.out_atoms, 2);
+        assert!(params.is_base_in);
+        assert!(!params.is_exact_in);
+        assert_eq!(params.self_trade_behavior, SelfTradeBehavior::Allow);
+        assert_eq!(params.limit_price_mantissa, 0);
+        assert!(!params.rest_remainder);
+        assert!(!params.native_sol);
+
+        // Layout with only self trade prevention appended.
+        let data: Vec<u8> = (1_u64, 2_u64, true, false, SelfTradeBehavior::CancelTaker)
+            .try_to_vec()
+            .unwrap();
+        let params: SwapParams = SwapParams::try_from_versioned_slice(&data).unwrap();
+        assert_eq!(params.self_trade_behavior, SelfTradeBehavior::CancelTaker);
+        assert_eq!(params.limit_price_mantissa, 0);
+
+        // Truncated in the middle of a field.
+        assert!(SwapParams::try_from_versioned_slice(&data[..17]).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+use std::cell::{Ref, RefMut};
+
+use super::{
+    get_mut_dynamic_account,
+    shared::assert_gate_passed,
+    swap::{
+        place_order, spl_token_2022_transfer_from_trader_to_vault,
+        spl_token_2022_transfer_from_vault_to_trader, spl_token_transfer_from_trader_to_vault,
+        spl_token_transfer_from_vault_to_trader,
+    },
+};
+use crate::{
+    logs::{emit_stack, PlaceOrderLogV2},
+    program::{expand_market_if_needed, ManifestError},
+    quantities::{BaseAtoms, QuoteAtoms, QuoteAtomsPerBaseAtom, WrapperU64},
+    require,
+    state::{
+        AddOrderToMarketArgs, AddOrderToMarketResult, EventQueueFixed, MarketFixed, MarketRefMut,
+        OrderType, ReversePriceBounds, SelfTradeBehavior, NO_EXPIRATION_LAST_VALID_SLOT,
+    },
+    validation::{
+        loaders::{GlobalTradeAccounts, SwapRouteContext},
+        ManifestAccountInfo, MintAccountInfo, Signer, TokenAccountInfo, TokenProgram,
+    },
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::{trace, DataIndex, NIL};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct SwapRouteParams {
+    // Exact in of the first mint. As with swap, this is desired, the unused
+    // remainder stays in the trader wallet if the first book is exhausted.
+    pub in_atoms: u64,
+    // Minimum of the last mint received after taker fees on both hops.
+    pub min_out_atoms: u64,
+}
+
+impl SwapRouteParams {
+    pub fn new(in_atoms: u64, min_out_atoms: u64) -> Self {
+        SwapRouteParams {
+            in_atoms,
+            min_out_atoms,
+        }
+    }
+}
+
+/// Exact in swap through two markets that share a mint. The intermediate
+/// tokens move directly from the first market vault to the second, only the
+/// part the second market could not use is sent to the trader.
+pub(crate) fn process_swap_route(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let params: SwapRouteParams = SwapRouteParams::try_from_slice(data)?;
+    let swap_route_context: SwapRouteContext = SwapRouteContext::load(accounts)?;
+
+    let SwapRouteContext {
+        payer,
+        owner,
+        first_market,
+        second_market,
+        in_mint,
+        intermediate_mint,
+        out_mint,
+        trader_in,
+        trader_intermediate,
+        trader_out,
+        first_in_vault,
+        first_intermediate_vault,
+        second_intermediate_vault,
+        second_out_vault,
+        token_program_in,
+        token_program_intermediate,
+        token_program_out,
+        first_event_queue_opt,
+        second_event_queue_opt,
+    } = swap_route_context;
+
+    let SwapRouteParams {
+        in_atoms,
+        min_out_atoms,
+    } = params;
+
+    trace!("swap route in_atoms:{in_atoms} min_out_atoms:{min_out_atoms}");
+
+    let (first_in_atoms_used, intermediate_atoms) = match_route_hop(
+        &payer,
+        &owner,
+        &first_market,
+        in_mint.info.key,
+        in_atoms,
+        &first_event_queue_opt,
+    )?;
+    let (second_in_atoms_used, out_atoms) = match_route_hop(
+        &payer,
+        &owner,
+        &second_market,
+        intermediate_mint.info.key,
+        intermediate_atoms,
+        &second_event_queue_opt,
+    )?;
+
+    require!(
+        min_out_atoms <= out_atoms,
+        ManifestError::InsufficientOut,
+        "Insufficient out atoms returned. Minimum: {} Actual: {}",
+        min_out_atoms,
+        out_atoms
+    )?;
+
+    // Trader pays the first market.
+    let (_, in_decimals) = get_vault_bump_and_decimals(&first_market, in_mint.info.key)?;
+    if *token_program_in.key == spl_token_2022::id() {
+        spl_token_2022_transfer_from_trader_to_vault(
+            &token_program_in,
+            &trader_in,
+            Some(in_mint.clone()),
+            in_mint.info.key,
+            &first_in_vault,
+            &owner,
+            first_in_atoms_used,
+            in_decimals,
+            // Route mints never have a transfer hook, see SwapRouteContext.
+            &[],
+        )?;
+    } else {
+        spl_token_transfer_from_trader_to_vault(
+            &token_program_in,
+            &trader_in,
+            &first_in_vault,
+            &owner,
+            first_in_atoms_used,
+        )?;
+    }
+
+    // First market pays the second market and returns anything unused.
+    let (first_intermediate_bump, intermediate_decimals) =
+        get_vault_bump_and_decimals(&first_market, intermediate_mint.info.key)?;
+    transfer_from_vault(
+        &token_program_intermediate,
+        &intermediate_mint,
+        &first_intermediate_vault,
+        &second_intermediate_vault,
+        second_in_atoms_used,
+        intermediate_decimals,
+        first_market.key,
+        first_intermediate_bump,
+    )?;
+    let unused_intermediate_atoms: u64 = intermediate_atoms - second_in_atoms_used;
+    if unused_intermediate_atoms > 0 {
+        transfer_from_vault(
+            &token_program_intermediate,
+            &intermediate_mint,
+            &first_intermediate_vault,
+            &trader_intermediate,
+            unused_intermediate_atoms,
+            intermediate_decimals,
+            first_market.key,
+            first_intermediate_bump,
+        )?;
+    }
+
+    // Second market pays the trader.
+    let (second_out_bump, out_decimals) =
+        get_vault_bump_and_decimals(&second_market, out_mint.info.key)?;
+    transfer_from_vault(
+        &token_program_out,
+        &out_mint,
+        &second_out_vault,
+        &trader_out,
+        out_atoms,
+        out_decimals,
+        second_market.key,
+        second_out_bump,
+    )?;
+
+    Ok(())
+}
+
+/// Takes in_atoms of in_mint on one market as an IOC order with a temporary
+/// seat, the same way swap does. Returns the in atoms used and out atoms
+/// received. Does not move any tokens.
+fn match_route_hop<'a, 'info>(
+    payer: &Signer<'a, 'info>,
+    owner: &Signer<'a, 'info>,
+    market: &ManifestAccountInfo<'a, 'info, MarketFixed>,
+    in_mint: &Pubkey,
+    in_atoms: u64,
+    event_queue_opt: &Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
+) -> Result<(u64, u64), ProgramError> {
+    let existing_seat_index: DataIndex = {
+        let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+        let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+        dynamic_account.assert_market_active()?;
+
+        // There is no room for gate credentials on a route, so permissioned
+        // markets need an existing seat.
+        let existing_seat_index: DataIndex = dynamic_account.get_trader_index(owner.key);
+        if existing_seat_index == NIL {
+            assert_gate_passed(&dynamic_account, market.key, owner.key, None)?;
+            dynamic_account.claim_seat(owner.key)?;
+        }
+        existing_seat_index
+    };
+
+    expand_market_if_needed(payer, market)?;
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+
+    let is_base_in: bool = dynamic_account.get_base_mint() == in_mint;
+    let trader_index: DataIndex = dynamic_account.get_trader_index(owner.key);
+    let (initial_base_atoms, initial_quote_atoms) = dynamic_account.get_trader_balance(owner.key);
+
+    // Virtual credit, net token transfers are handled by the caller.
+    dynamic_account.deposit(trader_index, in_atoms, is_base_in)?;
+
+    let no_global_trade_accounts: [Option<GlobalTradeAccounts>; 2] = [None, None];
+    let base_atoms: BaseAtoms = if is_base_in {
+        BaseAtoms::new(in_atoms)
+    } else {
+        dynamic_account.impact_base_atoms(
+            true,
+            QuoteAtoms::new(in_atoms),
+            &no_global_trade_accounts,
+        )?
+    };
+    let price: QuoteAtomsPerBaseAtom = if is_base_in {
+        QuoteAtomsPerBaseAtom::MIN
+    } else {
+        QuoteAtomsPerBaseAtom::MAX
+    };
+    let last_valid_slot: u32 = NO_EXPIRATION_LAST_VALID_SLOT;
+    let order_type: OrderType = OrderType::ImmediateOrCancel;
+
+    let AddOrderToMarketResult {
+        order_sequence_number,
+        order_index,
+        ..
+    } = place_order(
+        &mut dynamic_account,
+        AddOrderToMarketArgs {
+            market: *market.key,
+            trader_index,
+            num_base_atoms: base_atoms,
+            price,
+            is_bid: !is_base_in,
+            last_valid_slot,
+            order_type,
+            global_trade_accounts_opts: &no_global_trade_accounts,
+            current_slot: None,
+            self_trade_behavior: SelfTradeBehavior::Allow,
+            client_order_id: 0,
+            reverse_price_bounds: ReversePriceBounds::default(),
+            iceberg_display_base_atoms: BaseAtoms::ZERO,
+            event_queue_opt,
+        },
+    )?;
+
+    let (end_base_atoms, end_quote_atoms) = dynamic_account.get_trader_balance(owner.key);
+    let extra_base_atoms: BaseAtoms = end_base_atoms.checked_sub(initial_base_atoms)?;
+    let extra_quote_atoms: QuoteAtoms = end_quote_atoms.checked_sub(initial_quote_atoms)?;
+
+    if existing_seat_index == NIL {
+        dynamic_account.release_seat(owner.key)?;
+    } else {
+        dynamic_account.withdraw(trader_index, extra_base_atoms.as_u64(), true)?;
+        dynamic_account.withdraw(trader_index, extra_quote_atoms.as_u64(), false)?;
+    }
+    // Verify that there wasnt a reverse order that took the only spare block.
+    require!(
+        dynamic_account.has_free_block(),
+        ManifestError::InvalidFreeList,
+        "Cannot swap against a reverse order unless there is a free block"
+    )?;
+
+    emit_stack(PlaceOrderLogV2 {
+        market: *market.key,
+        trader: *owner.key,
+        payer: *payer.key,
+        base_atoms,
+        price,
+        order_type,
+        is_bid: (!is_base_in).into(),
+        _padding: [0; 6],
+        client_order_id: 0,
+        order_sequence_number,
+        order_index,
+        last_valid_slot,
+    })?;
+
+    if is_base_in {
+        Ok((
+            in_atoms - extra_base_atoms.as_u64(),
+            extra_quote_atoms.as_u64(),
+        ))
+    } else {
+        Ok((
+            in_atoms - extra_quote_atoms.as_u64(),
+            extra_base_atoms.as_u64(),
+        ))
+    }
+}
+
+fn get_vault_bump_and_decimals(
+    market: &ManifestAccountInfo<MarketFixed>,
+    mint: &Pubkey,
+) -> Result<(u8, u8), ProgramError> {
+    let market_fixed: Ref<MarketFixed> = market.get_fixed()?;
+    if market_fixed.get_base_mint() == mint {
+        Ok((
+            market_fixed.get_base_vault_bump(),
+            market_fixed.get_base_mint_decimals(),
+        ))
+    } else {
+        Ok((
+            market_fixed.get_quote_vault_bump(),
+            market_fixed.get_quote_mint_decimals(),
+        ))
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn transfer_from_vault<'a, 'info>(
+    token_program: &TokenProgram<'a, 'info>,
+    mint: &MintAccountInfo<'a, 'info>,
+    vault: &TokenAccountInfo<'a, 'info>,
+    destination: &TokenAccountInfo<'a, 'info>,
+    amount_atoms: u64,
+    decimals: u8,
+    market_key: &Pubkey,
+    vault_bump: u8,
+) -> ProgramResult {
+    if *token_program.key == spl_token_2022::id() {
+        spl_token_2022_transfer_from_vault_to_trader(
+            token_program,
+            Some(mint.clone()),
+            mint.info.key,
+            vault,
+            destination,
+            amount_atoms,
+            decimals,
+            market_key,
+            vault_bump,
+            // Route mints never have a transfer hook, see SwapRouteContext.
+            &[],
+        )
+    } else {
+        spl_token_transfer_from_vault_to_trader(
+            token_program,
+            vault,
+            destination,
+            amount_atoms,
+            market_key,
+            vault_bump,
+            mint.info.key,
+        )
+    }
+}
@@ -0,0 +1,48 @@
+use std::cell::RefMut;
+
+use crate::{
+    logs::{emit_stack, MarketAuthorityLog},
+    program::get_mut_dynamic_account,
+    state::MarketRefMut,
+    validation::loaders::MarketAuthorityContext,
+};
+use borsh::{BorshDeserialize, BorshSerialize};
+use hypertree::trace;
+use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct TransferMarketAuthorityParams {
+    /// Default pubkey renounces the authority.
+    pub new_authority: Pubkey,
+}
+
+impl TransferMarketAuthorityParams {
+    pub fn new(new_authority: Pubkey) -> Self {
+        TransferMarketAuthorityParams { new_authority }
+    }
+}
+
+pub(crate) fn process_transfer_market_authority(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let market_authority_context: MarketAuthorityContext = MarketAuthorityContext::load(accounts)?;
+    let TransferMarketAuthorityParams { new_authority } =
+        TransferMarketAuthorityParams::try_from_slice(data)?;
+    trace!("transfer_market_authority new_authority:{new_authority}");
+
+    let MarketAuthorityContext { authority, market } = market_authority_context;
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+    dynamic_account.set_market_authority(authority.key, &new_authority)?;
+
+    emit_stack(MarketAuthorityLog {
+        market: *market.key,
+        old_authority: *authority.key,
+        new_authority,
+    })?;
+
+    Ok(())
+}
@@ -2,14 +2,17 @@ use std::cell::RefMut;
 
 use super::get_trader_index_with_hint;
 use crate::{
-    logs::{emit_stack, WithdrawLog},
+    logs::{emit_stack, WithdrawLog, WithdrawToLog},
     program::get_mut_dynamic_account,
     state::MarketRefMut,
     validation::{loaders::WithdrawContext, MintAccountInfo, TokenAccountInfo, TokenProgram},
 };
 use borsh::{BorshDeserialize, BorshSerialize};
 use hypertree::DataIndex;
-use solana_program::{account_info::AccountInfo, entrypoint::ProgramResult, pubkey::Pubkey};
+use solana_program::{
+    account_info::AccountInfo, entrypoint::ProgramResult, program_error::ProgramError,
+    pubkey::Pubkey,
+};
 
 #[cfg(not(feature = "certora"))]
 use {crate::market_vault_seeds_with_bump, solana_program::program::invoke_signed};
@@ -56,18 +59,92 @@ pub(crate) fn process_withdraw_core(
         trader_index_hint,
     } = params;
 
+    let market_data: &mut RefMut<&mut [u8]> = &mut withdraw_context.market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+
+    let is_base: bool =
+        transfer_from_vault_to_token_account(&withdraw_context, &dynamic_account, amount_atoms)?;
+
+    let WithdrawContext { market, payer, .. } = &withdraw_context;
+    let trader_index: DataIndex =
+        get_trader_index_with_hint(trader_index_hint, &dynamic_account, payer)?;
+    dynamic_account.withdraw(trader_index, amount_atoms, is_base)?;
+
+    emit_stack(WithdrawLog {
+        market: *market.key,
+        trader: *payer.key,
+        mint: if is_base {
+            *dynamic_account.get_base_mint()
+        } else {
+            *dynamic_account.get_quote_mint()
+        },
+        amount_atoms,
+    })?;
+
+    Ok(())
+}
+
+/// Same as withdraw, but the funds go to a token account owned by someone
+/// else. The trader signs for the seat.
+pub(crate) fn process_withdraw_to(
+    _program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    let withdraw_context: WithdrawContext = WithdrawContext::load_to_recipient(accounts)?;
+    let WithdrawParams {
+        amount_atoms,
+        trader_index_hint,
+    } = WithdrawParams::try_from_slice(data)?;
+
+    let market_data: &mut RefMut<&mut [u8]> = &mut withdraw_context.market.try_borrow_mut_data()?;
+    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
+
+    let is_base: bool =
+        transfer_from_vault_to_token_account(&withdraw_context, &dynamic_account, amount_atoms)?;
+
     let WithdrawContext {
         market,
         payer,
         trader_token,
+        ..
+    } = &withdraw_context;
+    let trader_index: DataIndex =
+        get_trader_index_with_hint(trader_index_hint, &dynamic_account, payer)?;
+    dynamic_account.withdraw(trader_index, amount_atoms, is_base)?;
+
+    emit_stack(WithdrawToLog {
+        market: *market.key,
+        trader: *payer.key,
+        recipient: trader_token.get_owner(),
+        mint: if is_base {
+            *dynamic_account.get_base_mint()
+        } else {
+            *dynamic_account.get_quote_mint()
+        },
+        amount_atoms,
+    })?;
+
+    Ok(())
+}
+
+/// Moves amount_atoms from the vault to the token account of the withdraw.
+/// Returns whether it was base.
+pub(crate) fn transfer_from_vault_to_token_account(
+    withdraw_context: &WithdrawContext,
+    dynamic_account: &MarketRefMut,
+    amount_atoms: u64,
+) -> Result<bool, ProgramError> {
+    let WithdrawContext {
+        market,
+        trader_token,
         vault,
         token_program,
         mint,
+        transfer_hook_accounts,
+        ..
     } = withdraw_context;
 
-    let market_data: &mut RefMut<&mut [u8]> = &mut market.try_borrow_mut_data()?;
-    let mut dynamic_account: MarketRefMut = get_mut_dynamic_account(market_data);
-
     // Validation verifies that the mint is either base or quote.
     let is_base: bool =
         &trader_token.try_borrow_data()?[0..32] == dynamic_account.get_base_mint().as_ref();
@@ -86,11 +163,11 @@ pub(crate) fn process_withdraw_core(
 
     if *vault.owner == spl_token_2022::id() {
         spl_token_2022_transfer_from_vault_to_trader_fixed(
-            &token_program,
-            Some(mint),
+            token_program,
+            Some(mint.clone()),
             mint_key,
-            &vault,
-            &trader_token,
+            vault,
+            trader_token,
             amount_atoms,
             if is_base {
                 dynamic_account.fixed.get_base_mint_decimals()
@@ -99,40 +176,25 @@ pub(crate) fn process_withdraw_core(
             },
             market.key,
             bump,
+            transfer_hook_accounts,
         )?;
     } else {
         spl_token_transfer_from_vault_to_trader(
-            &token_program,
-            &vault,
-            &trader_token,
+            token_program,
+            vault,
+            trader_token,
             amount_atoms,
             market.key,
             bump,
             mint_key,
         )?;
     }
-
-    let trader_index: DataIndex =
-        get_trader_index_with_hint(trader_index_hint, &dynamic_account, &payer)?;
-    dynamic_account.withdraw(trader_index, amount_atoms, is_base)?;
-
-    emit_stack(WithdrawLog {
-        market: *market.key,
-        trader: *payer.key,
-        mint: if is_base {
-            *dynamic_account.get_base_mint()
-        } else {
-            *dynamic_account.get_quote_mint()
-        },
-        amount_atoms,
-    })?;
-
-    Ok(())
+    Ok(is_base)
 }
 
 /** Transfer from base (quote) vault to base (quote) trader using SPL Token **/
 #[cfg(not(feature = "certora"))]
-fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
+pub(crate) fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
     token_program: &TokenProgram<'a, 'info>,
     vault: &TokenAccountInfo<'a, 'info>,
     trader_account: &TokenAccountInfo<'a, 'info>,
@@ -161,7 +223,7 @@ fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
 
 #[cfg(feature = "certora")]
 /** (Summary) Transfer from base (quote) vault to base (quote) trader using SPL Token **/
-fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
+pub(crate) fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
     _token_program: &TokenProgram<'a, 'info>,
     vault: &TokenAccountInfo<'a, 'info>,
     trader_account: &TokenAccountInfo<'a, 'info>,
@@ -175,7 +237,7 @@ fn spl_token_transfer_from_vault_to_trader<'a, 'info>(
 
 /** Transfer from base (quote) vault to base (quote) trader using SPL Token 2022 **/
 #[cfg(not(feature = "certora"))]
-fn spl_token_2022_transfer_from_vault_to_trader_fixed<'a, 'info>(
+pub(crate) fn spl_token_2022_transfer_from_vault_to_trader_fixed<'a, 'info>(
     token_program: &TokenProgram<'a, 'info>,
     mint: Option<MintAccountInfo<'a, 'info>>,
     mint_key: &Pubkey,
@@ -185,7 +247,23 @@ fn spl_token_2022_transfer_from_vault_to_trader_fixed<'a, 'info>(
     decimals: u8,
     market_key: &Pubkey,
     bump: u8,
+    transfer_hook_accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
+    // Resolving hook accounts needs the mint extensions, so only pay for it
+    // when the caller passed some.
+    if !transfer_hook_accounts.is_empty() {
+        return spl_token_2022::onchain::invoke_transfer_checked(
+            token_program.key,
+            vault.as_ref().clone(),
+            mint.unwrap().as_ref().clone(),
+            trader_token.as_ref().clone(),
+            vault.as_ref().clone(),
+            transfer_hook_accounts,
+            amount_atoms,
+            decimals,
+            market_vault_seeds_with_bump!(market_key, mint_key, bump),
+        );
+    }
     invoke_signed(
         &spl_token_2022::instruction::transfer_checked(
             token_program.key,
@@ -210,7 +288,7 @@ fn spl_token_2022_transfer_from_vault_to_trader_fixed<'a, 'info>(
 // TODO: Share these with swap and deposit.
 #[cfg(feature = "certora")]
 /** (Summary) Transfer from base (quote) vault to base (quote) trader using SPL Token 2022 **/
-fn spl_token_2022_transfer_from_vault_to_trader_fixed<'a, 'info>(
+pub(crate) fn spl_token_2022_transfer_from_vault_to_trader_fixed<'a, 'info>(
     _token_program: &TokenProgram<'a, 'info>,
     _mint: Option<MintAccountInfo<'a, 'info>>,
     _mint_key: &Pubkey,
@@ -220,6 +298,7 @@ fn spl_token_2022_transfer_from_vault_to_trader_fixed<'a, 'info>(
     _decimals: u8,
     _market_key: &Pubkey,
     _bump: u8,
+    _transfer_hook_accounts: &[AccountInfo<'info>],
 ) -> ProgramResult {
     spl_token_2022_transfer(vault.info, trader_token.info, vault.info, amount_atoms)
 }
@@ -235,7 +235,7 @@ pub struct QuoteAtomsPerBaseAtom {
 // has no native support for u128 math and requires us only to be 8 byte
 // aligned.
 #[cfg(not(feature = "certora"))]
-const fn u128_to_u64_slice(a: u128) -> [u64; 2] {
+pub(crate) const fn u128_to_u64_slice(a: u128) -> [u64; 2] {
     unsafe {
         let ptr: *const u128 = &a;
         *ptr.cast::<[u64; 2]>()
@@ -363,6 +363,77 @@ impl QuoteAtomsPerBaseAtom {
         Ok(Self::from_mantissa_and_exponent_(mantissa, exponent))
     }
 
+    /// Smallest representable step above or below this price.
+    pub fn checked_tick(self, is_up: bool) -> Result<Self, PriceConversionError> {
+        let inner: u128 = u64_slice_to_u128(self.inner);
+        let new_inner: Option<u128> = if is_up {
+            inner.checked_add(1)
+        } else {
+            inner.checked_sub(1)
+        };
+        let Some(new_inner) = new_inner else {
+            return Err(PriceConversionError(0x10));
+        };
+        Ok(QuoteAtomsPerBaseAtom {
+            inner: u128_to_u64_slice(new_inner),
+        })
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self, PriceConversionError> {
+        let Some(new_inner) =
+            u64_slice_to_u128(self.inner).checked_add(u64_slice_to_u128(other.inner))
+        else {
+            return Err(PriceConversionError(0x12));
+        };
+        Ok(QuoteAtomsPerBaseAtom {
+            inner: u128_to_u64_slice(new_inner),
+        })
+    }
+
+    /// Round to a multiple of tick_size. A zero tick size leaves the price
+    /// unchanged.
+    pub fn checked_round_to_tick(
+        self,
+        tick_size: Self,
+        round_up: bool,
+    ) -> Result<Self, PriceConversionError> {
+        let tick_inner: u128 = u64_slice_to_u128(tick_size.inner);
+        if tick_inner == 0 {
+            return Ok(self);
+        }
+        let inner: u128 = u64_slice_to_u128(self.inner);
+        let num_ticks: u128 = if round_up {
+            inner.div_ceil(tick_inner)
+        } else {
+            inner.div(tick_inner)
+        };
+        let Some(new_inner) = num_ticks.checked_mul(tick_inner) else {
+            return Err(PriceConversionError(0x11));
+        };
+        Ok(QuoteAtomsPerBaseAtom {
+            inner: u128_to_u64_slice(new_inner),
+        })
+    }
+
+    /// Price times a number of slots for the market price accumulator. Wraps.
+    pub(crate) fn wrapping_mul_slots(self, num_slots: u32) -> u128 {
+        u64_slice_to_u128(self.inner).wrapping_mul(num_slots as u128)
+    }
+
+    /// Average price over num_slots given the difference between two readings
+    /// of the market price accumulator.
+    pub fn checked_average_from_cumulative(
+        cumulative_price_slots: u128,
+        num_slots: u32,
+    ) -> Result<Self, PriceConversionError> {
+        if num_slots == 0 {
+            return Err(PriceConversionError(0x13));
+        }
+        Ok(QuoteAtomsPerBaseAtom {
+            inner: u128_to_u64_slice(cumulative_price_slots / num_slots as u128),
+        })
+    }
+
     #[inline(always)]
     pub fn checked_base_for_quote(
         self,
@@ -755,3 +826,55 @@ fn test_debug() {
         }
     );
 }
+
+#[test]
+fn test_checked_tick() {
+    let price: QuoteAtomsPerBaseAtom = QuoteAtomsPerBaseAtom::MIN;
+    assert_eq!(
+        price.checked_tick(false).unwrap(),
+        QuoteAtomsPerBaseAtom::ZERO
+    );
+    assert_eq!(
+        price
+            .checked_tick(true)
+            .unwrap()
+            .checked_tick(false)
+            .unwrap(),
+        price
+    );
+    assert!(QuoteAtomsPerBaseAtom::ZERO.checked_tick(false).is_err());
+}
+
+#[test]
+fn test_checked_add_price() {
+    let one: QuoteAtomsPerBaseAtom =
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(1, 0).unwrap();
+    let step: QuoteAtomsPerBaseAtom =
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(25, -2).unwrap();
+    assert_eq!(
+        one.checked_add(step).unwrap(),
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(125, -2).unwrap()
+    );
+    assert!(QuoteAtomsPerBaseAtom {
+        inner: [u64::MAX, u64::MAX],
+    }
+    .checked_add(QuoteAtomsPerBaseAtom::MIN)
+    .is_err());
+}
+
+#[test]
+fn test_checked_average_from_cumulative() {
+    let one: QuoteAtomsPerBaseAtom =
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(1, 0).unwrap();
+    let three: QuoteAtomsPerBaseAtom =
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(3, 0).unwrap();
+    // 1 for 3 slots then 3 for 1 slot.
+    let cumulative: u128 = one
+        .wrapping_mul_slots(3)
+        .wrapping_add(three.wrapping_mul_slots(1));
+    assert_eq!(
+        QuoteAtomsPerBaseAtom::checked_average_from_cumulative(cumulative, 4).unwrap(),
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(15, -1).unwrap()
+    );
+    assert!(QuoteAtomsPerBaseAtom::checked_average_from_cumulative(cumulative, 0).is_err());
+}
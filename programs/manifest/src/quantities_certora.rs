@@ -105,6 +105,50 @@ impl QuoteAtomsPerBaseAtom {
         Self { inner: [x, 0] }
     }
 
+    pub fn checked_tick(self, is_up: bool) -> Result<Self, PriceConversionError> {
+        let new_inner: Option<u64> = if is_up {
+            self.inner[0].checked_add(1)
+        } else {
+            self.inner[0].checked_sub(1)
+        };
+        let Some(new_inner) = new_inner else {
+            return Err(PriceConversionError(0x10));
+        };
+        Ok(Self {
+            inner: [new_inner, 0],
+        })
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self, PriceConversionError> {
+        let Some(new_inner) = self.inner[0].checked_add(other.inner[0]) else {
+            return Err(PriceConversionError(0x12));
+        };
+        Ok(Self {
+            inner: [new_inner, 0],
+        })
+    }
+
+    pub fn checked_round_to_tick(
+        self,
+        tick_size: Self,
+        round_up: bool,
+    ) -> Result<Self, PriceConversionError> {
+        if tick_size.inner[0] == 0 {
+            return Ok(self);
+        }
+        let num_ticks: u64 = if round_up {
+            self.inner[0].div_ceil(tick_size.inner[0])
+        } else {
+            self.inner[0] / tick_size.inner[0]
+        };
+        let Some(new_inner) = num_ticks.checked_mul(tick_size.inner[0]) else {
+            return Err(PriceConversionError(0x11));
+        };
+        Ok(Self {
+            inner: [new_inner, 0],
+        })
+    }
+
     pub fn checked_multiply_rational(
         self,
         _numerator: u32,
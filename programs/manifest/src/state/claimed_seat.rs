@@ -4,6 +4,7 @@ use std::mem::size_of;
 use crate::quantities::WrapperU64;
 use crate::quantities::{BaseAtoms, QuoteAtoms};
 use bytemuck::{Pod, Zeroable};
+use hypertree::{DataIndex, PodBool};
 use shank::ShankType;
 use solana_program::pubkey::Pubkey;
 use static_assertions::const_assert_eq;
@@ -25,13 +26,23 @@ pub struct ClaimedSeat {
     /// not guaranteed to be maintained. It does not secure any value in
     /// manifest. Use at your own risk.
     pub quote_volume: QuoteAtoms,
-    _padding: [u8; 8],
+    /// Index of the MarketSeatDelegate extension block. Only valid when
+    /// has_delegate is set, older seats have this zeroed.
+    delegate_index: DataIndex,
+    has_delegate: PodBool,
+    /// Set when the trader's membership on a permissioned market is revoked.
+    /// The seat can still cancel and withdraw but cannot place orders.
+    is_cancel_only: PodBool,
+    _padding: [u8; 2],
 }
 // 32 + // trader
 //  8 + // base_balance
 //  8 + // quote_balance
 //  8 + // quote_volume
-//  8   // padding
+//  4 + // delegate_index
+//  1 + // has_delegate
+//  1 + // is_cancel_only
+//  2   // padding
 // = 64
 const_assert_eq!(size_of::<ClaimedSeat>(), CLAIMED_SEAT_SIZE);
 const_assert_eq!(size_of::<ClaimedSeat>() % 8, 0);
@@ -43,6 +54,27 @@ impl ClaimedSeat {
             ..Default::default()
         }
     }
+
+    pub fn get_delegate_index(&self) -> Option<DataIndex> {
+        if self.has_delegate.0 == 1 {
+            Some(self.delegate_index)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_delegate_index(&mut self, delegate_index: Option<DataIndex>) {
+        self.delegate_index = delegate_index.unwrap_or_default();
+        self.has_delegate = PodBool::from_bool(delegate_index.is_some());
+    }
+
+    pub fn get_is_cancel_only(&self) -> bool {
+        self.is_cancel_only.0 == 1
+    }
+
+    pub fn set_is_cancel_only(&mut self, is_cancel_only: bool) {
+        self.is_cancel_only = PodBool::from_bool(is_cancel_only);
+    }
 }
 
 #[cfg(feature = "certora")]
@@ -53,7 +85,10 @@ impl nondet::Nondet for ClaimedSeat {
             base_withdrawable_balance: BaseAtoms::new(nondet::nondet()),
             quote_withdrawable_balance: QuoteAtoms::new(nondet::nondet()),
             quote_volume: QuoteAtoms::new(nondet::nondet()),
-            _padding: [0; 8],
+            delegate_index: 0,
+            has_delegate: PodBool::from_bool(false),
+            is_cancel_only: PodBool::from_bool(false),
+            _padding: [0; 2],
         }
     }
 }
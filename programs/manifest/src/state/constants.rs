@@ -2,6 +2,9 @@ use hypertree::RBTREE_OVERHEAD_BYTES;
 
 pub const MARKET_FIXED_SIZE: usize = 256;
 pub const GLOBAL_FIXED_SIZE: usize = 96;
+pub const EVENT_QUEUE_FIXED_SIZE: usize = 64;
+pub const MARKET_EVENT_SIZE: usize = 160;
+pub const MARKET_MEMBERSHIP_SIZE: usize = 80;
 
 // Red black tree overhead is 16 bytes. If each block is 80 bytes, then we get
 // 64 bytes for a RestingOrder or ClaimedSeat.
@@ -10,6 +13,7 @@ pub const MARKET_BLOCK_SIZE: usize = 80;
 const MARKET_BLOCK_PAYLOAD_SIZE: usize = MARKET_BLOCK_SIZE - RBTREE_OVERHEAD_BYTES;
 pub const RESTING_ORDER_SIZE: usize = MARKET_BLOCK_PAYLOAD_SIZE;
 pub const CLAIMED_SEAT_SIZE: usize = MARKET_BLOCK_PAYLOAD_SIZE;
+pub const MARKET_EXTENSION_SIZE: usize = MARKET_BLOCK_PAYLOAD_SIZE;
 const GLOBAL_BLOCK_PAYLOAD_SIZE: usize = GLOBAL_BLOCK_SIZE - RBTREE_OVERHEAD_BYTES;
 pub const GLOBAL_TRADER_SIZE: usize = GLOBAL_BLOCK_PAYLOAD_SIZE;
 pub const GLOBAL_DEPOSIT_SIZE: usize = GLOBAL_BLOCK_PAYLOAD_SIZE;
@@ -21,6 +25,8 @@ pub const NO_EXPIRATION_LAST_VALID_SLOT: u32 = 0;
 
 pub const MARKET_FIXED_DISCRIMINANT: u64 = 4859840929024028656;
 pub const GLOBAL_FIXED_DISCRIMINANT: u64 = 10787423733276977665;
+pub const EVENT_QUEUE_FIXED_DISCRIMINANT: u64 = 10877218982611990070;
+pub const MARKET_MEMBERSHIP_DISCRIMINANT: u64 = 2851980225247789281;
 
 // Amount of gas deposited for every global order. This is done to as an
 // economic disincentive to spam.
@@ -0,0 +1,435 @@
+/// The event queue is an optional ring buffer attached to a market that holds
+/// a record of every fill and cancel. It is an alternative to program logs,
+/// which RPCs can truncate and which are lost when a subscription drops.
+///
+/// Events are written at the head and consumed from the tail. Every event gets
+/// the next sequence number, so a reader that tracks sequence numbers can tell
+/// whether it missed anything. When the queue is full, fills fail until events
+/// are consumed, so events are never overwritten before they are consumed.
+/// Cancels never need the queue so that makers can always pull their orders.
+/// Their events are only written when the queue is included, and on a full
+/// queue they are dropped and counted in num_dropped_events.
+/// Consuming does not erase the events, they can still be read until their
+/// slot is reused.
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+use hypertree::{get_helper, get_mut_helper, DataIndex, Get, PodBool};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use shank::ShankType;
+use solana_program::{entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+use static_assertions::const_assert_eq;
+
+use crate::{
+    program::ManifestError,
+    quantities::{BaseAtoms, QuoteAtoms, QuoteAtomsPerBaseAtom},
+    require,
+    validation::ManifestAccount,
+};
+
+use super::{
+    DerefOrBorrow, DerefOrBorrowMut, DynamicAccount, EVENT_QUEUE_FIXED_DISCRIMINANT,
+    EVENT_QUEUE_FIXED_SIZE, MARKET_EVENT_SIZE,
+};
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct EventQueueFixed {
+    /// Discriminant for identifying this type of account.
+    pub discriminant: u64,
+
+    /// Market that writes to this queue.
+    market: Pubkey,
+
+    /// Sequence number of the next event written. Also the number of events
+    /// ever written.
+    head_sequence_number: u64,
+
+    /// Sequence number of the oldest event that has not been consumed.
+    tail_sequence_number: u64,
+
+    /// Number of events that fit in the account. Set from the account size.
+    capacity: u32,
+
+    /// Number of cancel events that were not written because the queue was
+    /// full.
+    num_dropped_events: u32,
+}
+const_assert_eq!(
+    size_of::<EventQueueFixed>(),
+    8 +   // discriminant
+    32 +  // market
+    8 +   // head_sequence_number
+    8 +   // tail_sequence_number
+    4 +   // capacity
+    4 // num_dropped_events
+);
+const_assert_eq!(size_of::<EventQueueFixed>(), EVENT_QUEUE_FIXED_SIZE);
+const_assert_eq!(size_of::<EventQueueFixed>() % 8, 0);
+impl Get for EventQueueFixed {}
+
+#[derive(Debug, PartialEq, Clone, Copy, IntoPrimitive, TryFromPrimitive)]
+#[repr(u8)]
+pub enum MarketEventType {
+    // Zero is left for slots that were never written.
+    Fill = 1,
+    Cancel = 2,
+}
+
+/// One fill or cancel. Fills have the same information as the FillLog.
+#[repr(C)]
+#[derive(Default, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct MarketEvent {
+    /// Position in the queue, assigned when the event is written.
+    pub sequence_number: u64,
+    pub maker: Pubkey,
+    /// Default for cancels.
+    pub taker: Pubkey,
+    pub price: QuoteAtomsPerBaseAtom,
+    /// Matched for fills, everything that was left on the order for cancels.
+    pub base_atoms: BaseAtoms,
+    /// Zero for cancels.
+    pub quote_atoms: QuoteAtoms,
+    pub maker_sequence_number: u64,
+    /// Zero for cancels.
+    pub taker_sequence_number: u64,
+    pub maker_client_order_id: u64,
+    /// Zero for cancels.
+    pub taker_client_order_id: u64,
+    /// Zero for cancels.
+    pub taker_fee_atoms: u64,
+    /// Zero for cancels.
+    pub maker_rebate_atoms: u64,
+    pub slot: u32,
+    event_type: u8,
+    /// Side of the maker order.
+    pub maker_is_bid: PodBool,
+    pub _padding: [u8; 2],
+}
+const_assert_eq!(
+    size_of::<MarketEvent>(),
+    8 +   // sequence_number
+    32 +  // maker
+    32 +  // taker
+    16 +  // price
+    8 +   // base_atoms
+    8 +   // quote_atoms
+    8 +   // maker_sequence_number
+    8 +   // taker_sequence_number
+    8 +   // maker_client_order_id
+    8 +   // taker_client_order_id
+    8 +   // taker_fee_atoms
+    8 +   // maker_rebate_atoms
+    4 +   // slot
+    1 +   // event_type
+    1 +   // maker_is_bid
+    2 // padding
+);
+const_assert_eq!(size_of::<MarketEvent>(), MARKET_EVENT_SIZE);
+const_assert_eq!(size_of::<MarketEvent>() % 8, 0);
+impl Get for MarketEvent {}
+
+impl EventQueueFixed {
+    pub fn new_empty(market: &Pubkey, capacity: u32) -> Self {
+        EventQueueFixed {
+            discriminant: EVENT_QUEUE_FIXED_DISCRIMINANT,
+            market: *market,
+            head_sequence_number: 0,
+            tail_sequence_number: 0,
+            capacity,
+            num_dropped_events: 0,
+        }
+    }
+
+    /// Number of events that fit in an account of the given size. Zero if the
+    /// size is not a whole number of events.
+    pub fn get_capacity_for_account_size(account_size: usize) -> u32 {
+        if account_size <= EVENT_QUEUE_FIXED_SIZE
+            || (account_size - EVENT_QUEUE_FIXED_SIZE) % MARKET_EVENT_SIZE != 0
+        {
+            return 0;
+        }
+        ((account_size - EVENT_QUEUE_FIXED_SIZE) / MARKET_EVENT_SIZE) as u32
+    }
+
+    pub fn get_market(&self) -> &Pubkey {
+        &self.market
+    }
+    pub fn get_head_sequence_number(&self) -> u64 {
+        self.head_sequence_number
+    }
+    pub fn get_tail_sequence_number(&self) -> u64 {
+        self.tail_sequence_number
+    }
+    pub fn get_capacity(&self) -> u32 {
+        self.capacity
+    }
+    pub fn get_num_unconsumed_events(&self) -> u64 {
+        self.head_sequence_number - self.tail_sequence_number
+    }
+    pub fn get_num_dropped_events(&self) -> u32 {
+        self.num_dropped_events
+    }
+    pub fn is_full(&self) -> bool {
+        self.get_num_unconsumed_events() >= self.capacity as u64
+    }
+}
+
+impl ManifestAccount for EventQueueFixed {
+    fn verify_discriminant(&self) -> ProgramResult {
+        // Check the discriminant to make sure it is an event queue account.
+        require!(
+            self.discriminant == EVENT_QUEUE_FIXED_DISCRIMINANT,
+            ProgramError::InvalidAccountData,
+            "Invalid event queue discriminant actual: {} expected: {}",
+            self.discriminant,
+            EVENT_QUEUE_FIXED_DISCRIMINANT
+        )?;
+        Ok(())
+    }
+}
+
+impl MarketEvent {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_fill(
+        maker: Pubkey,
+        taker: Pubkey,
+        price: QuoteAtomsPerBaseAtom,
+        base_atoms: BaseAtoms,
+        quote_atoms: QuoteAtoms,
+        maker_sequence_number: u64,
+        taker_sequence_number: u64,
+        maker_client_order_id: u64,
+        taker_client_order_id: u64,
+        taker_fee_atoms: u64,
+        maker_rebate_atoms: u64,
+        maker_is_bid: bool,
+    ) -> Self {
+        MarketEvent {
+            maker,
+            taker,
+            price,
+            base_atoms,
+            quote_atoms,
+            maker_sequence_number,
+            taker_sequence_number,
+            maker_client_order_id,
+            taker_client_order_id,
+            taker_fee_atoms,
+            maker_rebate_atoms,
+            event_type: MarketEventType::Fill.into(),
+            maker_is_bid: PodBool::from(maker_is_bid),
+            ..Default::default()
+        }
+    }
+
+    pub fn new_cancel(
+        maker: Pubkey,
+        price: QuoteAtomsPerBaseAtom,
+        base_atoms: BaseAtoms,
+        maker_sequence_number: u64,
+        maker_client_order_id: u64,
+        maker_is_bid: bool,
+    ) -> Self {
+        MarketEvent {
+            maker,
+            price,
+            base_atoms,
+            maker_sequence_number,
+            maker_client_order_id,
+            event_type: MarketEventType::Cancel.into(),
+            maker_is_bid: PodBool::from(maker_is_bid),
+            ..Default::default()
+        }
+    }
+
+    pub fn get_event_type(&self) -> Result<MarketEventType, ProgramError> {
+        MarketEventType::try_from(self.event_type).map_err(|_| ProgramError::InvalidAccountData)
+    }
+}
+
+/// Fully owned EventQueue, used in clients that can copy.
+pub type EventQueueValue = DynamicAccount<EventQueueFixed, Vec<u8>>;
+/// Full event queue reference type.
+pub type EventQueueRef<'a> = DynamicAccount<&'a EventQueueFixed, &'a [u8]>;
+/// Full event queue reference type.
+pub type EventQueueRefMut<'a> = DynamicAccount<&'a mut EventQueueFixed, &'a mut [u8]>;
+
+fn get_event_index(fixed: &EventQueueFixed, sequence_number: u64) -> DataIndex {
+    ((sequence_number % fixed.capacity as u64) as usize * MARKET_EVENT_SIZE) as DataIndex
+}
+
+impl<Fixed: DerefOrBorrow<EventQueueFixed>, Dynamic: DerefOrBorrow<[u8]>>
+    DynamicAccount<Fixed, Dynamic>
+{
+    fn borrow_event_queue(&self) -> EventQueueRef {
+        EventQueueRef {
+            fixed: self.fixed.deref_or_borrow(),
+            dynamic: self.dynamic.deref_or_borrow(),
+        }
+    }
+
+    /// Event with the given sequence number if it is still in the queue. This
+    /// includes consumed events whose slot has not been reused.
+    pub fn get_event(&self, sequence_number: u64) -> Option<&MarketEvent> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_event_queue();
+        if sequence_number >= fixed.head_sequence_number
+            || fixed.head_sequence_number - sequence_number > fixed.capacity as u64
+        {
+            return None;
+        }
+        Some(get_helper::<MarketEvent>(
+            dynamic,
+            get_event_index(fixed, sequence_number),
+        ))
+    }
+
+    /// Events from tail to head, oldest first.
+    pub fn get_unconsumed_events(&self) -> Vec<MarketEvent> {
+        let DynamicAccount { fixed, .. } = self.borrow_event_queue();
+        (fixed.tail_sequence_number..fixed.head_sequence_number)
+            .filter_map(|sequence_number| self.get_event(sequence_number).copied())
+            .collect()
+    }
+}
+
+impl<Fixed: DerefOrBorrowMut<EventQueueFixed>, Dynamic: DerefOrBorrowMut<[u8]>>
+    DynamicAccount<Fixed, Dynamic>
+{
+    fn borrow_mut_event_queue(&mut self) -> EventQueueRefMut {
+        EventQueueRefMut {
+            fixed: self.fixed.deref_or_borrow_mut(),
+            dynamic: self.dynamic.deref_or_borrow_mut(),
+        }
+    }
+
+    /// Writes the event at the head and returns its sequence number.
+    pub fn push_event(&mut self, mut event: MarketEvent) -> Result<u64, ProgramError> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut_event_queue();
+        require!(
+            !fixed.is_full(),
+            ManifestError::EventQueueFull,
+            "Event queue is full with {} events",
+            fixed.capacity,
+        )?;
+        let sequence_number: u64 = fixed.head_sequence_number;
+        event.sequence_number = sequence_number;
+        *get_mut_helper::<MarketEvent>(dynamic, get_event_index(fixed, sequence_number)) = event;
+        fixed.head_sequence_number = sequence_number + 1;
+        Ok(sequence_number)
+    }
+
+    /// Same as push_event, but when the queue is full the event is counted as
+    /// dropped instead of failing. Returns the sequence number if written.
+    pub fn push_event_or_drop(&mut self, event: MarketEvent) -> Option<u64> {
+        let DynamicAccount { fixed, .. } = self.borrow_mut_event_queue();
+        if fixed.is_full() {
+            fixed.num_dropped_events = fixed.num_dropped_events.saturating_add(1);
+            return None;
+        }
+        self.push_event(event).ok()
+    }
+
+    /// Moves the tail forward by up to limit events. Returns how many were
+    /// consumed.
+    pub fn consume_events(&mut self, limit: u64) -> u64 {
+        let DynamicAccount { fixed, .. } = self.borrow_mut_event_queue();
+        let num_consumed: u64 = limit.min(fixed.get_num_unconsumed_events());
+        fixed.tail_sequence_number += num_consumed;
+        num_consumed
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::quantities::WrapperU64;
+
+    fn cancel_event(maker_sequence_number: u64) -> MarketEvent {
+        MarketEvent::new_cancel(
+            Pubkey::new_unique(),
+            QuoteAtomsPerBaseAtom::ZERO,
+            BaseAtoms::new(1),
+            maker_sequence_number,
+            0,
+            true,
+        )
+    }
+
+    #[test]
+    fn test_capacity_for_account_size() {
+        assert_eq!(
+            EventQueueFixed::get_capacity_for_account_size(EVENT_QUEUE_FIXED_SIZE),
+            0
+        );
+        assert_eq!(
+            EventQueueFixed::get_capacity_for_account_size(
+                EVENT_QUEUE_FIXED_SIZE + 3 * MARKET_EVENT_SIZE
+            ),
+            3
+        );
+        assert_eq!(
+            EventQueueFixed::get_capacity_for_account_size(
+                EVENT_QUEUE_FIXED_SIZE + 3 * MARKET_EVENT_SIZE + 1
+            ),
+            0
+        );
+    }
+
+    #[test]
+    fn test_push_and_consume() {
+        let mut fixed: EventQueueFixed = EventQueueFixed::new_empty(&Pubkey::new_unique(), 2);
+        let mut dynamic: Vec<u8> = vec![0; 2 * MARKET_EVENT_SIZE];
+        let mut event_queue: EventQueueRefMut = DynamicAccount {
+            fixed: &mut fixed,
+            dynamic: &mut dynamic[..],
+        };
+
+        assert_eq!(event_queue.push_event(cancel_event(10)).unwrap(), 0);
+        assert_eq!(event_queue.push_event(cancel_event(11)).unwrap(), 1);
+        // Full until consumed.
+        assert!(event_queue.push_event(cancel_event(12)).is_err());
+
+        assert_eq!(event_queue.consume_events(1), 1);
+        assert_eq!(event_queue.push_event(cancel_event(12)).unwrap(), 2);
+
+        let events: Vec<MarketEvent> = event_queue.get_unconsumed_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].maker_sequence_number, 11);
+        assert_eq!(events[1].maker_sequence_number, 12);
+        assert_eq!(events[1].sequence_number, 2);
+        assert_eq!(events[1].get_event_type().unwrap(), MarketEventType::Cancel);
+
+        // Consumed and overwritten.
+        assert!(event_queue.get_event(0).is_none());
+        // Not written yet.
+        assert!(event_queue.get_event(3).is_none());
+
+        assert_eq!(event_queue.consume_events(5), 2);
+        assert_eq!(event_queue.fixed.get_num_unconsumed_events(), 0);
+        // Still readable after being consumed.
+        assert_eq!(event_queue.get_event(2).unwrap().maker_sequence_number, 12);
+    }
+
+    #[test]
+    fn test_push_or_drop_when_full() {
+        let mut fixed: EventQueueFixed = EventQueueFixed::new_empty(&Pubkey::new_unique(), 1);
+        let mut dynamic: Vec<u8> = vec![0; MARKET_EVENT_SIZE];
+        let mut event_queue: EventQueueRefMut = DynamicAccount {
+            fixed: &mut fixed,
+            dynamic: &mut dynamic[..],
+        };
+
+        assert_eq!(event_queue.push_event_or_drop(cancel_event(10)), Some(0));
+        assert_eq!(event_queue.push_event_or_drop(cancel_event(11)), None);
+        assert_eq!(event_queue.push_event_or_drop(cancel_event(12)), None);
+        assert_eq!(event_queue.fixed.get_num_dropped_events(), 2);
+        // The unconsumed event is not overwritten.
+        assert_eq!(event_queue.get_unconsumed_events().len(), 1);
+        assert_eq!(event_queue.get_event(0).unwrap().maker_sequence_number, 10);
+
+        assert_eq!(event_queue.consume_events(1), 1);
+        assert_eq!(event_queue.push_event_or_drop(cancel_event(13)), Some(1));
+        assert_eq!(event_queue.fixed.get_num_dropped_events(), 2);
+    }
+}
@@ -6,39 +6,49 @@ use hypertree::{
     get_helper, get_mut_helper, is_not_nil, trace, DataIndex, FreeList, FreeListNode, Get, PodBool,
     RBNode, NIL,
 };
-#[cfg(not(feature = "certora"))]
-use hypertree::{
-    HyperTreeReadOperations, HyperTreeValueIteratorTrait, HyperTreeWriteOperations, RedBlackTree,
-    RedBlackTreeReadOnly,
-};
 use shank::ShankType;
 use solana_program::{entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
 use static_assertions::const_assert_eq;
-use std::mem::size_of;
+use std::{cell::RefMut, mem::size_of};
+#[cfg(not(feature = "certora"))]
+use {
+    crate::quantities::{u128_to_u64_slice, u64_slice_to_u128},
+    hypertree::{
+        HyperTreeReadOperations, HyperTreeValueIteratorTrait, HyperTreeWriteOperations,
+        RedBlackTree, RedBlackTreeReadOnly,
+    },
+};
 
 use crate::{
-    logs::{emit_stack, FillLog},
-    program::{batch_update::MarketDataTreeNodeType, ManifestError},
+    logs::{emit_stack, CancelOrderLog, FillLog, IcebergRefillLog},
+    program::{batch_update::MarketDataTreeNodeType, get_mut_dynamic_account, ManifestError},
     quantities::{BaseAtoms, GlobalAtoms, QuoteAtoms, QuoteAtomsPerBaseAtom, WrapperU64},
     require,
     state::{
         utils::{assert_can_take, remove_from_global, try_to_move_global_tokens},
-        OrderType,
+        OrderType, SelfTradeBehavior,
     },
     validation::{
-        get_vault_address, loaders::GlobalTradeAccounts, ManifestAccount, MintAccountInfo,
+        get_vault_address, loaders::GlobalTradeAccounts, ManifestAccount, ManifestAccountInfo,
+        MintAccountInfo,
     },
 };
 
 use super::{
     claimed_seat::ClaimedSeat,
     constants::{MARKET_BLOCK_SIZE, MARKET_FIXED_SIZE},
+    market_extension::{
+        get_helper_extension, get_mut_helper_extension, init_extension, MarketAuthorityConfig,
+        MarketEventQueueConfig, MarketFeeConfig, MarketGateConfig, MarketSeatDelegate,
+        MarketStatus, MarketTradingConfig,
+    },
     order_type_can_rest,
     utils::{
         assert_already_has_seat, assert_not_already_expired, can_back_order, get_now_slot,
         try_to_add_to_global,
     },
-    DerefOrBorrow, DerefOrBorrowMut, DynamicAccount, RestingOrder, MARKET_FIXED_DISCRIMINANT,
+    DerefOrBorrow, DerefOrBorrowMut, DynamicAccount, EventQueueFixed, EventQueueRefMut,
+    MarketEvent, MarketEventType, RestingOrder, ReversePriceBounds, MARKET_FIXED_DISCRIMINANT,
     MARKET_FREE_LIST_BLOCK_SIZE, NO_EXPIRATION_LAST_VALID_SLOT,
 };
 
@@ -106,6 +116,14 @@ pub struct AddOrderToMarketArgs<'a, 'info> {
     pub order_type: OrderType,
     pub global_trade_accounts_opts: &'a [Option<GlobalTradeAccounts<'a, 'info>>; 2],
     pub current_slot: Option<u32>,
+    pub self_trade_behavior: SelfTradeBehavior,
+    pub client_order_id: u64,
+    /// Only used when resting a reverse order.
+    pub reverse_price_bounds: ReversePriceBounds,
+    /// Only used when resting an iceberg order.
+    pub iceberg_display_base_atoms: BaseAtoms,
+    /// Required when the market has an event queue.
+    pub event_queue_opt: &'a Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
 }
 
 pub struct AddOrderToMarketResult {
@@ -113,6 +131,9 @@ pub struct AddOrderToMarketResult {
     pub order_index: DataIndex,
     pub base_atoms_traded: BaseAtoms,
     pub quote_atoms_traded: QuoteAtoms,
+    /// Fees taken from what the taker received. Base atoms for bids and quote
+    /// atoms for asks.
+    pub taker_fee_atoms: u64,
 }
 
 #[repr(C, packed)]
@@ -128,6 +149,10 @@ const_assert_eq!(
 );
 // Does not need to align to word boundaries because does not deserialize.
 
+/// Markets before this version have zeroed padding where the extension indices
+/// now are, so those indices are only read from this version on.
+pub const MARKET_EXTENSIONS_VERSION: u8 = 1;
+
 #[repr(C)]
 #[derive(Default, Copy, Clone, Zeroable, Pod, ShankType)]
 pub struct MarketFixed {
@@ -173,7 +198,9 @@ pub struct MarketFixed {
     /// LinkedList representing all free blocks that could be used for ClaimedSeats or RestingOrders
     free_list_head_index: DataIndex,
 
-    _padding2: [u32; 1],
+    /// Slot of the last fill. Zero if the market has not traded since the
+    /// price accumulator was added.
+    last_trade_slot: u32,
 
     /// Quote volume traded over lifetime, can overflow. This is for
     /// informational and monitoring purposes only. This is not guaranteed to
@@ -195,14 +222,40 @@ pub struct MarketFixed {
     #[cfg(feature = "certora")]
     /// Quote tokens reserved for non-global orders
     pub orderbook_quote_atoms: QuoteAtoms,
-    #[cfg(feature = "certora")]
-    _padding3: [u64; 4],
 
-    // Unused padding. Saved in case a later version wants to be backwards
-    // compatible. Also, it is nice to have the fixed size be a round number,
-    // 256 bytes.
+    // The price accumulator takes the space that is used by the informational
+    // fields above for formal verification.
     #[cfg(not(feature = "certora"))]
-    _padding3: [u64; 8],
+    /// Price of the last fill.
+    last_trade_price: QuoteAtomsPerBaseAtom,
+    #[cfg(not(feature = "certora"))]
+    /// Sum over slots of the last trade price at that slot. Wraps, only the
+    /// difference between two readings is meaningful.
+    cumulative_price_slots: [u64; 2],
+
+    /// Index of the MarketFeeConfig extension block. NIL when the market has
+    /// no fees. Only valid from MARKET_EXTENSIONS_VERSION on.
+    fee_config_index: DataIndex,
+
+    /// Index of the MarketTradingConfig extension block. NIL when the market
+    /// has no tick, lot or minimum size. Only valid from
+    /// MARKET_EXTENSIONS_VERSION on.
+    trading_config_index: DataIndex,
+
+    /// Index of the MarketAuthorityConfig extension block. NIL when the market
+    /// has no authority. Only valid from MARKET_EXTENSIONS_VERSION on.
+    authority_config_index: DataIndex,
+
+    /// Index of the MarketEventQueueConfig extension block. NIL when the
+    /// market has no event queue. Only valid from MARKET_EXTENSIONS_VERSION
+    /// on.
+    event_queue_config_index: DataIndex,
+
+    /// Index of the MarketGateConfig extension block. NIL when anyone can
+    /// trade on the market. Only valid from MARKET_EXTENSIONS_VERSION on.
+    gate_config_index: DataIndex,
+
+    _padding4: [u32; 3],
 }
 const_assert_eq!(
     size_of::<MarketFixed>(),
@@ -224,10 +277,17 @@ const_assert_eq!(
     4 +   // asks_root_index
     4 +   // asks_best_index
     4 +   // claimed_seats_root_index
-    4 +   // claimed_seats_best_index
     4 +   // free_list_head_index
-    8 +   // padding2
-    64 // padding4
+    4 +   // last_trade_slot
+    8 +   // quote_volume
+    16 +  // last_trade_price
+    16 +  // cumulative_price_slots
+    4 +   // fee_config_index
+    4 +   // trading_config_index
+    4 +   // authority_config_index
+    4 +   // event_queue_config_index
+    4 +   // gate_config_index
+    12 // padding4
 );
 const_assert_eq!(size_of::<MarketFixed>(), MARKET_FIXED_SIZE);
 const_assert_eq!(size_of::<MarketFixed>() % 8, 0);
@@ -243,7 +303,7 @@ impl MarketFixed {
         let (quote_vault, quote_vault_bump) = get_vault_address(market_key, quote_mint.info.key);
         MarketFixed {
             discriminant: MARKET_FIXED_DISCRIMINANT,
-            version: 0,
+            version: MARKET_EXTENSIONS_VERSION,
             base_mint_decimals: base_mint.mint.decimals,
             quote_mint_decimals: quote_mint.mint.decimals,
             base_vault_bump,
@@ -265,10 +325,12 @@ impl MarketFixed {
             #[cfg(feature = "certora")]
             // non NIL
             free_list_head_index: 0,
-            _padding2: [0; 1],
+            last_trade_slot: 0,
             quote_volume: QuoteAtoms::ZERO,
             #[cfg(not(feature = "certora"))]
-            _padding3: [0; 8],
+            last_trade_price: QuoteAtomsPerBaseAtom::ZERO,
+            #[cfg(not(feature = "certora"))]
+            cumulative_price_slots: [0; 2],
             #[cfg(feature = "certora")]
             withdrawable_base_atoms: BaseAtoms::new(0),
             #[cfg(feature = "certora")]
@@ -277,8 +339,12 @@ impl MarketFixed {
             orderbook_base_atoms: BaseAtoms::new(0),
             #[cfg(feature = "certora")]
             orderbook_quote_atoms: QuoteAtoms::new(0),
-            #[cfg(feature = "certora")]
-            _padding3: [0; 4],
+            fee_config_index: NIL,
+            trading_config_index: NIL,
+            authority_config_index: NIL,
+            event_queue_config_index: NIL,
+            gate_config_index: NIL,
+            _padding4: [0; 3],
         }
     }
 
@@ -307,13 +373,18 @@ impl MarketFixed {
             asks_best_index: NIL,
             claimed_seats_root_index,
             free_list_head_index: 0,
-            _padding2: [0; 1],
+            last_trade_slot: 0,
             quote_volume: QuoteAtoms::ZERO,
             withdrawable_base_atoms: BaseAtoms::new(nondet()),
             withdrawable_quote_atoms: QuoteAtoms::new(nondet()),
             orderbook_base_atoms: BaseAtoms::new(nondet()),
             orderbook_quote_atoms: QuoteAtoms::new(nondet()),
-            _padding3: [0; 4],
+            fee_config_index: NIL,
+            trading_config_index: NIL,
+            authority_config_index: NIL,
+            event_queue_config_index: NIL,
+            gate_config_index: NIL,
+            _padding4: [0; 3],
         }
     }
 
@@ -344,6 +415,74 @@ impl MarketFixed {
     pub fn get_quote_volume(&self) -> QuoteAtoms {
         self.quote_volume
     }
+    pub fn get_last_trade_slot(&self) -> u32 {
+        self.last_trade_slot
+    }
+    #[cfg(not(feature = "certora"))]
+    pub fn get_last_trade_price(&self) -> QuoteAtomsPerBaseAtom {
+        self.last_trade_price
+    }
+
+    /// Reading of the price accumulator at now_slot, including the slots
+    /// since the last fill. Read this at two slots and use
+    /// PriceObservation::get_twap for the time weighted average price between
+    /// them.
+    #[cfg(not(feature = "certora"))]
+    pub fn get_price_observation(&self, now_slot: u32) -> PriceObservation {
+        // Before the first fill the price is zero so nothing accrues.
+        let cumulative_price_slots: u128 = u64_slice_to_u128(self.cumulative_price_slots)
+            .wrapping_add(
+                self.last_trade_price
+                    .wrapping_mul_slots(now_slot.saturating_sub(self.last_trade_slot)),
+            );
+        PriceObservation {
+            slot: now_slot,
+            cumulative_price_slots,
+        }
+    }
+
+    #[cfg(not(feature = "certora"))]
+    fn record_trade_price(&mut self, price: QuoteAtomsPerBaseAtom, now_slot: u32) {
+        let PriceObservation {
+            cumulative_price_slots,
+            ..
+        } = self.get_price_observation(now_slot);
+        self.cumulative_price_slots = u128_to_u64_slice(cumulative_price_slots);
+        self.last_trade_price = price;
+        self.last_trade_slot = now_slot;
+    }
+    #[cfg(feature = "certora")]
+    fn record_trade_price(&mut self, _price: QuoteAtomsPerBaseAtom, _now_slot: u32) {}
+    pub fn get_fee_config_index(&self) -> DataIndex {
+        if self.version < MARKET_EXTENSIONS_VERSION {
+            return NIL;
+        }
+        self.fee_config_index
+    }
+    pub fn get_trading_config_index(&self) -> DataIndex {
+        if self.version < MARKET_EXTENSIONS_VERSION {
+            return NIL;
+        }
+        self.trading_config_index
+    }
+    pub fn get_authority_config_index(&self) -> DataIndex {
+        if self.version < MARKET_EXTENSIONS_VERSION {
+            return NIL;
+        }
+        self.authority_config_index
+    }
+    pub fn get_event_queue_config_index(&self) -> DataIndex {
+        if self.version < MARKET_EXTENSIONS_VERSION {
+            return NIL;
+        }
+        self.event_queue_config_index
+    }
+    pub fn get_gate_config_index(&self) -> DataIndex {
+        if self.version < MARKET_EXTENSIONS_VERSION {
+            return NIL;
+        }
+        self.gate_config_index
+    }
 
     // Used only in this file to construct iterator
     pub(crate) fn get_bids_root_index(&self) -> DataIndex {
@@ -395,6 +534,41 @@ impl ManifestAccount for MarketFixed {
     }
 }
 
+/// Reading of the market price accumulator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PriceObservation {
+    pub slot: u32,
+    /// Sum over slots of the last trade price. Wraps.
+    pub cumulative_price_slots: u128,
+}
+
+#[cfg(not(feature = "certora"))]
+impl PriceObservation {
+    /// Time weighted average of the last trade price between this and a
+    /// later observation of the same market. A single fill moves the price
+    /// for the slots until the next fill, so manipulating the average costs
+    /// a trade that is left standing for the whole window. Slots before the
+    /// first fill count as zero.
+    pub fn get_twap(
+        &self,
+        later: &PriceObservation,
+    ) -> Result<QuoteAtomsPerBaseAtom, ProgramError> {
+        require!(
+            later.slot > self.slot,
+            ManifestError::InvalidMarketParameters,
+            "Observations must be in order {} {}",
+            self.slot,
+            later.slot,
+        )?;
+        Ok(QuoteAtomsPerBaseAtom::checked_average_from_cumulative(
+            later
+                .cumulative_price_slots
+                .wrapping_sub(self.cumulative_price_slots),
+            later.slot - self.slot,
+        )?)
+    }
+}
+
 /// Fully owned Market, used in clients that can copy.
 pub type MarketValue = DynamicAccount<MarketFixed, Vec<u8>>;
 /// Full market reference type.
@@ -719,12 +893,110 @@ impl<Fixed: DerefOrBorrow<MarketFixed>, Dynamic: DerefOrBorrow<[u8]>>
         )
     }
 
+    pub fn get_fee_config(&self) -> Option<&MarketFeeConfig> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_market();
+        let fee_config_index: DataIndex = fixed.get_fee_config_index();
+        if fee_config_index == NIL {
+            return None;
+        }
+        Some(get_helper_extension::<MarketFeeConfig>(
+            dynamic,
+            fee_config_index,
+        ))
+    }
+
+    pub fn get_trading_config(&self) -> Option<&MarketTradingConfig> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_market();
+        let trading_config_index: DataIndex = fixed.get_trading_config_index();
+        if trading_config_index == NIL {
+            return None;
+        }
+        Some(get_helper_extension::<MarketTradingConfig>(
+            dynamic,
+            trading_config_index,
+        ))
+    }
+
+    pub fn get_authority_config(&self) -> Option<&MarketAuthorityConfig> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_market();
+        let authority_config_index: DataIndex = fixed.get_authority_config_index();
+        if authority_config_index == NIL {
+            return None;
+        }
+        Some(get_helper_extension::<MarketAuthorityConfig>(
+            dynamic,
+            authority_config_index,
+        ))
+    }
+
+    pub fn get_event_queue_config(&self) -> Option<&MarketEventQueueConfig> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_market();
+        let event_queue_config_index: DataIndex = fixed.get_event_queue_config_index();
+        if event_queue_config_index == NIL {
+            return None;
+        }
+        Some(get_helper_extension::<MarketEventQueueConfig>(
+            dynamic,
+            event_queue_config_index,
+        ))
+    }
+
+    pub fn get_gate_config(&self) -> Option<&MarketGateConfig> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_market();
+        let gate_config_index: DataIndex = fixed.get_gate_config_index();
+        if gate_config_index == NIL {
+            return None;
+        }
+        Some(get_helper_extension::<MarketGateConfig>(
+            dynamic,
+            gate_config_index,
+        ))
+    }
+
+    /// Markets without an authority are always active.
+    pub fn get_market_status(&self) -> MarketStatus {
+        self.get_authority_config()
+            .map_or(MarketStatus::Active, |authority_config| {
+                authority_config.get_status()
+            })
+    }
+
+    /// Required for anything that adds funds or matches.
+    pub fn assert_market_active(&self) -> ProgramResult {
+        match self.get_market_status() {
+            MarketStatus::Active => Ok(()),
+            MarketStatus::Paused => Err(ManifestError::MarketPaused.into()),
+            MarketStatus::CancelOnly => Err(ManifestError::MarketCancelOnly.into()),
+        }
+    }
+
+    /// Required for cancels, which are still allowed in cancel only.
+    pub fn assert_market_not_paused(&self) -> ProgramResult {
+        require!(
+            self.get_market_status() != MarketStatus::Paused,
+            ManifestError::MarketPaused,
+            "Market is paused",
+        )
+    }
+
     pub fn get_trader_key_by_index(&self, index: DataIndex) -> &Pubkey {
         let DynamicAccount { dynamic, .. } = self.borrow_market();
 
         &get_helper_seat(dynamic, index).get_value().trader
     }
 
+    /// Key that may place and cancel orders for the seat at index, if any.
+    pub fn get_seat_delegate(&self, index: DataIndex) -> Option<&Pubkey> {
+        let DynamicAccount { dynamic, .. } = self.borrow_market();
+
+        get_helper_seat(dynamic, index)
+            .get_value()
+            .get_delegate_index()
+            .map(|delegate_index| {
+                get_helper_extension::<MarketSeatDelegate>(dynamic, delegate_index).get_delegate()
+            })
+    }
+
     pub fn get_trader_voume(&self, trader: &Pubkey) -> QuoteAtoms {
         let DynamicAccount { fixed, dynamic } = self.borrow_market();
 
@@ -839,6 +1111,171 @@ impl<
         }
     }
 
+    /// Store the fee config in a free block. Only done at market creation.
+    pub fn init_fee_config(&mut self, fee_config: MarketFeeConfig) -> ProgramResult {
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        require!(
+            fixed.get_fee_config_index() == NIL,
+            ManifestError::InvalidMarketParameters,
+            "Fee config already initialized",
+        )?;
+        let free_address: DataIndex =
+            get_free_address_on_market_fixed_for_extension(fixed, dynamic);
+        init_extension(dynamic, free_address, fee_config);
+        fixed.fee_config_index = free_address;
+        Ok(())
+    }
+
+    /// Store the tick, lot and minimum size in a free block. Only done at
+    /// market creation.
+    pub fn init_trading_config(&mut self, trading_config: MarketTradingConfig) -> ProgramResult {
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        require!(
+            fixed.get_trading_config_index() == NIL,
+            ManifestError::InvalidMarketParameters,
+            "Trading config already initialized",
+        )?;
+        let free_address: DataIndex =
+            get_free_address_on_market_fixed_for_extension(fixed, dynamic);
+        init_extension(dynamic, free_address, trading_config);
+        fixed.trading_config_index = free_address;
+        Ok(())
+    }
+
+    /// Store the market authority in a free block. Only done at market
+    /// creation.
+    pub fn init_authority_config(
+        &mut self,
+        authority_config: MarketAuthorityConfig,
+    ) -> ProgramResult {
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        require!(
+            fixed.get_authority_config_index() == NIL,
+            ManifestError::InvalidMarketParameters,
+            "Authority config already initialized",
+        )?;
+        let free_address: DataIndex =
+            get_free_address_on_market_fixed_for_extension(fixed, dynamic);
+        init_extension(dynamic, free_address, authority_config);
+        fixed.authority_config_index = free_address;
+        Ok(())
+    }
+
+    pub fn init_gate_config(&mut self, gate_config: MarketGateConfig) -> ProgramResult {
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        require!(
+            fixed.get_gate_config_index() == NIL,
+            ManifestError::InvalidMarketParameters,
+            "Gate config already initialized",
+        )?;
+        let free_address: DataIndex =
+            get_free_address_on_market_fixed_for_extension(fixed, dynamic);
+        init_extension(dynamic, free_address, gate_config);
+        fixed.gate_config_index = free_address;
+        Ok(())
+    }
+
+    /// Revoked members keep their seat but can only cancel and withdraw. Does
+    /// nothing if the trader has no seat.
+    pub fn set_seat_cancel_only(&mut self, trader: &Pubkey, is_cancel_only: bool) {
+        let trader_index: DataIndex = self.get_trader_index(trader);
+        if trader_index == NIL {
+            return;
+        }
+        let DynamicAccount { dynamic, .. } = self.borrow_mut();
+        get_mut_helper_seat(dynamic, trader_index)
+            .get_mut_value()
+            .set_is_cancel_only(is_cancel_only);
+    }
+
+    fn get_mut_authority_config_checked(
+        &mut self,
+        authority: &Pubkey,
+    ) -> Result<&mut MarketAuthorityConfig, ProgramError> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        let authority_config_index: DataIndex = fixed.get_authority_config_index();
+        require!(
+            authority_config_index != NIL,
+            ManifestError::IncorrectMarketAuthority,
+            "Market does not have an authority",
+        )?;
+        let authority_config: &mut MarketAuthorityConfig =
+            get_mut_helper_extension::<MarketAuthorityConfig>(dynamic, authority_config_index);
+        require!(
+            authority_config.get_authority() == authority,
+            ManifestError::IncorrectMarketAuthority,
+            "Expected authority {} got {}",
+            authority_config.get_authority(),
+            authority,
+        )?;
+        Ok(authority_config)
+    }
+
+    /// Attach an event queue. Only the market authority can do this since
+    /// every later fill then requires the queue account. Markets without an
+    /// authority cannot have a queue.
+    pub fn init_event_queue_config(
+        &mut self,
+        authority: &Pubkey,
+        event_queue: &Pubkey,
+    ) -> ProgramResult {
+        self.get_mut_authority_config_checked(authority)?;
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        require!(
+            fixed.get_event_queue_config_index() == NIL,
+            ManifestError::InvalidMarketParameters,
+            "Event queue already initialized",
+        )?;
+        let free_address: DataIndex =
+            get_free_address_on_market_fixed_for_extension(fixed, dynamic);
+        init_extension(
+            dynamic,
+            free_address,
+            MarketEventQueueConfig::new(*event_queue),
+        );
+        fixed.event_queue_config_index = free_address;
+        Ok(())
+    }
+
+    pub fn set_market_status(&mut self, authority: &Pubkey, status: MarketStatus) -> ProgramResult {
+        self.get_mut_authority_config_checked(authority)?
+            .set_status(status);
+        Ok(())
+    }
+
+    pub fn set_market_authority(
+        &mut self,
+        authority: &Pubkey,
+        new_authority: &Pubkey,
+    ) -> ProgramResult {
+        let authority_config: &mut MarketAuthorityConfig =
+            self.get_mut_authority_config_checked(authority)?;
+        // Renouncing while not active would leave the market stuck.
+        require!(
+            *new_authority != Pubkey::default()
+                || authority_config.get_status() == MarketStatus::Active,
+            ManifestError::InvalidMarketParameters,
+            "Cannot renounce authority unless market is active",
+        )?;
+        authority_config.set_authority(*new_authority);
+        Ok(())
+    }
+
+    /// Removes all collected fees for one side and returns the amount.
+    pub fn collect_fees(&mut self, is_base: bool) -> Result<u64, ProgramError> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        let fee_config_index: DataIndex = fixed.get_fee_config_index();
+        require!(
+            fee_config_index != NIL,
+            ManifestError::InvalidMarketParameters,
+            "Market does not have fees",
+        )?;
+        Ok(
+            get_mut_helper_extension::<MarketFeeConfig>(dynamic, fee_config_index)
+                .take_collected(is_base),
+        )
+    }
+
     pub fn market_expand(&mut self) -> ProgramResult {
         let DynamicAccount { fixed, dynamic } = self.borrow_mut();
         let mut free_list: FreeList<MarketUnusedFreeListPadding> =
@@ -896,6 +1333,7 @@ impl<
     // seat.
     pub fn release_seat(&mut self, trader: &Pubkey) -> ProgramResult {
         let trader_seat_index: DataIndex = self.get_trader_index(trader);
+        self.clear_seat_delegate(trader_seat_index)?;
         let DynamicAccount { fixed, dynamic } = self.borrow_mut();
 
         let mut claimed_seats_tree: ClaimedSeatTree =
@@ -908,6 +1346,56 @@ impl<
         Ok(())
     }
 
+    /// Set or replace the delegate for a seat. The first delegate takes a free
+    /// block, replacing it reuses that block.
+    pub fn set_seat_delegate(
+        &mut self,
+        trader_index: DataIndex,
+        delegate: &Pubkey,
+    ) -> ProgramResult {
+        assert_already_has_seat(trader_index)?;
+        require!(
+            *delegate != Pubkey::default()
+                && delegate != self.get_trader_key_by_index(trader_index),
+            ManifestError::InvalidSeatDelegate,
+            "Invalid seat delegate {}",
+            delegate,
+        )?;
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        let delegate_index: Option<DataIndex> = get_helper_seat(dynamic, trader_index)
+            .get_value()
+            .get_delegate_index();
+        match delegate_index {
+            Some(delegate_index) => {
+                *get_mut_helper_extension::<MarketSeatDelegate>(dynamic, delegate_index) =
+                    MarketSeatDelegate::new(*delegate);
+            }
+            None => {
+                let free_address: DataIndex =
+                    get_free_address_on_market_fixed_for_extension(fixed, dynamic);
+                init_extension(dynamic, free_address, MarketSeatDelegate::new(*delegate));
+                get_mut_helper_seat(dynamic, trader_index)
+                    .get_mut_value()
+                    .set_delegate_index(Some(free_address));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove the delegate for a seat and put its block back on the free list.
+    /// Does nothing if there is no delegate.
+    pub fn clear_seat_delegate(&mut self, trader_index: DataIndex) -> ProgramResult {
+        assert_already_has_seat(trader_index)?;
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+        let claimed_seat: &mut ClaimedSeat =
+            get_mut_helper_seat(dynamic, trader_index).get_mut_value();
+        if let Some(delegate_index) = claimed_seat.get_delegate_index() {
+            claimed_seat.set_delegate_index(None);
+            release_address_on_market_fixed_for_extension(fixed, dynamic, delegate_index);
+        }
+        Ok(())
+    }
+
     pub fn deposit(
         &mut self,
         trader_index: DataIndex,
@@ -954,12 +1442,17 @@ impl<
             market,
             trader_index,
             num_base_atoms,
-            price,
+            mut price,
             is_bid,
             last_valid_slot,
             order_type,
             global_trade_accounts_opts,
             current_slot,
+            self_trade_behavior,
+            client_order_id,
+            reverse_price_bounds: _,
+            iceberg_display_base_atoms: _,
+            event_queue_opt,
         } = args;
         assert_already_has_seat(trader_index)?;
         let now_slot: u32 = current_slot.unwrap_or_else(|| get_now_slot());
@@ -971,6 +1464,27 @@ impl<
 
         let DynamicAccount { fixed, dynamic } = self.borrow_mut();
 
+        require!(
+            !get_helper_seat(dynamic, trader_index)
+                .get_value()
+                .get_is_cancel_only(),
+            ManifestError::TraderCancelOnly,
+            "Trader at index {} is cancel only",
+            trader_index,
+        )?;
+
+        let trading_config_index: DataIndex = fixed.get_trading_config_index();
+        let tick_size: QuoteAtomsPerBaseAtom = if trading_config_index != NIL {
+            let trading_config: &MarketTradingConfig =
+                get_helper_extension::<MarketTradingConfig>(dynamic, trading_config_index);
+            if order_type_can_rest(order_type) {
+                trading_config.verify_order(price, num_base_atoms)?;
+            }
+            trading_config.get_tick_size()
+        } else {
+            QuoteAtomsPerBaseAtom::ZERO
+        };
+
         let mut current_maker_order_index: DataIndex = if is_bid {
             fixed.asks_best_index
         } else {
@@ -980,6 +1494,21 @@ impl<
         let mut total_base_atoms_traded: BaseAtoms = BaseAtoms::ZERO;
         let mut total_quote_atoms_traded: QuoteAtoms = QuoteAtoms::ZERO;
 
+        // Fees are taken from what the taker receives, so they are in base for
+        // bids and quote for asks. Maker rebates are paid out of the fee in the
+        // same token.
+        let fee_config_index: DataIndex = fixed.get_fee_config_index();
+        let fee_config_opt: Option<MarketFeeConfig> = if fee_config_index != NIL {
+            Some(*get_helper_extension::<MarketFeeConfig>(
+                dynamic,
+                fee_config_index,
+            ))
+        } else {
+            None
+        };
+        let mut total_taker_fee_atoms: u64 = 0;
+        let mut total_fee_atoms_accrued: u64 = 0;
+
         let mut remaining_base_atoms: BaseAtoms = num_base_atoms;
         while remaining_base_atoms > BaseAtoms::ZERO && is_not_nil!(current_maker_order_index) {
             let maker_order: &RestingOrder =
@@ -998,6 +1527,8 @@ impl<
                     dynamic,
                     current_maker_order_index,
                     global_trade_accounts_opts,
+                    event_queue_opt,
+                    now_slot,
                 )?;
                 current_maker_order_index = next_maker_order_index;
                 continue;
@@ -1010,6 +1541,16 @@ impl<
                 break;
             }
 
+            // Post only slide orders never take. Move the price one tick
+            // behind the best remaining opposite order and rest there.
+            if order_type == OrderType::PostOnlySlide {
+                price = maker_order
+                    .get_price()
+                    .checked_tick(!is_bid)?
+                    .checked_round_to_tick(tick_size, !is_bid)?;
+                break;
+            }
+
             // Got a match. First make sure we are allowed to match. We check
             // inside the matching rather than skipping the matching altogether
             // because post only orders should fail, not produce a crossed book.
@@ -1025,8 +1566,79 @@ impl<
                 remaining_base_atoms
             };
 
+            // Self trade prevention. Nothing is exchanged with the trader's own
+            // order, so no FillLog is emitted and no volume is recorded.
+            if maker_trader_index == trader_index {
+                match self_trade_behavior {
+                    SelfTradeBehavior::Allow => {}
+                    SelfTradeBehavior::Abort => {
+                        return Err(ManifestError::WouldSelfTrade.into());
+                    }
+                    SelfTradeBehavior::CancelTaker => {
+                        require!(
+                            order_type != OrderType::FillOrKill,
+                            ManifestError::FillOrKillNotFilled,
+                            "Fill or kill cancelled by self trade with {} remaining",
+                            remaining_base_atoms,
+                        )?;
+                        // Zero out the remainder so that it does not rest.
+                        remaining_base_atoms = BaseAtoms::ZERO;
+                        break;
+                    }
+                    SelfTradeBehavior::CancelMaker | SelfTradeBehavior::DecrementBoth => {
+                        let is_decrement: bool =
+                            self_trade_behavior == SelfTradeBehavior::DecrementBoth;
+                        // Decremented size is not a fill, so a fill or kill
+                        // could otherwise pass with nothing received.
+                        require!(
+                            !is_decrement || order_type != OrderType::FillOrKill,
+                            ManifestError::FillOrKillNotFilled,
+                            "Fill or kill decremented by self trade with {} remaining",
+                            remaining_base_atoms,
+                        )?;
+                        let trader: Pubkey =
+                            get_helper_seat(dynamic, trader_index).get_value().trader;
+                        let next_maker_order_index: DataIndex = get_next_candidate_match_index(
+                            fixed,
+                            dynamic,
+                            current_maker_order_index,
+                            is_bid,
+                        );
+                        if did_fully_match_resting_order || !is_decrement {
+                            remove_and_update_balances(
+                                fixed,
+                                dynamic,
+                                current_maker_order_index,
+                                global_trade_accounts_opts,
+                                event_queue_opt,
+                                now_slot,
+                            )?;
+                            emit_stack(CancelOrderLog {
+                                market,
+                                trader,
+                                order_sequence_number: maker_sequence_number,
+                            })?;
+                        } else {
+                            reduce_and_update_balances(
+                                fixed,
+                                dynamic,
+                                current_maker_order_index,
+                                base_atoms_traded,
+                            )?;
+                        }
+                        if is_decrement {
+                            remaining_base_atoms =
+                                remaining_base_atoms.checked_sub(base_atoms_traded)?;
+                        }
+                        current_maker_order_index = next_maker_order_index;
+                        continue;
+                    }
+                }
+            }
+
             let matched_price: QuoteAtomsPerBaseAtom = maker_order.get_price();
             let maker_order_type: OrderType = maker_order.get_order_type();
+            let maker_client_order_id: u64 = maker_order.get_client_order_id();
             let maker_price_reverse: Result<QuoteAtomsPerBaseAtom, _> = maker_order.reverse_price();
 
             // on full fill: round in favor of the taker
@@ -1043,7 +1655,15 @@ impl<
                 .trader;
             let taker: Pubkey = get_helper_seat(dynamic, trader_index).get_value().trader;
             let is_global: bool = maker_order.is_global();
-            let is_maker_reverse: bool = maker_order.is_reversible();
+            // Bounded reverse orders stop flipping once the flip would land
+            // outside their range. The proceeds stay in the seat balance.
+            let maker_reverse_price_bounds: ReversePriceBounds =
+                maker_order.get_reverse_price_bounds();
+            let is_maker_reverse: bool = maker_order.is_reversible()
+                && match maker_price_reverse {
+                    Ok(price_reverse) => maker_reverse_price_bounds.contains(price_reverse)?,
+                    Err(_) => false,
+                };
 
             let maker_reverse_spread: u16 = maker_order.get_reverse_spread();
 
@@ -1087,6 +1707,8 @@ impl<
                         dynamic,
                         current_maker_order_index,
                         global_trade_accounts_opts,
+                        event_queue_opt,
+                        now_slot,
                     )?;
                     current_maker_order_index = next_maker_order_index;
                     continue;
@@ -1121,14 +1743,14 @@ impl<
             // over.  The extra one that is no longer needed for taker rounding
             // is not brought over, so dont credit the maker for it.
             if !is_bid && !is_global {
-                // These are only used when is_bid.
+                // These are only used when is_bid. Iceberg bids are backed by
+                // the total including the reserve.
+                let maker_total_base_atoms: BaseAtoms = maker_order.get_total_base_atoms()?;
                 let previous_maker_quote_atoms_allocated: QuoteAtoms =
-                    matched_price.checked_quote_for_base(maker_order.get_num_base_atoms(), true)?;
+                    matched_price.checked_quote_for_base(maker_total_base_atoms, true)?;
                 let new_maker_quote_atoms_allocated: QuoteAtoms = matched_price
                     .checked_quote_for_base(
-                        maker_order
-                            .get_num_base_atoms()
-                            .checked_sub(base_atoms_traded)?,
+                        maker_total_base_atoms.checked_sub(base_atoms_traded)?,
                         true,
                     )?;
                 let bonus_atom_or_zero: QuoteAtoms = previous_maker_quote_atoms_allocated
@@ -1175,6 +1797,15 @@ impl<
                     base_atoms_traded.into()
                 },
             )?;
+            let received_atoms: u64 = if is_bid {
+                base_atoms_traded.as_u64()
+            } else {
+                quote_atoms_traded.as_u64()
+            };
+            let (taker_fee_atoms, maker_rebate_atoms) = match &fee_config_opt {
+                Some(fee_config) => fee_config.get_fee_and_rebate(received_atoms),
+                None => (0, 0),
+            };
             // Increase taker
             update_balance(
                 fixed,
@@ -1182,12 +1813,30 @@ impl<
                 trader_index,
                 is_bid,
                 true,
-                if is_bid {
-                    base_atoms_traded.into()
-                } else {
-                    quote_atoms_traded.into()
-                },
+                received_atoms
+                    .checked_sub(taker_fee_atoms)
+                    .ok_or(ManifestError::Overflow)?,
             )?;
+            if maker_rebate_atoms > 0 {
+                update_balance(
+                    fixed,
+                    dynamic,
+                    maker_trader_index,
+                    is_bid,
+                    true,
+                    maker_rebate_atoms,
+                )?;
+            }
+            total_taker_fee_atoms = total_taker_fee_atoms
+                .checked_add(taker_fee_atoms)
+                .ok_or(ManifestError::Overflow)?;
+            total_fee_atoms_accrued = total_fee_atoms_accrued
+                .checked_add(
+                    taker_fee_atoms
+                        .checked_sub(maker_rebate_atoms)
+                        .ok_or(ManifestError::Overflow)?,
+                )
+                .ok_or(ManifestError::Overflow)?;
 
             // record maker & taker volume
             record_volume_by_trader_index(dynamic, maker_trader_index, quote_atoms_traded);
@@ -1206,10 +1855,81 @@ impl<
                 taker_sequence_number: fixed.order_sequence_number,
                 taker_is_buy: PodBool::from(is_bid),
                 is_maker_global: PodBool::from(is_global),
-                _padding: [0; 14],
+                _padding: [0; 6],
+                maker_client_order_id,
+                taker_client_order_id: client_order_id,
+                taker_fee_atoms,
+                maker_rebate_atoms,
             })?;
+            record_event(
+                fixed,
+                dynamic,
+                event_queue_opt,
+                now_slot,
+                MarketEvent::new_fill(
+                    maker,
+                    taker,
+                    matched_price,
+                    base_atoms_traded,
+                    quote_atoms_traded,
+                    maker_sequence_number,
+                    fixed.order_sequence_number,
+                    maker_client_order_id,
+                    client_order_id,
+                    taker_fee_atoms,
+                    maker_rebate_atoms,
+                    !is_bid,
+                ),
+            )?;
+            fixed.record_trade_price(matched_price, now_slot);
 
-            if did_fully_match_resting_order {
+            let maker_iceberg_reserve: BaseAtoms =
+                get_helper::<RBNode<RestingOrder>>(dynamic, current_maker_order_index)
+                    .get_value()
+                    .get_iceberg_reserve_base_atoms();
+            if did_fully_match_resting_order && maker_iceberg_reserve > BaseAtoms::ZERO {
+                // Refill the iceberg from its reserve. It keeps its block but
+                // is reinserted with a new sequence number so it loses time
+                // priority to other orders at the same price.
+                let refill_sequence_number: u64 = fixed.order_sequence_number;
+                fixed.order_sequence_number = refill_sequence_number.wrapping_add(1);
+
+                remove_order_from_tree(fixed, dynamic, current_maker_order_index, !is_bid)?;
+                let maker_order: &mut RestingOrder =
+                    get_mut_helper::<RBNode<RestingOrder>>(dynamic, current_maker_order_index)
+                        .get_mut_value();
+                maker_order.reduce(base_atoms_traded)?;
+                maker_order.refill_iceberg(refill_sequence_number)?;
+                let refilled_order: RestingOrder = *maker_order;
+                insert_order_into_tree(
+                    !is_bid,
+                    fixed,
+                    dynamic,
+                    current_maker_order_index,
+                    &refilled_order,
+                );
+                set_payload_order(dynamic, current_maker_order_index);
+
+                emit_stack(IcebergRefillLog {
+                    market,
+                    trader: maker,
+                    price: matched_price,
+                    base_atoms: refilled_order.get_num_base_atoms(),
+                    previous_order_sequence_number: maker_sequence_number,
+                    order_sequence_number: refill_sequence_number,
+                    order_index: current_maker_order_index,
+                    _padding: [0; 4],
+                })?;
+
+                // Everything ahead of the maker was removed, so the next
+                // candidate is the new best which may be the refilled order.
+                remaining_base_atoms = remaining_base_atoms.checked_sub(base_atoms_traded)?;
+                current_maker_order_index = if is_bid {
+                    fixed.asks_best_index
+                } else {
+                    fixed.bids_best_index
+                };
+            } else if did_fully_match_resting_order {
                 // Get paid for removing a global order.
                 if get_helper::<RBNode<RestingOrder>>(dynamic, current_maker_order_index)
                     .get_value()
@@ -1326,6 +2046,9 @@ impl<
                             maker_order_type,
                         )?;
                         new_reverse_resting_order.set_reverse_spread(maker_reverse_spread);
+                        new_reverse_resting_order.set_client_order_id(maker_client_order_id);
+                        new_reverse_resting_order
+                            .set_reverse_price_bounds(maker_reverse_price_bounds);
                         insert_order_into_tree(
                             is_bid,
                             fixed,
@@ -1360,9 +2083,22 @@ impl<
             }
         }
 
+        require!(
+            order_type != OrderType::FillOrKill || remaining_base_atoms == BaseAtoms::ZERO,
+            ManifestError::FillOrKillNotFilled,
+            "Fill or kill only matched {} of {}",
+            total_base_atoms_traded,
+            num_base_atoms,
+        )?;
+
         // Record volume on market
         fixed.quote_volume = fixed.quote_volume.wrapping_add(total_quote_atoms_traded);
 
+        if total_fee_atoms_accrued > 0 {
+            get_mut_helper_extension::<MarketFeeConfig>(dynamic, fee_config_index)
+                .accrue(is_bid, total_fee_atoms_accrued);
+        }
+
         // Bump the order sequence number even for orders which do not end up
         // resting.
         let order_sequence_number: u64 = fixed.order_sequence_number;
@@ -1378,15 +2114,17 @@ impl<
                 order_index: NIL,
                 base_atoms_traded: total_base_atoms_traded,
                 quote_atoms_traded: total_quote_atoms_traded,
+                taker_fee_atoms: total_taker_fee_atoms,
             });
         }
 
         self.rest_remaining(
-            args,
+            AddOrderToMarketArgs { price, ..args },
             remaining_base_atoms,
             order_sequence_number,
             total_base_atoms_traded,
             total_quote_atoms_traded,
+            total_taker_fee_atoms,
         )
     }
 
@@ -1399,6 +2137,7 @@ impl<
         order_sequence_number: u64,
         total_base_atoms_traded: BaseAtoms,
         total_quote_atoms_traded: QuoteAtoms,
+        total_taker_fee_atoms: u64,
     ) -> Result<AddOrderToMarketResult, ProgramError> {
         self.rest_remaining(
             args,
@@ -1406,6 +2145,7 @@ impl<
             order_sequence_number,
             total_base_atoms_traded,
             total_quote_atoms_traded,
+            total_taker_fee_atoms,
         )
     }
 
@@ -1416,6 +2156,7 @@ impl<
         order_sequence_number: u64,
         total_base_atoms_traded: BaseAtoms,
         total_quote_atoms_traded: QuoteAtoms,
+        total_taker_fee_atoms: u64,
     ) -> Result<AddOrderToMarketResult, ProgramError> {
         let AddOrderToMarketArgs {
             trader_index,
@@ -1424,6 +2165,9 @@ impl<
             last_valid_slot,
             order_type,
             global_trade_accounts_opts,
+            client_order_id,
+            reverse_price_bounds,
+            iceberg_display_base_atoms,
             ..
         } = args;
         let DynamicAccount { fixed, dynamic } = self.borrow_mut();
@@ -1435,9 +2179,15 @@ impl<
             get_free_address_on_market_fixed_for_ask_order(fixed, dynamic)
         };
 
+        // Only the display slice of an iceberg is on the book.
+        let display_base_atoms: BaseAtoms = if order_type == OrderType::Iceberg {
+            iceberg_display_base_atoms.min(remaining_base_atoms)
+        } else {
+            remaining_base_atoms
+        };
         let mut resting_order: RestingOrder = RestingOrder::new(
             trader_index,
-            remaining_base_atoms,
+            display_base_atoms,
             price,
             order_sequence_number,
             if !order_type.is_reversible() {
@@ -1451,7 +2201,15 @@ impl<
 
         if order_type.is_reversible() {
             resting_order.set_reverse_spread(last_valid_slot as u16);
+            resting_order.set_reverse_price_bounds(reverse_price_bounds);
+        }
+        if order_type == OrderType::Iceberg {
+            resting_order.set_iceberg(
+                iceberg_display_base_atoms,
+                remaining_base_atoms.checked_sub(display_base_atoms)?,
+            )?;
         }
+        resting_order.set_client_order_id(client_order_id);
 
         if resting_order.is_global() {
             let global_trade_account_opt = &global_trade_accounts_opts[if is_bid { 1 } else { 0 }];
@@ -1486,6 +2244,7 @@ impl<
             order_index: free_address,
             base_atoms_traded: total_base_atoms_traded,
             quote_atoms_traded: total_quote_atoms_traded,
+            taker_fee_atoms: total_taker_fee_atoms,
         })
     }
 
@@ -1495,6 +2254,7 @@ impl<
         trader_index: DataIndex,
         order_sequence_number: u64,
         global_trade_accounts_opts: &[Option<GlobalTradeAccounts>; 2],
+        event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
     ) -> ProgramResult {
         let DynamicAccount { fixed, dynamic } = self.borrow_mut();
 
@@ -1540,7 +2300,11 @@ impl<
 
         if is_not_nil!(index_to_remove) {
             // Cancel order by index will update balances.
-            self.cancel_order_by_index(index_to_remove, global_trade_accounts_opts)?;
+            self.cancel_order_by_index(
+                index_to_remove,
+                global_trade_accounts_opts,
+                event_queue_opt,
+            )?;
             return Ok(());
         }
 
@@ -1554,30 +2318,188 @@ impl<
         Ok(())
     }
 
+    // Does a linear scan over the orderbook to find the trader's order with the
+    // client order id. Returns the sequence number of the cancelled order.
+    pub fn cancel_order_by_client_order_id(
+        &mut self,
+        trader_index: DataIndex,
+        client_order_id: u64,
+        global_trade_accounts_opts: &[Option<GlobalTradeAccounts>; 2],
+        event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
+    ) -> Result<u64, ProgramError> {
+        let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+
+        let mut index_to_remove: DataIndex = NIL;
+        let mut order_sequence_number: u64 = 0;
+
+        for (root_index, best_index) in [
+            (fixed.asks_root_index, fixed.asks_best_index),
+            (fixed.bids_root_index, fixed.bids_best_index),
+        ] {
+            let tree: BooksideReadOnly = BooksideReadOnly::new(dynamic, root_index, best_index);
+            for (index, resting_order) in tree.iter::<RestingOrder>() {
+                if resting_order.get_trader_index() == trader_index
+                    && resting_order.get_client_order_id() == client_order_id
+                {
+                    require!(
+                        index_to_remove == NIL,
+                        ManifestError::InvalidCancel,
+                        "Multiple orders match client order id {}",
+                        client_order_id,
+                    )?;
+                    index_to_remove = index;
+                    order_sequence_number = resting_order.get_sequence_number();
+                }
+            }
+        }
+
+        // Do not fail silently.
+        require!(
+            is_not_nil!(index_to_remove),
+            ManifestError::InvalidCancel,
+            "Unable to find the order for cancel. Client order id: {}",
+            client_order_id
+        )?;
+
+        // Cancel order by index will update balances.
+        self.cancel_order_by_index(index_to_remove, global_trade_accounts_opts, event_queue_opt)?;
+        Ok(order_sequence_number)
+    }
+
+    // Cancels every order for the trader, optionally filtered by side and order
+    // type. Returns the sequence number and index of each cancelled order.
+    pub fn cancel_all_orders(
+        &mut self,
+        trader_index: DataIndex,
+        is_bid_opt: Option<bool>,
+        order_type_opt: Option<OrderType>,
+        global_trade_accounts_opts: &[Option<GlobalTradeAccounts>; 2],
+        event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
+    ) -> Result<Vec<(u64, DataIndex)>, ProgramError> {
+        let mut orders_to_remove: Vec<(u64, DataIndex)> = Vec::new();
+        {
+            let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+            for (is_bid, root_index, best_index) in [
+                (false, fixed.asks_root_index, fixed.asks_best_index),
+                (true, fixed.bids_root_index, fixed.bids_best_index),
+            ] {
+                if is_bid_opt.is_some_and(|side| side != is_bid) {
+                    continue;
+                }
+                let tree: BooksideReadOnly = BooksideReadOnly::new(dynamic, root_index, best_index);
+                for (index, resting_order) in tree.iter::<RestingOrder>() {
+                    if resting_order.get_trader_index() == trader_index
+                        && order_type_opt.map_or(true, |order_type| {
+                            resting_order.get_order_type() == order_type
+                        })
+                    {
+                        orders_to_remove.push((resting_order.get_sequence_number(), index));
+                    }
+                }
+            }
+        }
+
+        // Removal is done after the iteration because removing rebalances the
+        // tree that is being walked.
+        for (_, index) in orders_to_remove.iter() {
+            self.cancel_order_by_index(*index, global_trade_accounts_opts, event_queue_opt)?;
+        }
+        Ok(orders_to_remove)
+    }
+
+    // Removes regular orders that are expired or have no size left and returns
+    // their funds to the owner seats. Candidates are the given indices and the
+    // best num_to_scan_per_side orders on each side. Indices that are not
+    // resting orders anymore are skipped so that racing cranks do not fail.
+    // Returns the trader index and sequence number of each removed order.
+    pub fn clean_expired_orders(
+        &mut self,
+        order_indices: &[DataIndex],
+        num_to_scan_per_side: u32,
+        now_slot: u32,
+        event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
+    ) -> Result<Vec<(DataIndex, u64)>, ProgramError> {
+        let mut candidate_indices: Vec<DataIndex> = order_indices.to_vec();
+        {
+            let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+            for (root_index, best_index) in [
+                (fixed.asks_root_index, fixed.asks_best_index),
+                (fixed.bids_root_index, fixed.bids_best_index),
+            ] {
+                let tree: BooksideReadOnly = BooksideReadOnly::new(dynamic, root_index, best_index);
+                candidate_indices.extend(
+                    tree.iter::<RestingOrder>()
+                        .take(num_to_scan_per_side as usize)
+                        .map(|(index, _)| index),
+                );
+            }
+        }
+
+        let mut removed_orders: Vec<(DataIndex, u64)> = Vec::new();
+        for order_index in candidate_indices {
+            let DynamicAccount { fixed, dynamic } = self.borrow_mut();
+            if order_index % (MARKET_BLOCK_SIZE as DataIndex) != 0
+                || order_index >= fixed.num_bytes_allocated
+            {
+                continue;
+            }
+            let resting_order_node: &RBNode<RestingOrder> = get_helper_order(dynamic, order_index);
+            if resting_order_node.get_payload_type() != MarketDataTreeNodeType::RestingOrder as u8 {
+                continue;
+            }
+            let resting_order: &RestingOrder = resting_order_node.get_value();
+            // Global orders need the global account to be removed. Those are
+            // cleaned through GlobalClean.
+            if resting_order.is_global()
+                || !(resting_order.is_expired(now_slot)
+                    || resting_order.get_num_base_atoms() == BaseAtoms::ZERO)
+            {
+                continue;
+            }
+            removed_orders.push((
+                resting_order.get_trader_index(),
+                resting_order.get_sequence_number(),
+            ));
+            remove_and_update_balances(
+                fixed,
+                dynamic,
+                order_index,
+                &[None, None],
+                event_queue_opt,
+                now_slot,
+            )?;
+        }
+        Ok(removed_orders)
+    }
+
     #[cfg_attr(feature = "certora", cvt_hook_end(cancel_order_by_index_was_called()))]
     pub fn cancel_order_by_index(
         &mut self,
         order_index: DataIndex,
         global_trade_accounts_opts: &[Option<GlobalTradeAccounts>; 2],
+        event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
     ) -> ProgramResult {
         let DynamicAccount { fixed, dynamic } = self.borrow_mut();
         // TODO: Undo expansion here when it was just
         // remove_and_update_balances(fixed, dynamic, order_index, global_trade_accounts_opts)?;
         // because the tracking for
 
+        record_cancel_event(fixed, dynamic, order_index, event_queue_opt, get_now_slot())?;
+
         let resting_order: &RestingOrder = get_helper_order(dynamic, order_index).get_value();
         let is_bid: bool = resting_order.get_is_bid();
 
         // Important to round up because there was an extra atom taken for full
         // taker rounding when the order was placed.
+        let total_base_atoms: BaseAtoms = resting_order.get_total_base_atoms()?;
         let amount_atoms: u64 = if is_bid {
             (resting_order
                 .get_price()
-                .checked_quote_for_base(resting_order.get_num_base_atoms(), true)
+                .checked_quote_for_base(total_base_atoms, true)
                 .unwrap())
             .into()
         } else {
-            resting_order.get_num_base_atoms().into()
+            total_base_atoms.into()
         };
 
         // Update the accounting for the order that was just canceled.
@@ -1858,7 +2780,17 @@ fn remove_and_update_balances(
     dynamic: &mut [u8],
     order_to_remove_index: DataIndex,
     global_trade_accounts_opts: &[Option<GlobalTradeAccounts>; 2],
+    event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
+    now_slot: u32,
 ) -> ProgramResult {
+    record_cancel_event(
+        fixed,
+        dynamic,
+        order_to_remove_index,
+        event_queue_opt,
+        now_slot,
+    )?;
+
     let resting_order_to_remove: &RestingOrder =
         get_helper_order(dynamic, order_to_remove_index).get_value();
     let order_to_remove_is_bid: bool = resting_order_to_remove.get_is_bid();
@@ -1875,13 +2807,14 @@ fn remove_and_update_balances(
         // ask. If the resting order is bid, multiply by price and round
         // in favor of the taker which here means up. The maker places
         // the minimum number of atoms required.
+        let total_base_atoms: BaseAtoms = resting_order_to_remove.get_total_base_atoms()?;
         let amount_atoms_to_return: u64 = if order_to_remove_is_bid {
             resting_order_to_remove
                 .get_price()
-                .checked_quote_for_base(resting_order_to_remove.get_num_base_atoms(), true)?
+                .checked_quote_for_base(total_base_atoms, true)?
                 .as_u64()
         } else {
-            resting_order_to_remove.get_num_base_atoms().as_u64()
+            total_base_atoms.as_u64()
         };
         update_balance(
             fixed,
@@ -1901,6 +2834,130 @@ fn remove_and_update_balances(
     Ok(())
 }
 
+// Writes the event to the market event queue with the slot the caller used.
+// Does nothing when the market does not have one. When it does, fills require
+// the queue so that no fill is missed. Cancels never fail because of the
+// queue so that makers can always pull their orders. They are skipped when it
+// is not included and counted as dropped when it is full.
+fn record_event(
+    fixed: &MarketFixed,
+    dynamic: &[u8],
+    event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
+    now_slot: u32,
+    mut event: MarketEvent,
+) -> ProgramResult {
+    let event_queue_config_index: DataIndex = fixed.get_event_queue_config_index();
+    if event_queue_config_index == NIL {
+        return Ok(());
+    }
+    let is_cancel: bool = event.get_event_type()? == MarketEventType::Cancel;
+    if is_cancel && event_queue_opt.is_none() {
+        return Ok(());
+    }
+    let expected_event_queue: &Pubkey =
+        get_helper_extension::<MarketEventQueueConfig>(dynamic, event_queue_config_index)
+            .get_event_queue();
+    require!(
+        event_queue_opt
+            .as_ref()
+            .is_some_and(|event_queue| event_queue.key == expected_event_queue),
+        ManifestError::MissingEventQueue,
+        "Market event queue {} was not included",
+        expected_event_queue,
+    )?;
+
+    let event_queue_data: &mut RefMut<&mut [u8]> =
+        &mut event_queue_opt.as_ref().unwrap().try_borrow_mut_data()?;
+    let mut event_queue: EventQueueRefMut = get_mut_dynamic_account(event_queue_data);
+    event.slot = now_slot;
+    if is_cancel {
+        event_queue.push_event_or_drop(event);
+    } else {
+        event_queue.push_event(event)?;
+    }
+    Ok(())
+}
+
+// Records the cancel of a resting order. Must be called before it is removed.
+fn record_cancel_event(
+    fixed: &MarketFixed,
+    dynamic: &[u8],
+    order_index: DataIndex,
+    event_queue_opt: &Option<ManifestAccountInfo<EventQueueFixed>>,
+    now_slot: u32,
+) -> ProgramResult {
+    if fixed.get_event_queue_config_index() == NIL {
+        return Ok(());
+    }
+    let resting_order: &RestingOrder = get_helper_order(dynamic, order_index).get_value();
+    let maker: Pubkey = get_helper_seat(dynamic, resting_order.get_trader_index())
+        .get_value()
+        .trader;
+    record_event(
+        fixed,
+        dynamic,
+        event_queue_opt,
+        now_slot,
+        MarketEvent::new_cancel(
+            maker,
+            resting_order.get_price(),
+            resting_order.get_total_base_atoms()?,
+            resting_order.get_sequence_number(),
+            resting_order.get_client_order_id(),
+            resting_order.get_is_bid(),
+        ),
+    )
+}
+
+// Shrink a resting order without trading against it and return the funds that
+// were backing the removed size to the owner.
+fn reduce_and_update_balances(
+    fixed: &mut MarketFixed,
+    dynamic: &mut [u8],
+    order_index: DataIndex,
+    num_base_atoms_to_remove: BaseAtoms,
+) -> ProgramResult {
+    let resting_order: &RestingOrder = get_helper_order(dynamic, order_index).get_value();
+    let is_bid: bool = resting_order.get_is_bid();
+    let trader_index: DataIndex = resting_order.get_trader_index();
+
+    // Global order balances are accounted for on the global accounts, not on
+    // the market. For bids, return the difference in rounded up quote so that
+    // a later cancel still returns exactly what was locked.
+    let amount_atoms_to_return: u64 = if resting_order.is_global() {
+        0
+    } else if is_bid {
+        let price: QuoteAtomsPerBaseAtom = resting_order.get_price();
+        let num_base_atoms: BaseAtoms = resting_order.get_total_base_atoms()?;
+        price
+            .checked_quote_for_base(num_base_atoms, true)?
+            .checked_sub(price.checked_quote_for_base(
+                num_base_atoms.checked_sub(num_base_atoms_to_remove)?,
+                true,
+            )?)?
+            .as_u64()
+    } else {
+        num_base_atoms_to_remove.as_u64()
+    };
+
+    #[cfg(feature = "certora")]
+    remove_from_orderbook_balance(fixed, dynamic, order_index);
+    get_mut_helper_order(dynamic, order_index)
+        .get_mut_value()
+        .reduce(num_base_atoms_to_remove)?;
+    #[cfg(feature = "certora")]
+    add_to_orderbook_balance(fixed, dynamic, order_index);
+
+    update_balance(
+        fixed,
+        dynamic,
+        trader_index,
+        !is_bid,
+        true,
+        amount_atoms_to_return,
+    )
+}
+
 #[cfg(any(test, feature = "no-clock"))]
 pub fn create_empty_market(
     base_mint: &str,
@@ -0,0 +1,438 @@
+use std::mem::size_of;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use bytemuck::{Pod, Zeroable};
+use hypertree::{get_helper, get_mut_helper, DataIndex, Get, RBTREE_OVERHEAD_BYTES};
+use num_enum::{IntoPrimitive, TryFromPrimitive};
+use shank::ShankType;
+use solana_program::{entrypoint::ProgramResult, pubkey::Pubkey};
+use static_assertions::const_assert_eq;
+
+use crate::{
+    program::{batch_update::MarketDataTreeNodeType, ManifestError},
+    quantities::{BaseAtoms, QuoteAtoms, QuoteAtomsPerBaseAtom, WrapperU64},
+    require,
+};
+
+use super::MARKET_EXTENSION_SIZE;
+
+// Market extensions hold market level config that does not fit in MarketFixed.
+// Each one lives in a block in the dynamic region that is taken from the free
+// list when the market is created. They are never inserted into a tree and
+// never freed. The index of each is stored in MarketFixed.
+//
+// Seat delegates use the same kind of block but are per seat. They are taken
+// when a delegate is first set, freed when it is cleared, and their index is
+// stored in the ClaimedSeat.
+
+/// Fee rates are expressed as a mantissa over this denominator.
+pub const FEE_DENOMINATOR: u64 = 1_000_000_000;
+
+/// Occupies the same bytes as the RBNode header so the payload type of every
+/// block in the dynamic region can be read the same way.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, Zeroable, Pod)]
+pub struct MarketExtensionHeader {
+    _tree_padding: [u8; 13],
+    payload_type: u8,
+    _padding: [u8; 2],
+}
+const_assert_eq!(size_of::<MarketExtensionHeader>(), RBTREE_OVERHEAD_BYTES);
+impl Get for MarketExtensionHeader {}
+
+impl MarketExtensionHeader {
+    pub fn new() -> Self {
+        MarketExtensionHeader {
+            payload_type: MarketDataTreeNodeType::Extension as u8,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_payload_type(&self) -> u8 {
+        self.payload_type
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct MarketFeeConfig {
+    /// Owner of the token accounts that collected fees are sent to.
+    fee_recipient: Pubkey,
+    /// Charged to takers on the atoms they receive, over FEE_DENOMINATOR.
+    taker_fee_mantissa: u32,
+    /// Paid to makers out of the taker fee, over FEE_DENOMINATOR.
+    maker_rebate_mantissa: u32,
+    /// Fees accrued and not yet collected.
+    collected_base_atoms: BaseAtoms,
+    collected_quote_atoms: QuoteAtoms,
+    _padding: [u8; 8],
+}
+
+// 32 + // fee_recipient
+//  4 + // taker_fee_mantissa
+//  4 + // maker_rebate_mantissa
+//  8 + // collected_base_atoms
+//  8 + // collected_quote_atoms
+//  8   // padding
+// = 64
+const_assert_eq!(size_of::<MarketFeeConfig>(), MARKET_EXTENSION_SIZE);
+const_assert_eq!(size_of::<MarketFeeConfig>() % 8, 0);
+impl Get for MarketFeeConfig {}
+
+impl MarketFeeConfig {
+    pub fn new(fee_recipient: Pubkey, taker_fee_mantissa: u32, maker_rebate_mantissa: u32) -> Self {
+        MarketFeeConfig {
+            fee_recipient,
+            taker_fee_mantissa,
+            maker_rebate_mantissa,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_fee_recipient(&self) -> &Pubkey {
+        &self.fee_recipient
+    }
+    pub fn get_taker_fee_mantissa(&self) -> u32 {
+        self.taker_fee_mantissa
+    }
+    pub fn get_maker_rebate_mantissa(&self) -> u32 {
+        self.maker_rebate_mantissa
+    }
+    pub fn get_collected_base_atoms(&self) -> BaseAtoms {
+        self.collected_base_atoms
+    }
+    pub fn get_collected_quote_atoms(&self) -> QuoteAtoms {
+        self.collected_quote_atoms
+    }
+
+    /// Taker fee and maker rebate for a fill where the taker receives
+    /// received_atoms. Fee rounds up and rebate rounds down, so the rebate
+    /// never exceeds the fee as long as the rebate rate does not.
+    pub fn get_fee_and_rebate(&self, received_atoms: u64) -> (u64, u64) {
+        let fee_atoms: u64 = (received_atoms as u128 * self.taker_fee_mantissa as u128)
+            .div_ceil(FEE_DENOMINATOR as u128) as u64;
+        let rebate_atoms: u64 = (received_atoms as u128 * self.maker_rebate_mantissa as u128
+            / FEE_DENOMINATOR as u128) as u64;
+        (fee_atoms, rebate_atoms)
+    }
+
+    pub fn accrue(&mut self, is_base: bool, atoms: u64) {
+        if is_base {
+            self.collected_base_atoms = self
+                .collected_base_atoms
+                .saturating_add(BaseAtoms::new(atoms));
+        } else {
+            self.collected_quote_atoms = self
+                .collected_quote_atoms
+                .saturating_add(QuoteAtoms::new(atoms));
+        }
+    }
+
+    /// Zeroes the collected fees for one side and returns how many there were.
+    pub fn take_collected(&mut self, is_base: bool) -> u64 {
+        if is_base {
+            let atoms: u64 = self.collected_base_atoms.as_u64();
+            self.collected_base_atoms = BaseAtoms::ZERO;
+            atoms
+        } else {
+            let atoms: u64 = self.collected_quote_atoms.as_u64();
+            self.collected_quote_atoms = QuoteAtoms::ZERO;
+            atoms
+        }
+    }
+}
+
+/// Restrictions on orders that can rest on the book. Zero means no
+/// restriction. Orders that cannot rest, like swaps, are not checked and
+/// neither are reverse orders placed by the program after a fill.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct MarketTradingConfig {
+    tick_size: QuoteAtomsPerBaseAtom,
+    lot_size: BaseAtoms,
+    min_base_atoms: BaseAtoms,
+    _padding: [u8; 32],
+}
+
+// 16 + // tick_size
+//  8 + // lot_size
+//  8 + // min_base_atoms
+// 32   // padding
+// = 64
+const_assert_eq!(size_of::<MarketTradingConfig>(), MARKET_EXTENSION_SIZE);
+const_assert_eq!(size_of::<MarketTradingConfig>() % 8, 0);
+impl Get for MarketTradingConfig {}
+
+impl MarketTradingConfig {
+    pub fn new(
+        tick_size: QuoteAtomsPerBaseAtom,
+        lot_size: BaseAtoms,
+        min_base_atoms: BaseAtoms,
+    ) -> Self {
+        MarketTradingConfig {
+            tick_size,
+            lot_size,
+            min_base_atoms,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_tick_size(&self) -> QuoteAtomsPerBaseAtom {
+        self.tick_size
+    }
+    pub fn get_lot_size(&self) -> BaseAtoms {
+        self.lot_size
+    }
+    pub fn get_min_base_atoms(&self) -> BaseAtoms {
+        self.min_base_atoms
+    }
+
+    pub fn verify_order(
+        &self,
+        price: QuoteAtomsPerBaseAtom,
+        num_base_atoms: BaseAtoms,
+    ) -> ProgramResult {
+        require!(
+            price.checked_round_to_tick(self.tick_size, false)? == price,
+            ManifestError::PriceNotOnTick,
+            "Price {} is not a multiple of tick size {}",
+            price,
+            self.tick_size,
+        )?;
+        require!(
+            self.lot_size == BaseAtoms::ZERO
+                || num_base_atoms.as_u64() % self.lot_size.as_u64() == 0,
+            ManifestError::SizeNotOnLot,
+            "Base atoms {} is not a multiple of lot size {}",
+            num_base_atoms,
+            self.lot_size,
+        )?;
+        require!(
+            num_base_atoms >= self.min_base_atoms,
+            ManifestError::OrderTooSmall,
+            "Base atoms {} is below minimum {}",
+            num_base_atoms,
+            self.min_base_atoms,
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(
+    Debug,
+    BorshDeserialize,
+    BorshSerialize,
+    PartialEq,
+    Clone,
+    Copy,
+    ShankType,
+    IntoPrimitive,
+    TryFromPrimitive,
+)]
+#[repr(u8)]
+pub enum MarketStatus {
+    // Normal trading.
+    Active = 0,
+    // No placing, cancelling, swapping or depositing. Withdraw only.
+    Paused = 1,
+    // Cancels and withdraws only, so traders can wind down.
+    CancelOnly = 2,
+}
+
+/// Optional admin set at market creation for incident response. Renounced by
+/// transferring to the default pubkey, after which the status can no longer
+/// change.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct MarketAuthorityConfig {
+    authority: Pubkey,
+    status: u8,
+    _padding: [u8; 31],
+}
+
+// 32 + // authority
+//  1 + // status
+// 31   // padding
+// = 64
+const_assert_eq!(size_of::<MarketAuthorityConfig>(), MARKET_EXTENSION_SIZE);
+const_assert_eq!(size_of::<MarketAuthorityConfig>() % 8, 0);
+impl Get for MarketAuthorityConfig {}
+
+impl MarketAuthorityConfig {
+    pub fn new(authority: Pubkey) -> Self {
+        MarketAuthorityConfig {
+            authority,
+            status: MarketStatus::Active.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn get_authority(&self) -> &Pubkey {
+        &self.authority
+    }
+    pub fn get_status(&self) -> MarketStatus {
+        // Only ever written from a valid MarketStatus.
+        MarketStatus::try_from(self.status).unwrap()
+    }
+
+    pub fn set_authority(&mut self, authority: Pubkey) {
+        self.authority = authority;
+    }
+    pub fn set_status(&mut self, status: MarketStatus) {
+        self.status = status.into();
+    }
+}
+
+/// Key allowed to place and cancel orders for a seat. It can never withdraw.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct MarketSeatDelegate {
+    delegate: Pubkey,
+    _padding: [u8; 32],
+}
+
+// 32 + // delegate
+// 32   // padding
+// = 64
+const_assert_eq!(size_of::<MarketSeatDelegate>(), MARKET_EXTENSION_SIZE);
+const_assert_eq!(size_of::<MarketSeatDelegate>() % 8, 0);
+impl Get for MarketSeatDelegate {}
+
+impl MarketSeatDelegate {
+    pub fn new(delegate: Pubkey) -> Self {
+        MarketSeatDelegate {
+            delegate,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_delegate(&self) -> &Pubkey {
+        &self.delegate
+    }
+}
+
+/// Event queue that fills and cancels on the market are written to. Once set,
+/// it has to be included on everything that can write an event.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct MarketEventQueueConfig {
+    event_queue: Pubkey,
+    _padding: [u8; 32],
+}
+
+// 32 + // event_queue
+// 32   // padding
+// = 64
+const_assert_eq!(size_of::<MarketEventQueueConfig>(), MARKET_EXTENSION_SIZE);
+const_assert_eq!(size_of::<MarketEventQueueConfig>() % 8, 0);
+impl Get for MarketEventQueueConfig {}
+
+impl MarketEventQueueConfig {
+    pub fn new(event_queue: Pubkey) -> Self {
+        MarketEventQueueConfig {
+            event_queue,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_event_queue(&self) -> &Pubkey {
+        &self.event_queue
+    }
+}
+
+/// Key that admits traders to a permissioned market. Seats can only be claimed
+/// and swaps can only take a temporary seat with its signature or a
+/// membership it issued.
+#[repr(C)]
+#[derive(Default, Debug, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct MarketGateConfig {
+    gate_authority: Pubkey,
+    _padding: [u8; 32],
+}
+
+// 32 + // gate_authority
+// 32   // padding
+// = 64
+const_assert_eq!(size_of::<MarketGateConfig>(), MARKET_EXTENSION_SIZE);
+const_assert_eq!(size_of::<MarketGateConfig>() % 8, 0);
+impl Get for MarketGateConfig {}
+
+impl MarketGateConfig {
+    pub fn new(gate_authority: Pubkey) -> Self {
+        MarketGateConfig {
+            gate_authority,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_gate_authority(&self) -> &Pubkey {
+        &self.gate_authority
+    }
+}
+
+/// Read a market extension in an array of data at the index of its block.
+pub fn get_helper_extension<T: Get>(data: &[u8], index: DataIndex) -> &T {
+    get_helper::<T>(data, index + RBTREE_OVERHEAD_BYTES as DataIndex)
+}
+
+/// Read a mutable market extension in an array of data at the index of its block.
+pub fn get_mut_helper_extension<T: Get>(data: &mut [u8], index: DataIndex) -> &mut T {
+    get_mut_helper::<T>(data, index + RBTREE_OVERHEAD_BYTES as DataIndex)
+}
+
+/// Write a new extension into the block at index.
+pub fn init_extension<T: Get>(data: &mut [u8], index: DataIndex, value: T) {
+    *get_mut_helper::<MarketExtensionHeader>(data, index) = MarketExtensionHeader::new();
+    *get_mut_helper_extension::<T>(data, index) = value;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fee_and_rebate() {
+        // 10 bps taker, 2 bps rebate
+        let fee_config: MarketFeeConfig =
+            MarketFeeConfig::new(Pubkey::default(), 1_000_000, 200_000);
+        assert_eq!(fee_config.get_fee_and_rebate(1_000_000), (1_000, 200));
+        // Fee rounds up, rebate rounds down.
+        assert_eq!(fee_config.get_fee_and_rebate(1), (1, 0));
+        assert_eq!(fee_config.get_fee_and_rebate(0), (0, 0));
+    }
+
+    #[test]
+    fn test_take_collected() {
+        let mut fee_config: MarketFeeConfig = MarketFeeConfig::new(Pubkey::default(), 0, 0);
+        fee_config.accrue(true, 5);
+        fee_config.accrue(false, 7);
+        assert_eq!(fee_config.take_collected(true), 5);
+        assert_eq!(fee_config.take_collected(true), 0);
+        assert_eq!(fee_config.get_collected_quote_atoms(), QuoteAtoms::new(7));
+    }
+
+    #[test]
+    fn test_verify_order() {
+        let trading_config: MarketTradingConfig = MarketTradingConfig::new(
+            QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(5, -2).unwrap(),
+            BaseAtoms::new(100),
+            BaseAtoms::new(1_000),
+        );
+        let on_tick: QuoteAtomsPerBaseAtom =
+            QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(15, -2).unwrap();
+        let off_tick: QuoteAtomsPerBaseAtom =
+            QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(16, -2).unwrap();
+        assert!(trading_config
+            .verify_order(on_tick, BaseAtoms::new(1_100))
+            .is_ok());
+        assert!(trading_config
+            .verify_order(off_tick, BaseAtoms::new(1_100))
+            .is_err());
+        assert!(trading_config
+            .verify_order(on_tick, BaseAtoms::new(1_150))
+            .is_err());
+        assert!(trading_config
+            .verify_order(on_tick, BaseAtoms::new(900))
+            .is_err());
+    }
+}
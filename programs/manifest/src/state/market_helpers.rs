@@ -21,6 +21,13 @@ mod free_addr_helpers {
         get_free_address_on_market_fixed(fixed, dynamic)
     }
 
+    pub fn get_free_address_on_market_fixed_for_extension(
+        fixed: &mut MarketFixed,
+        dynamic: &mut [u8],
+    ) -> DataIndex {
+        get_free_address_on_market_fixed(fixed, dynamic)
+    }
+
     pub fn get_free_address_on_market_fixed_for_bid_order(
         fixed: &mut MarketFixed,
         dynamic: &mut [u8],
@@ -54,6 +61,14 @@ mod free_addr_helpers {
         release_address_on_market_fixed(fixed, dynamic, index);
     }
 
+    pub fn release_address_on_market_fixed_for_extension(
+        fixed: &mut MarketFixed,
+        dynamic: &mut [u8],
+        index: DataIndex,
+    ) {
+        release_address_on_market_fixed(fixed, dynamic, index);
+    }
+
     pub fn release_address_on_market_fixed_for_bid_order(
         fixed: &mut MarketFixed,
         dynamic: &mut [u8],
@@ -93,6 +108,15 @@ mod free_addr_helpers {
         }
     }
 
+    pub fn get_free_address_on_market_fixed_for_extension(
+        _fixed: &mut MarketFixed,
+        _dynamic: &mut [u8],
+    ) -> DataIndex {
+        // Extensions are not modeled for formal verification.
+        cvt::cvt_assert!(false);
+        super::NIL
+    }
+
     pub fn get_free_address_on_market_fixed_for_bid_order(
         _fixed: &mut MarketFixed,
         _dynamic: &mut [u8],
@@ -124,6 +148,13 @@ mod free_addr_helpers {
     ) {
     }
 
+    pub fn release_address_on_market_fixed_for_extension(
+        _fixed: &mut MarketFixed,
+        _dynamic: &mut [u8],
+        _index: DataIndex,
+    ) {
+    }
+
     pub fn release_address_on_market_fixed_for_bid_order(
         _fixed: &mut MarketFixed,
         _dynamic: &mut [u8],
@@ -209,6 +240,11 @@ impl<'a, 'b, 'info> AddSingleOrderCtx<'a, 'b, 'info> {
             order_type,
             global_trade_accounts_opts,
             current_slot: _,
+            self_trade_behavior: _,
+            client_order_id,
+            reverse_price_bounds: _,
+            iceberg_display_base_atoms: _,
+            event_queue_opt,
         } = self.args;
 
         let next_order_index: DataIndex =
@@ -223,6 +259,8 @@ impl<'a, 'b, 'info> AddSingleOrderCtx<'a, 'b, 'info> {
                 dynamic,
                 current_order_index,
                 global_trade_accounts_opts,
+                event_queue_opt,
+                now_slot,
             )?;
             return Ok(AddOrderToMarketInnerResult {
                 next_order_index,
@@ -252,6 +290,7 @@ impl<'a, 'b, 'info> AddSingleOrderCtx<'a, 'b, 'info> {
         assert_can_take(order_type)?;
 
         let maker_sequence_number = other_order.get_sequence_number();
+        let maker_client_order_id: u64 = other_order.get_client_order_id();
         let other_trader_index: DataIndex = other_order.get_trader_index();
         let did_fully_match_resting_order: bool =
             remaining_base_atoms >= other_order.get_num_base_atoms();
@@ -296,6 +335,8 @@ impl<'a, 'b, 'info> AddSingleOrderCtx<'a, 'b, 'info> {
                     dynamic,
                     current_order_index,
                     global_trade_accounts_opts,
+                    event_queue_opt,
+                    now_slot,
                 )?;
                 return Ok(AddOrderToMarketInnerResult {
                     next_order_index,
@@ -419,8 +460,33 @@ impl<'a, 'b, 'info> AddSingleOrderCtx<'a, 'b, 'info> {
             quote_mint: *fixed.get_quote_mint(),
             // TODO: Fix this
             is_maker_global: PodBool::from(false),
-            _padding: [0; 14],
+            _padding: [0; 6],
+            maker_client_order_id,
+            taker_client_order_id: client_order_id,
+            // Fees are not modeled for formal verification.
+            taker_fee_atoms: 0,
+            maker_rebate_atoms: 0,
         })?;
+        record_event(
+            fixed,
+            dynamic,
+            event_queue_opt,
+            now_slot,
+            MarketEvent::new_fill(
+                maker,
+                taker,
+                matched_price,
+                base_atoms_traded,
+                quote_atoms_traded,
+                maker_sequence_number,
+                fixed.order_sequence_number,
+                maker_client_order_id,
+                client_order_id,
+                0,
+                0,
+                !is_bid,
+            ),
+        )?;
 
         if did_fully_match_resting_order {
             // Get paid for removing a global order.
@@ -478,6 +544,11 @@ pub fn place_order_helper<
         order_type,
         global_trade_accounts_opts: _,
         current_slot,
+        self_trade_behavior: _,
+        client_order_id: _,
+        reverse_price_bounds: _,
+        iceberg_display_base_atoms: _,
+        event_queue_opt: _,
     } = args;
     assert_already_has_seat(trader_index)?;
     let now_slot: u32 = current_slot.unwrap_or_else(|| get_now_slot());
@@ -540,6 +611,7 @@ pub fn place_order_helper<
             order_index: NIL,
             base_atoms_traded: total_base_atoms_traded,
             quote_atoms_traded: total_quote_atoms_traded,
+            taker_fee_atoms: 0,
         });
     }
 
@@ -549,5 +621,6 @@ pub fn place_order_helper<
         order_sequence_number,
         total_base_atoms_traded,
         total_quote_atoms_traded,
+        0,
     )
 }
@@ -0,0 +1,82 @@
+/// Membership on a permissioned market, issued by the market gate authority.
+/// Lives at a PDA of the market and trader so that it can be checked without
+/// trusting the caller. Revoked memberships are kept so that re-issuing does
+/// not need a new account.
+use std::mem::size_of;
+
+use bytemuck::{Pod, Zeroable};
+use hypertree::{Get, PodBool};
+use shank::ShankType;
+use solana_program::{entrypoint::ProgramResult, program_error::ProgramError, pubkey::Pubkey};
+use static_assertions::const_assert_eq;
+
+use crate::{require, validation::ManifestAccount};
+
+use super::{MARKET_MEMBERSHIP_DISCRIMINANT, MARKET_MEMBERSHIP_SIZE};
+
+#[repr(C)]
+#[derive(Default, Copy, Clone, Zeroable, Pod, ShankType)]
+pub struct MarketMembership {
+    /// Discriminant for identifying this type of account.
+    pub discriminant: u64,
+
+    market: Pubkey,
+    trader: Pubkey,
+
+    /// Revoked members can only cancel and withdraw on seats they already
+    /// have and cannot claim new seats or swap.
+    is_revoked: PodBool,
+
+    _padding: [u8; 7],
+}
+const_assert_eq!(
+    size_of::<MarketMembership>(),
+    8 +   // discriminant
+    32 +  // market
+    32 +  // trader
+    1 +   // is_revoked
+    7 // padding
+);
+const_assert_eq!(size_of::<MarketMembership>(), MARKET_MEMBERSHIP_SIZE);
+const_assert_eq!(size_of::<MarketMembership>() % 8, 0);
+impl Get for MarketMembership {}
+
+impl MarketMembership {
+    pub fn new(market: &Pubkey, trader: &Pubkey) -> Self {
+        MarketMembership {
+            discriminant: MARKET_MEMBERSHIP_DISCRIMINANT,
+            market: *market,
+            trader: *trader,
+            is_revoked: PodBool::from(false),
+            _padding: [0; 7],
+        }
+    }
+
+    pub fn get_market(&self) -> &Pubkey {
+        &self.market
+    }
+    pub fn get_trader(&self) -> &Pubkey {
+        &self.trader
+    }
+    pub fn get_is_revoked(&self) -> bool {
+        self.is_revoked.0 == 1
+    }
+
+    pub fn set_is_revoked(&mut self, is_revoked: bool) {
+        self.is_revoked = PodBool::from(is_revoked);
+    }
+}
+
+impl ManifestAccount for MarketMembership {
+    fn verify_discriminant(&self) -> ProgramResult {
+        // Check the discriminant to make sure it is a membership account.
+        require!(
+            self.discriminant == MARKET_MEMBERSHIP_DISCRIMINANT,
+            ProgramError::InvalidAccountData,
+            "Invalid membership discriminant actual: {} expected: {}",
+            self.discriminant,
+            MARKET_MEMBERSHIP_DISCRIMINANT
+        )?;
+        Ok(())
+    }
+}
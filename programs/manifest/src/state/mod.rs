@@ -1,13 +1,19 @@
 pub mod claimed_seat;
 pub mod constants;
 pub mod dynamic_account;
+pub mod event_queue;
 pub mod global;
 pub mod market;
+pub mod market_extension;
+pub mod market_membership;
 pub mod resting_order;
 pub mod utils;
 
 pub use constants::*;
 pub use dynamic_account::*;
+pub use event_queue::*;
 pub use global::*;
 pub use market::*;
+pub use market_extension::*;
+pub use market_membership::*;
 pub use resting_order::*;
@@ -1,10 +1,13 @@
-use std::mem::size_of;
+use std::mem::{offset_of, size_of};
 
-use crate::quantities::{
-    u64_slice_to_u128, BaseAtoms, PriceConversionError, QuoteAtomsPerBaseAtom,
-};
 #[cfg(feature = "certora")]
-use crate::quantities::{QuoteAtoms, WrapperU64};
+use crate::quantities::QuoteAtoms;
+use crate::{
+    program::ManifestError,
+    quantities::{
+        u64_slice_to_u128, BaseAtoms, PriceConversionError, QuoteAtomsPerBaseAtom, WrapperU64,
+    },
+};
 use borsh::{BorshDeserialize, BorshSerialize};
 use bytemuck::{Pod, Zeroable};
 use hypertree::{DataIndex, PodBool};
@@ -49,6 +52,18 @@ pub enum OrderType {
     // Same as a reverse order except that it much tighter, allowing for stables
     // to have even smaller spreads.
     ReverseTight = 5,
+
+    // Does not rest. Take only and fails unless the full size is matched.
+    FillOrKill = 6,
+
+    // Like post only, but instead of failing when it would cross, the price is
+    // moved one tick behind the best opposite order and it rests there.
+    PostOnlySlide = 7,
+
+    // Only a display slice of the order is on the book. When the slice is
+    // filled, it is replenished from the hidden reserve with a new sequence
+    // number, so it goes behind other orders at the same price.
+    Iceberg = 8,
 }
 unsafe impl bytemuck::Zeroable for OrderType {}
 unsafe impl bytemuck::Pod for OrderType {}
@@ -72,11 +87,103 @@ impl OrderType {
 }
 
 pub fn order_type_can_rest(order_type: OrderType) -> bool {
-    order_type != OrderType::ImmediateOrCancel
+    order_type != OrderType::ImmediateOrCancel && order_type != OrderType::FillOrKill
 }
 
 pub fn order_type_can_take(order_type: OrderType) -> bool {
-    order_type != OrderType::PostOnly && order_type != OrderType::Global
+    order_type != OrderType::PostOnly
+        && order_type != OrderType::Global
+        && order_type != OrderType::PostOnlySlide
+}
+
+/// What to do when an incoming order would match a resting order from the same
+/// trader. Prevented self trades do not move funds, record volume or emit a
+/// FillLog.
+#[derive(
+    Debug,
+    BorshDeserialize,
+    BorshSerialize,
+    PartialEq,
+    Clone,
+    Copy,
+    Default,
+    ShankType,
+    IntoPrimitive,
+    TryFromPrimitive,
+)]
+#[repr(u8)]
+pub enum SelfTradeBehavior {
+    // Match against own orders like any other order. This was the only
+    // behavior before self trade prevention and remains the default.
+    #[default]
+    Allow = 0,
+
+    // Cancel the resting maker order and keep matching.
+    CancelMaker = 1,
+
+    // Stop matching and drop whatever is left of the taker. Nothing rests.
+    CancelTaker = 2,
+
+    // Reduce both the maker and the taker by the overlapping size without
+    // trading. The maker is removed if fully decremented. A global maker only
+    // shrinks since its funds stay on the global account.
+    DecrementBoth = 3,
+
+    // Fail the order.
+    Abort = 4,
+}
+
+/// Price range that a bounded reverse order may flip within. Once a flip would
+/// land outside the range, the order stops reversing and the proceeds stay in
+/// the seat balance. A zero mantissa leaves that side unbounded.
+#[derive(Debug, Default, BorshDeserialize, BorshSerialize, PartialEq, Clone, Copy)]
+pub struct ReversePriceBounds {
+    pub lower_mantissa: u32,
+    pub lower_exponent: i8,
+    pub upper_mantissa: u32,
+    pub upper_exponent: i8,
+}
+
+impl ReversePriceBounds {
+    pub fn new(
+        lower_mantissa: u32,
+        lower_exponent: i8,
+        upper_mantissa: u32,
+        upper_exponent: i8,
+    ) -> Self {
+        ReversePriceBounds {
+            lower_mantissa,
+            lower_exponent,
+            upper_mantissa,
+            upper_exponent,
+        }
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        self.lower_mantissa != 0 || self.upper_mantissa != 0
+    }
+
+    pub fn contains(&self, price: QuoteAtomsPerBaseAtom) -> Result<bool, PriceConversionError> {
+        if self.lower_mantissa != 0
+            && price
+                < QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(
+                    self.lower_mantissa,
+                    self.lower_exponent,
+                )?
+        {
+            return Ok(false);
+        }
+        if self.upper_mantissa != 0
+            && price
+                > QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(
+                    self.upper_mantissa,
+                    self.upper_exponent,
+                )?
+        {
+            return Ok(false);
+        }
+        Ok(true)
+    }
 }
 
 #[repr(C)]
@@ -91,9 +198,41 @@ pub struct RestingOrder {
     order_type: OrderType,
     // Spread for reverse orders. Defaults to zero.
     reverse_spread: u16,
-    _padding: [u8; 20],
+    // Price bounds for reverse orders, split around client_order_id to fit
+    // in what used to be padding. Zero mantissas mean unbounded.
+    reverse_lower_mantissa: u32,
+    // Trader supplied identifier. Not checked for uniqueness.
+    client_order_id: u64,
+    reverse_upper_mantissa: u32,
+    reverse_lower_exponent: i8,
+    reverse_upper_exponent: i8,
+    // Top bits of the iceberg reserve. Zero for other order types.
+    iceberg_reserve_high: u16,
 }
 
+// Iceberg orders are never reversible, so their sizes are split across the
+// reverse fields. The display size is reverse_spread then
+// reverse_lower_mantissa, and the hidden reserve is reverse_upper_mantissa,
+// both exponents and then iceberg_reserve_high, lowest bits first. Keeping the
+// fields adjacent stores both as little endian integers in the account.
+const_assert_eq!(
+    offset_of!(RestingOrder, reverse_lower_mantissa),
+    offset_of!(RestingOrder, reverse_spread) + size_of::<u16>()
+);
+const_assert_eq!(
+    offset_of!(RestingOrder, reverse_lower_exponent),
+    offset_of!(RestingOrder, reverse_upper_mantissa) + size_of::<u32>()
+);
+const_assert_eq!(
+    offset_of!(RestingOrder, reverse_upper_exponent),
+    offset_of!(RestingOrder, reverse_lower_exponent) + size_of::<i8>()
+);
+const_assert_eq!(
+    offset_of!(RestingOrder, iceberg_reserve_high),
+    offset_of!(RestingOrder, reverse_upper_exponent) + size_of::<i8>()
+);
+pub const MAX_ICEBERG_DISPLAY_BASE_ATOMS: u64 = (1 << 48) - 1;
+
 // 16 +  // price
 //  8 +  // num_base_atoms
 //  8 +  // sequence_number
@@ -102,7 +241,12 @@ pub struct RestingOrder {
 //  1 +  // is_bid
 //  1 +  // order_type
 //  2 +  // spread
-// 20    // padding 2
+//  4 +  // reverse_lower_mantissa
+//  8 +  // client_order_id
+//  4 +  // reverse_upper_mantissa
+//  1 +  // reverse_lower_exponent
+//  1 +  // reverse_upper_exponent
+//  2    // iceberg_reserve_high
 // = 64
 const_assert_eq!(size_of::<RestingOrder>(), RESTING_ORDER_SIZE);
 const_assert_eq!(size_of::<RestingOrder>() % 8, 0);
@@ -130,7 +274,12 @@ impl RestingOrder {
             is_bid: PodBool::from_bool(is_bid),
             order_type,
             reverse_spread: 0,
-            _padding: Default::default(),
+            reverse_lower_mantissa: 0,
+            client_order_id: 0,
+            reverse_upper_mantissa: 0,
+            reverse_lower_exponent: 0,
+            reverse_upper_exponent: 0,
+            iceberg_reserve_high: 0,
         })
     }
 
@@ -201,6 +350,96 @@ impl RestingOrder {
         self.reverse_spread = spread;
     }
 
+    pub fn get_reverse_price_bounds(&self) -> ReversePriceBounds {
+        ReversePriceBounds::new(
+            self.reverse_lower_mantissa,
+            self.reverse_lower_exponent,
+            self.reverse_upper_mantissa,
+            self.reverse_upper_exponent,
+        )
+    }
+
+    pub fn set_reverse_price_bounds(&mut self, bounds: ReversePriceBounds) {
+        self.reverse_lower_mantissa = bounds.lower_mantissa;
+        self.reverse_lower_exponent = bounds.lower_exponent;
+        self.reverse_upper_mantissa = bounds.upper_mantissa;
+        self.reverse_upper_exponent = bounds.upper_exponent;
+    }
+
+    pub fn is_iceberg(&self) -> bool {
+        self.order_type == OrderType::Iceberg
+    }
+
+    /// Size of each refill. Zero for other order types.
+    pub fn get_iceberg_display_base_atoms(&self) -> BaseAtoms {
+        if !self.is_iceberg() {
+            return BaseAtoms::ZERO;
+        }
+        BaseAtoms::new(self.reverse_spread as u64 | ((self.reverse_lower_mantissa as u64) << 16))
+    }
+
+    /// Size not yet on the book. Zero for other order types.
+    pub fn get_iceberg_reserve_base_atoms(&self) -> BaseAtoms {
+        if !self.is_iceberg() {
+            return BaseAtoms::ZERO;
+        }
+        BaseAtoms::new(
+            self.reverse_upper_mantissa as u64
+                | ((self.reverse_lower_exponent as u8 as u64) << 32)
+                | ((self.reverse_upper_exponent as u8 as u64) << 40)
+                | ((self.iceberg_reserve_high as u64) << 48),
+        )
+    }
+
+    /// Splits the order into the display slice and the hidden reserve.
+    pub fn set_iceberg(
+        &mut self,
+        display_base_atoms: BaseAtoms,
+        reserve_base_atoms: BaseAtoms,
+    ) -> ProgramResult {
+        if display_base_atoms.as_u64() > MAX_ICEBERG_DISPLAY_BASE_ATOMS {
+            return Err(ManifestError::InvalidPlaceOrderFromWalletParams.into());
+        }
+        let display_base_atoms: u64 = display_base_atoms.as_u64();
+        self.reverse_spread = display_base_atoms as u16;
+        self.reverse_lower_mantissa = (display_base_atoms >> 16) as u32;
+        let reserve_base_atoms: u64 = reserve_base_atoms.as_u64();
+        self.reverse_upper_mantissa = reserve_base_atoms as u32;
+        self.reverse_lower_exponent = (reserve_base_atoms >> 32) as u8 as i8;
+        self.reverse_upper_exponent = (reserve_base_atoms >> 40) as u8 as i8;
+        self.iceberg_reserve_high = (reserve_base_atoms >> 48) as u16;
+        Ok(())
+    }
+
+    /// Moves the next display slice from the reserve onto the book under a
+    /// new sequence number.
+    pub fn refill_iceberg(&mut self, sequence_number: u64) -> ProgramResult {
+        let display_base_atoms: BaseAtoms = self.get_iceberg_display_base_atoms();
+        let reserve_base_atoms: BaseAtoms = self.get_iceberg_reserve_base_atoms();
+        let refill_base_atoms: BaseAtoms = display_base_atoms.min(reserve_base_atoms);
+        self.num_base_atoms = self.num_base_atoms.checked_add(refill_base_atoms)?;
+        self.sequence_number = sequence_number;
+        self.set_iceberg(
+            display_base_atoms,
+            reserve_base_atoms.checked_sub(refill_base_atoms)?,
+        )
+    }
+
+    /// Displayed size plus any hidden reserve. This is what backs the order.
+    pub fn get_total_base_atoms(&self) -> Result<BaseAtoms, ProgramError> {
+        Ok(self
+            .num_base_atoms
+            .checked_add(self.get_iceberg_reserve_base_atoms())?)
+    }
+
+    pub fn get_client_order_id(&self) -> u64 {
+        self.client_order_id
+    }
+
+    pub fn set_client_order_id(&mut self, client_order_id: u64) {
+        self.client_order_id = client_order_id;
+    }
+
     pub fn get_sequence_number(&self) -> u64 {
         self.sequence_number
     }
@@ -220,10 +459,10 @@ impl RestingOrder {
         if self.is_global() {
             return Ok((BaseAtoms::new(0), QuoteAtoms::new(0)));
         } else if self.get_is_bid() {
-            let quote_amount = self.num_base_atoms.checked_mul(self.price, true)?;
+            let quote_amount = self.get_total_base_atoms()?.checked_mul(self.price, true)?;
             return Ok((BaseAtoms::new(0), quote_amount));
         } else {
-            return Ok((self.num_base_atoms, QuoteAtoms::new(0)));
+            return Ok((self.get_total_base_atoms()?, QuoteAtoms::new(0)));
         }
     }
 
@@ -287,11 +526,11 @@ impl std::fmt::Display for RestingOrder {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::quantities::WrapperU64;
 
     #[test]
     fn test_default() {
         assert_eq!(OrderType::default(), OrderType::Limit);
+        assert_eq!(SelfTradeBehavior::default(), SelfTradeBehavior::Allow);
     }
 
     #[test]
@@ -373,4 +612,113 @@ mod test {
         resting_order.set_last_valid_slot(1);
         resting_order.set_sequence_number(1);
     }
+
+    #[test]
+    fn test_reverse_price_bounds() {
+        let unbounded: ReversePriceBounds = ReversePriceBounds::default();
+        assert!(!unbounded.is_bounded());
+        assert!(unbounded
+            .contains(QuoteAtomsPerBaseAtom::try_from(1_000.0).unwrap())
+            .unwrap());
+
+        // [0.9, 1.1]
+        let bounds: ReversePriceBounds = ReversePriceBounds::new(9, -1, 11, -1);
+        assert!(bounds.is_bounded());
+        assert!(bounds
+            .contains(QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(1, 0).unwrap())
+            .unwrap());
+        assert!(bounds
+            .contains(QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(9, -1).unwrap())
+            .unwrap());
+        assert!(!bounds
+            .contains(QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(89, -2).unwrap())
+            .unwrap());
+        assert!(!bounds
+            .contains(QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(111, -2).unwrap())
+            .unwrap());
+
+        let mut resting_order: RestingOrder = RestingOrder::new(
+            0,
+            BaseAtoms::ZERO,
+            QuoteAtomsPerBaseAtom::ZERO,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            true,
+            OrderType::Reverse,
+        )
+        .unwrap();
+        resting_order.set_reverse_price_bounds(bounds);
+        assert_eq!(resting_order.get_reverse_price_bounds(), bounds);
+    }
+
+    #[test]
+    fn test_iceberg() {
+        let mut resting_order: RestingOrder = RestingOrder::new(
+            0,
+            BaseAtoms::new(10),
+            QuoteAtomsPerBaseAtom::ZERO,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            true,
+            OrderType::Iceberg,
+        )
+        .unwrap();
+        resting_order.set_client_order_id(u64::MAX);
+        resting_order
+            .set_iceberg(BaseAtoms::new(10), BaseAtoms::new(15))
+            .unwrap();
+        assert_eq!(resting_order.get_total_base_atoms().unwrap().as_u64(), 25);
+        assert_eq!(resting_order.get_client_order_id(), u64::MAX);
+
+        resting_order.reduce(BaseAtoms::new(10)).unwrap();
+        resting_order.refill_iceberg(1).unwrap();
+        assert_eq!(resting_order.get_num_base_atoms().as_u64(), 10);
+        assert_eq!(resting_order.get_iceberg_reserve_base_atoms().as_u64(), 5);
+        assert_eq!(resting_order.get_sequence_number(), 1);
+
+        resting_order.reduce(BaseAtoms::new(10)).unwrap();
+        resting_order.refill_iceberg(2).unwrap();
+        assert_eq!(resting_order.get_num_base_atoms().as_u64(), 5);
+        assert_eq!(resting_order.get_iceberg_reserve_base_atoms().as_u64(), 0);
+        assert_eq!(resting_order.get_iceberg_display_base_atoms().as_u64(), 10);
+
+        assert!(resting_order
+            .set_iceberg(
+                BaseAtoms::new(MAX_ICEBERG_DISPLAY_BASE_ATOMS + 1),
+                BaseAtoms::ZERO
+            )
+            .is_err());
+
+        // Large sizes use every bit of the split fields.
+        let display_base_atoms: u64 = MAX_ICEBERG_DISPLAY_BASE_ATOMS - 1;
+        let reserve_base_atoms: u64 = u64::MAX - 1;
+        resting_order
+            .set_iceberg(
+                BaseAtoms::new(display_base_atoms),
+                BaseAtoms::new(reserve_base_atoms),
+            )
+            .unwrap();
+        assert_eq!(
+            resting_order.get_iceberg_display_base_atoms().as_u64(),
+            display_base_atoms
+        );
+        assert_eq!(
+            resting_order.get_iceberg_reserve_base_atoms().as_u64(),
+            reserve_base_atoms
+        );
+        assert_eq!(resting_order.get_client_order_id(), u64::MAX);
+
+        // Stored as little endian integers so existing orders read the same.
+        let bytes: &[u8] = bytemuck::bytes_of(&resting_order);
+        let display_offset: usize = offset_of!(RestingOrder, reverse_spread);
+        assert_eq!(
+            bytes[display_offset..display_offset + 6],
+            display_base_atoms.to_le_bytes()[..6]
+        );
+        let reserve_offset: usize = offset_of!(RestingOrder, reverse_upper_mantissa);
+        assert_eq!(
+            bytes[reserve_offset..reserve_offset + 8],
+            reserve_base_atoms.to_le_bytes()
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use std::{cell::Ref, slice::Iter};
+use std::{cell::Ref, mem::size_of, slice::Iter};
 
 use hypertree::{get_helper, trace};
 use solana_program::{
@@ -9,11 +9,12 @@ use solana_program::{
 };
 
 use crate::{
-    program::ManifestError,
+    program::{get_dynamic_account, ManifestError},
     require,
-    state::{GlobalFixed, MarketFixed},
+    state::{EventQueueFixed, GlobalFixed, MarketFixed, MarketRef, EVENT_QUEUE_FIXED_DISCRIMINANT},
     validation::{
-        get_global_address, EmptyAccount, MintAccountInfo, Program, Signer, TokenAccountInfo,
+        get_global_address, get_native_sol_address, EmptyAccount, MintAccountInfo, Program, Signer,
+        TokenAccountInfo,
     },
 };
 
@@ -86,6 +87,8 @@ pub(crate) struct ClaimSeatContext<'a, 'info> {
     pub payer: Signer<'a, 'info>,
     pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
     pub _system_program: Program<'a, 'info>,
+    // Gate authority signer or membership. Only needed on permissioned markets.
+    pub gate_credential_opt: Option<&'a AccountInfo<'info>>,
 }
 
 impl<'a, 'info> ClaimSeatContext<'a, 'info> {
@@ -98,10 +101,12 @@ impl<'a, 'info> ClaimSeatContext<'a, 'info> {
             ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
         let _system_program: Program =
             Program::new(next_account_info(account_iter)?, &system_program::id())?;
+        let gate_credential_opt: Option<&AccountInfo<'info>> = next_account_info(account_iter).ok();
         Ok(Self {
             payer,
             market,
             _system_program,
+            gate_credential_opt,
         })
     }
 }
@@ -138,6 +143,8 @@ pub(crate) struct DepositContext<'a, 'info> {
     pub vault: TokenAccountInfo<'a, 'info>,
     pub token_program: TokenProgram<'a, 'info>,
     pub mint: MintAccountInfo<'a, 'info>,
+    // Extra account metas for mints with a transfer hook.
+    pub transfer_hook_accounts: &'a [AccountInfo<'info>],
 }
 
 impl<'a, 'info> DepositContext<'a, 'info> {
@@ -179,6 +186,7 @@ impl<'a, 'info> DepositContext<'a, 'info> {
 
         let token_program: TokenProgram = TokenProgram::new(next_account_info(account_iter)?)?;
         let mint: MintAccountInfo = MintAccountInfo::new(next_account_info(account_iter)?)?;
+        let transfer_hook_accounts: &'a [AccountInfo<'info>] = account_iter.as_slice();
 
         // Drop the market ref so it can be passed through the return.
         drop(market_fixed);
@@ -189,6 +197,7 @@ impl<'a, 'info> DepositContext<'a, 'info> {
             vault,
             token_program,
             mint,
+            transfer_hook_accounts,
         })
     }
 }
@@ -203,10 +212,24 @@ pub(crate) struct WithdrawContext<'a, 'info> {
     pub vault: TokenAccountInfo<'a, 'info>,
     pub token_program: TokenProgram<'a, 'info>,
     pub mint: MintAccountInfo<'a, 'info>,
+    // Extra account metas for mints with a transfer hook.
+    pub transfer_hook_accounts: &'a [AccountInfo<'info>],
 }
 
 impl<'a, 'info> WithdrawContext<'a, 'info> {
     pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        Self::load_with_recipient(accounts, false)
+    }
+
+    /// For WithdrawTo, the token account can be owned by anyone.
+    pub fn load_to_recipient(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        Self::load_with_recipient(accounts, true)
+    }
+
+    fn load_with_recipient(
+        accounts: &'a [AccountInfo<'info>],
+        allow_any_recipient: bool,
+    ) -> Result<Self, ProgramError> {
         let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
 
         let payer: Signer = Signer::new(next_account_info(account_iter)?)?;
@@ -228,8 +251,11 @@ impl<'a, 'info> WithdrawContext<'a, 'info> {
                 return Err(ManifestError::InvalidWithdrawAccounts.into());
             };
 
-        let trader_token: TokenAccountInfo =
-            TokenAccountInfo::new_with_owner(token_account_info, mint, payer.key)?;
+        let trader_token: TokenAccountInfo = if allow_any_recipient {
+            TokenAccountInfo::new(token_account_info, mint)?
+        } else {
+            TokenAccountInfo::new_with_owner(token_account_info, mint, payer.key)?
+        };
         let vault: TokenAccountInfo = TokenAccountInfo::new_with_owner_and_key(
             next_account_info(account_iter)?,
             mint,
@@ -239,6 +265,7 @@ impl<'a, 'info> WithdrawContext<'a, 'info> {
 
         let token_program: TokenProgram = TokenProgram::new(next_account_info(account_iter)?)?;
         let mint: MintAccountInfo = MintAccountInfo::new(next_account_info(account_iter)?)?;
+        let transfer_hook_accounts: &'a [AccountInfo<'info>] = account_iter.as_slice();
 
         // Drop the market ref so it can be passed through the return.
         drop(market_fixed);
@@ -249,15 +276,205 @@ impl<'a, 'info> WithdrawContext<'a, 'info> {
             vault,
             token_program,
             mint,
+            transfer_hook_accounts,
+        })
+    }
+}
+
+/// CollectFees account infos
+pub(crate) struct CollectFeesContext<'a, 'info> {
+    // Anyone can crank fees to the fee recipient.
+    pub payer: Signer<'a, 'info>,
+    pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    pub fee_recipient_token: TokenAccountInfo<'a, 'info>,
+    pub vault: TokenAccountInfo<'a, 'info>,
+    pub token_program: TokenProgram<'a, 'info>,
+    pub mint: MintAccountInfo<'a, 'info>,
+}
+
+impl<'a, 'info> CollectFeesContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let payer: Signer = Signer::new(next_account_info(account_iter)?)?;
+        let market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+
+        let market_data: Ref<&mut [u8]> = market.try_borrow_data()?;
+        let dynamic_account: MarketRef = get_dynamic_account(&market_data);
+        let fee_recipient: Pubkey = match dynamic_account.get_fee_config() {
+            Some(fee_config) => *fee_config.get_fee_recipient(),
+            None => {
+                return Err(ManifestError::InvalidMarketParameters.into());
+            }
+        };
+        let market_fixed: &MarketFixed = dynamic_account.fixed;
+        let base_mint: &Pubkey = market_fixed.get_base_mint();
+        let quote_mint: &Pubkey = market_fixed.get_quote_mint();
+
+        let token_account_info: &AccountInfo<'info> = next_account_info(account_iter)?;
+
+        let (mint, expected_vault_address) =
+            if &token_account_info.try_borrow_data()?[0..32] == base_mint.as_ref() {
+                (base_mint, market_fixed.get_base_vault())
+            } else if &token_account_info.try_borrow_data()?[0..32] == quote_mint.as_ref() {
+                (quote_mint, market_fixed.get_quote_vault())
+            } else {
+                return Err(ManifestError::InvalidWithdrawAccounts.into());
+            };
+
+        let fee_recipient_token: TokenAccountInfo =
+            TokenAccountInfo::new_with_owner(token_account_info, mint, &fee_recipient)?;
+        let vault: TokenAccountInfo = TokenAccountInfo::new_with_owner_and_key(
+            next_account_info(account_iter)?,
+            mint,
+            &expected_vault_address,
+            &expected_vault_address,
+        )?;
+
+        let token_program: TokenProgram = TokenProgram::new(next_account_info(account_iter)?)?;
+        let mint: MintAccountInfo = MintAccountInfo::new(next_account_info(account_iter)?)?;
+
+        // Drop the market ref so it can be passed through the return.
+        drop(market_data);
+        Ok(Self {
+            payer,
+            market,
+            fee_recipient_token,
+            vault,
+            token_program,
+            mint,
         })
     }
 }
 
+/// SetMarketStatus and TransferMarketAuthority account infos
+pub(crate) struct MarketAuthorityContext<'a, 'info> {
+    pub authority: Signer<'a, 'info>,
+    pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+}
+
+impl<'a, 'info> MarketAuthorityContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let authority: Signer = Signer::new(next_account_info(account_iter)?)?;
+        let market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        Ok(Self { authority, market })
+    }
+}
+
+/// SetSeatDelegate and ClearSeatDelegate account infos
+pub(crate) struct SeatDelegateContext<'a, 'info> {
+    /// Seat owner. Delegates cannot change the delegate.
+    pub owner: Signer<'a, 'info>,
+    pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    pub _system_program: Program<'a, 'info>,
+}
+
+impl<'a, 'info> SeatDelegateContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let owner: Signer = Signer::new(next_account_info(account_iter)?)?;
+        let market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        let _system_program: Program =
+            Program::new(next_account_info(account_iter)?, &system_program::id())?;
+        Ok(Self {
+            owner,
+            market,
+            _system_program,
+        })
+    }
+}
+
+/// SetMembership account infos
+pub(crate) struct SetMembershipContext<'a, 'info> {
+    /// Gate authority. Pays for the membership account.
+    pub gate_authority: Signer<'a, 'info>,
+    pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    // Not loaded as a ManifestAccountInfo since it does not exist before the
+    // first grant. The address is checked against the PDA instead.
+    pub membership: &'a AccountInfo<'info>,
+    pub system_program: Program<'a, 'info>,
+}
+
+impl<'a, 'info> SetMembershipContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let gate_authority: Signer = Signer::new_payer(next_account_info(account_iter)?)?;
+        let market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        let membership: &AccountInfo<'info> = next_account_info(account_iter)?;
+        let system_program: Program =
+            Program::new(next_account_info(account_iter)?, &system_program::id())?;
+        Ok(Self {
+            gate_authority,
+            market,
+            membership,
+            system_program,
+        })
+    }
+}
+
+/// CreateEventQueue account infos
+pub(crate) struct CreateEventQueueContext<'a, 'info> {
+    /// Market authority. Pays for the market block that holds the config.
+    pub authority: Signer<'a, 'info>,
+    pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    pub event_queue: ManifestAccountInfo<'a, 'info, EventQueueFixed>,
+    pub _system_program: Program<'a, 'info>,
+}
+
+impl<'a, 'info> CreateEventQueueContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let authority: Signer = Signer::new_payer(next_account_info(account_iter)?)?;
+        let market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        let event_queue: ManifestAccountInfo<EventQueueFixed> =
+            ManifestAccountInfo::<EventQueueFixed>::new_init_sized(next_account_info(
+                account_iter,
+            )?)?;
+        let _system_program: Program =
+            Program::new(next_account_info(account_iter)?, &system_program::id())?;
+        Ok(Self {
+            authority,
+            market,
+            event_queue,
+            _system_program,
+        })
+    }
+}
+
+/// ConsumeEvents account infos
+pub(crate) struct ConsumeEventsContext<'a, 'info> {
+    pub event_queue: ManifestAccountInfo<'a, 'info, EventQueueFixed>,
+}
+
+impl<'a, 'info> ConsumeEventsContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let event_queue: ManifestAccountInfo<EventQueueFixed> =
+            ManifestAccountInfo::<EventQueueFixed>::new(next_account_info(account_iter)?)?;
+        Ok(Self { event_queue })
+    }
+}
+
 /// Swap account infos
 pub(crate) struct SwapContext<'a, 'info> {
     pub payer: AccountInfo<'info>,
     pub owner: Signer<'a, 'info>,
     pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    pub system_program: Program<'a, 'info>,
+    // For the native SOL leg of a swap this may be the owner's native sol
+    // address instead, which is not a token account until the swap unwraps
+    // through it.
     pub trader_base: TokenAccountInfo<'a, 'info>,
     pub trader_quote: TokenAccountInfo<'a, 'info>,
     pub base_vault: TokenAccountInfo<'a, 'info>,
@@ -269,11 +486,26 @@ pub(crate) struct SwapContext<'a, 'info> {
 
     // One for each side. First is base, then is quote.
     pub global_trade_accounts_opts: [Option<GlobalTradeAccounts<'a, 'info>>; 2],
+
+    // Extra account metas for mints with a transfer hook. Shared by both
+    // sides, the accounts for each mint are found by key.
+    pub transfer_hook_accounts: &'a [AccountInfo<'info>],
+
+    // Gate authority signer or membership. Only needed when the swap claims a
+    // temporary seat on a permissioned market.
+    pub gate_credential_opt: Option<&'a AccountInfo<'info>>,
+
+    pub event_queue_opt: Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
 }
 
 impl<'a, 'info> SwapContext<'a, 'info> {
     #[cfg_attr(all(feature = "certora", not(feature = "certora-test")), early_panic)]
-    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+    pub fn load(
+        accounts: &'a [AccountInfo<'info>],
+        has_gate_credential: bool,
+    ) -> Result<Self, ProgramError> {
+        let (accounts, event_queue_opt) = split_event_queue(accounts)?;
+        let (accounts, gate_credential_opt) = split_gate_credential(accounts, has_gate_credential)?;
         let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
 
         // Do not check the signer here and let it fail later. This allows the
@@ -299,8 +531,9 @@ impl<'a, 'info> SwapContext<'a, 'info> {
                 )
             };
 
-        // Included in case we need to expand for a reverse order.
-        let _system_program: Program =
+        // Included in case we need to expand for a reverse order or move
+        // native SOL.
+        let system_program: Program =
             Program::new(next_account_info(account_iter)?, &system_program::id())?;
 
         let market_fixed: Ref<MarketFixed> = market.get_fixed()?;
@@ -308,9 +541,12 @@ impl<'a, 'info> SwapContext<'a, 'info> {
         let quote_mint_key: Pubkey = *market_fixed.get_quote_mint();
 
         let trader_base: TokenAccountInfo =
-            TokenAccountInfo::new(next_account_info(account_iter)?, &base_mint_key)?;
-        let trader_quote: TokenAccountInfo =
-            TokenAccountInfo::new(next_account_info(account_iter)?, &quote_mint_key)?;
+            load_trader_token_account(next_account_info(account_iter)?, &base_mint_key, owner.key)?;
+        let trader_quote: TokenAccountInfo = load_trader_token_account(
+            next_account_info(account_iter)?,
+            &quote_mint_key,
+            owner.key,
+        )?;
         let base_vault_address: &Pubkey = market_fixed.get_base_vault();
         let quote_vault_address: &Pubkey = market_fixed.get_quote_vault();
 
@@ -370,13 +606,29 @@ impl<'a, 'info> SwapContext<'a, 'info> {
             current_account_info_or = next_account_info(account_iter);
         }
 
+        // Accounts after the global accounts are for transfer hooks.
+        let mut transfer_hook_accounts: &'a [AccountInfo<'info>] = &[];
+        let has_transfer_hook_mint: bool = base_mint
+            .as_ref()
+            .is_some_and(|mint| mint.has_transfer_hook())
+            || quote_mint
+                .as_ref()
+                .is_some_and(|mint| mint.has_transfer_hook());
         if current_account_info_or.is_ok() {
             let current_account_info: &AccountInfo<'info> = current_account_info_or?;
 
             // It is possible that the global account does not exist. Do not
             // throw an error. This will happen when users just blindly include
-            // global accounts that have not been initialized.
-            if !current_account_info.data_is_empty() {
+            // global accounts that have not been initialized. Those start the
+            // transfer hook accounts, as does anything not owned by manifest
+            // when a mint with a transfer hook was passed. Otherwise it has to
+            // be a global.
+            if current_account_info.data_is_empty()
+                || (has_transfer_hook_mint && *current_account_info.owner != crate::ID)
+            {
+                transfer_hook_accounts =
+                    &accounts[accounts.len() - account_iter.as_slice().len() - 1..];
+            } else {
                 let global: ManifestAccountInfo<'a, 'info, GlobalFixed> =
                     ManifestAccountInfo::<GlobalFixed>::new(current_account_info)?;
                 let global_data: Ref<&mut [u8]> = global.data.borrow();
@@ -444,6 +696,7 @@ impl<'a, 'info> SwapContext<'a, 'info> {
                     market: *market.info.key,
                     system_program: None,
                 });
+                transfer_hook_accounts = account_iter.as_slice();
             }
         }
 
@@ -451,6 +704,7 @@ impl<'a, 'info> SwapContext<'a, 'info> {
             payer: payer.clone(),
             owner,
             market,
+            system_program,
             trader_base,
             trader_quote,
             base_vault,
@@ -460,10 +714,201 @@ impl<'a, 'info> SwapContext<'a, 'info> {
             base_mint,
             quote_mint,
             global_trade_accounts_opts,
+            transfer_hook_accounts,
+            gate_credential_opt,
+            event_queue_opt,
         })
     }
 }
 
+/// SwapRoute account infos
+pub(crate) struct SwapRouteContext<'a, 'info> {
+    pub payer: Signer<'a, 'info>,
+    pub owner: Signer<'a, 'info>,
+    pub first_market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    pub second_market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    pub in_mint: MintAccountInfo<'a, 'info>,
+    pub intermediate_mint: MintAccountInfo<'a, 'info>,
+    pub out_mint: MintAccountInfo<'a, 'info>,
+    pub trader_in: TokenAccountInfo<'a, 'info>,
+    pub trader_intermediate: TokenAccountInfo<'a, 'info>,
+    pub trader_out: TokenAccountInfo<'a, 'info>,
+    pub first_in_vault: TokenAccountInfo<'a, 'info>,
+    pub first_intermediate_vault: TokenAccountInfo<'a, 'info>,
+    pub second_intermediate_vault: TokenAccountInfo<'a, 'info>,
+    pub second_out_vault: TokenAccountInfo<'a, 'info>,
+    pub token_program_in: TokenProgram<'a, 'info>,
+    pub token_program_intermediate: TokenProgram<'a, 'info>,
+    pub token_program_out: TokenProgram<'a, 'info>,
+
+    // Event queues for each market that has one, in any order after all other
+    // accounts.
+    pub first_event_queue_opt: Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
+    pub second_event_queue_opt: Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
+}
+
+impl<'a, 'info> SwapRouteContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let (accounts, last_event_queue_opt) = split_event_queue(accounts)?;
+        let (accounts, other_event_queue_opt) = split_event_queue(accounts)?;
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let payer: Signer = Signer::new_payer(next_account_info(account_iter)?)?;
+        let owner: Signer = Signer::new(next_account_info(account_iter)?)?;
+        // Included in case we need to expand for a reverse order.
+        let _system_program: Program =
+            Program::new(next_account_info(account_iter)?, &system_program::id())?;
+        let first_market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        let second_market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        require!(
+            first_market.key != second_market.key,
+            ManifestError::IncorrectAccount,
+            "Route markets must be different",
+        )?;
+
+        let in_mint: MintAccountInfo = MintAccountInfo::new(next_account_info(account_iter)?)?;
+        let intermediate_mint: MintAccountInfo =
+            MintAccountInfo::new(next_account_info(account_iter)?)?;
+        let out_mint: MintAccountInfo = MintAccountInfo::new(next_account_info(account_iter)?)?;
+        // There is no room for transfer hook accounts on a route.
+        for mint in [&in_mint, &intermediate_mint, &out_mint] {
+            require!(
+                !mint.has_transfer_hook(),
+                ManifestError::TransferHookNotSupported,
+                "Route mint {} has a transfer hook",
+                mint.info.key,
+            )?;
+        }
+
+        let first_market_fixed: Ref<MarketFixed> = first_market.get_fixed()?;
+        let second_market_fixed: Ref<MarketFixed> = second_market.get_fixed()?;
+        let (first_in_vault_address, first_intermediate_vault_address) = get_route_vault_addresses(
+            &first_market_fixed,
+            in_mint.info.key,
+            intermediate_mint.info.key,
+        )?;
+        let (second_intermediate_vault_address, second_out_vault_address) =
+            get_route_vault_addresses(
+                &second_market_fixed,
+                intermediate_mint.info.key,
+                out_mint.info.key,
+            )?;
+        drop(first_market_fixed);
+        drop(second_market_fixed);
+
+        let trader_in: TokenAccountInfo =
+            TokenAccountInfo::new(next_account_info(account_iter)?, in_mint.info.key)?;
+        let trader_intermediate: TokenAccountInfo =
+            TokenAccountInfo::new(next_account_info(account_iter)?, intermediate_mint.info.key)?;
+        let trader_out: TokenAccountInfo =
+            TokenAccountInfo::new(next_account_info(account_iter)?, out_mint.info.key)?;
+
+        let first_in_vault: TokenAccountInfo = TokenAccountInfo::new_with_owner_and_key(
+            next_account_info(account_iter)?,
+            in_mint.info.key,
+            &first_in_vault_address,
+            &first_in_vault_address,
+        )?;
+        let first_intermediate_vault: TokenAccountInfo = TokenAccountInfo::new_with_owner_and_key(
+            next_account_info(account_iter)?,
+            intermediate_mint.info.key,
+            &first_intermediate_vault_address,
+            &first_intermediate_vault_address,
+        )?;
+        let second_intermediate_vault: TokenAccountInfo = TokenAccountInfo::new_with_owner_and_key(
+            next_account_info(account_iter)?,
+            intermediate_mint.info.key,
+            &second_intermediate_vault_address,
+            &second_intermediate_vault_address,
+        )?;
+        let second_out_vault: TokenAccountInfo = TokenAccountInfo::new_with_owner_and_key(
+            next_account_info(account_iter)?,
+            out_mint.info.key,
+            &second_out_vault_address,
+            &second_out_vault_address,
+        )?;
+
+        let token_program_in: TokenProgram = TokenProgram::new(next_account_info(account_iter)?)?;
+        let token_program_intermediate: TokenProgram =
+            TokenProgram::new(next_account_info(account_iter)?)?;
+        let token_program_out: TokenProgram = TokenProgram::new(next_account_info(account_iter)?)?;
+
+        let mut first_event_queue_opt: Option<ManifestAccountInfo<EventQueueFixed>> = None;
+        let mut second_event_queue_opt: Option<ManifestAccountInfo<EventQueueFixed>> = None;
+        for event_queue in [last_event_queue_opt, other_event_queue_opt]
+            .into_iter()
+            .flatten()
+        {
+            let event_queue_market: Pubkey = *event_queue.get_fixed()?.get_market();
+            if event_queue_market == *first_market.key {
+                first_event_queue_opt = Some(event_queue);
+            } else {
+                require!(
+                    event_queue_market == *second_market.key,
+                    ManifestError::IncorrectAccount,
+                    "Event queue is for another market {}",
+                    event_queue_market,
+                )?;
+                second_event_queue_opt = Some(event_queue);
+            }
+        }
+
+        Ok(Self {
+            payer,
+            owner,
+            first_market,
+            second_market,
+            in_mint,
+            intermediate_mint,
+            out_mint,
+            trader_in,
+            trader_intermediate,
+            trader_out,
+            first_in_vault,
+            first_intermediate_vault,
+            second_intermediate_vault,
+            second_out_vault,
+            token_program_in,
+            token_program_intermediate,
+            token_program_out,
+            first_event_queue_opt,
+            second_event_queue_opt,
+        })
+    }
+}
+
+/// Returns the vaults for the in and out mints of one hop, verifying that the
+/// market trades exactly that pair.
+fn get_route_vault_addresses(
+    market_fixed: &MarketFixed,
+    in_mint: &Pubkey,
+    out_mint: &Pubkey,
+) -> Result<(Pubkey, Pubkey), ProgramError> {
+    let base_mint: &Pubkey = market_fixed.get_base_mint();
+    let quote_mint: &Pubkey = market_fixed.get_quote_mint();
+    let is_base_in: bool = base_mint == in_mint;
+    require!(
+        (is_base_in && quote_mint == out_mint) || (quote_mint == in_mint && base_mint == out_mint),
+        ManifestError::IncorrectAccount,
+        "Route market does not trade {} for {}",
+        in_mint,
+        out_mint,
+    )?;
+    if is_base_in {
+        Ok((
+            *market_fixed.get_base_vault(),
+            *market_fixed.get_quote_vault(),
+        ))
+    } else {
+        Ok((
+            *market_fixed.get_quote_vault(),
+            *market_fixed.get_base_vault(),
+        ))
+    }
+}
+
 /// Accounts needed to make a global trade. Scope is beyond just crate so
 /// clients can place orders on markets in testing.
 pub struct GlobalTradeAccounts<'a, 'info> {
@@ -488,21 +933,42 @@ pub struct GlobalTradeAccounts<'a, 'info> {
 
 /// BatchUpdate account infos
 pub(crate) struct BatchUpdateContext<'a, 'info> {
+    /// Pays for expansions and global gas deposits.
     pub payer: Signer<'a, 'info>,
+    /// Seat owner or its delegate. The processor checks which against the
+    /// seat. Same as the payer except for BatchUpdateV2.
+    pub owner: Signer<'a, 'info>,
     pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
     pub _system_program: Program<'a, 'info>,
 
     // One for each side. First is base, then is quote.
     pub global_trade_accounts_opts: [Option<GlobalTradeAccounts<'a, 'info>>; 2],
+
+    pub event_queue_opt: Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
 }
 
 impl<'a, 'info> BatchUpdateContext<'a, 'info> {
-    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+    /// The seat owner is its own account after the payer only for
+    /// BatchUpdateV2, which is decided by the instruction and not by the
+    /// accounts.
+    pub fn load(
+        accounts: &'a [AccountInfo<'info>],
+        has_separate_owner: bool,
+    ) -> Result<Self, ProgramError> {
+        let (accounts, event_queue_opt) = split_event_queue(accounts)?;
         let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
 
         // Does not have to be writable, but this ix will fail if removing a
         // global or requiring expanding.
         let payer: Signer = Signer::new(next_account_info(account_iter)?)?;
+
+        let owner: Signer = if has_separate_owner {
+            // Separate seat owner from rent payer. This is BatchUpdateV2.
+            Signer::new(next_account_info(account_iter)?)?
+        } else {
+            // BatchUpdate where the payer is also the seat owner.
+            payer.clone()
+        };
         let market: ManifestAccountInfo<MarketFixed> =
             ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
         let system_program: Program =
@@ -604,13 +1070,195 @@ impl<'a, 'info> BatchUpdateContext<'a, 'info> {
             }
         }
 
+        Ok(Self {
+            payer,
+            owner,
+            market,
+            _system_program: system_program,
+            global_trade_accounts_opts,
+            event_queue_opt,
+        })
+    }
+}
+
+/// Rebalance account infos
+pub(crate) struct RebalanceContext<'a, 'info> {
+    /// Seat owner. Pays for expansions.
+    pub payer: Signer<'a, 'info>,
+    pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    pub _system_program: Program<'a, 'info>,
+
+    // One for each side. First is base, then is quote.
+    pub trader_tokens: [TokenAccountInfo<'a, 'info>; 2],
+    pub vaults: [TokenAccountInfo<'a, 'info>; 2],
+    pub token_programs: [TokenProgram<'a, 'info>; 2],
+    pub mints: [MintAccountInfo<'a, 'info>; 2],
+
+    // One for each side. First is base, then is quote.
+    pub global_trade_accounts_opts: [Option<GlobalTradeAccounts<'a, 'info>>; 2],
+
+    // Extra account metas for mints with a transfer hook. Shared by both
+    // sides, the accounts for each mint are found by key.
+    pub transfer_hook_accounts: &'a [AccountInfo<'info>],
+
+    pub event_queue_opt: Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
+}
+
+impl<'a, 'info> RebalanceContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let (accounts, event_queue_opt) = split_event_queue(accounts)?;
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let payer: Signer = Signer::new(next_account_info(account_iter)?)?;
+        let market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        let system_program: Program =
+            Program::new(next_account_info(account_iter)?, &system_program::id())?;
+
+        let market_fixed: Ref<MarketFixed> = market.get_fixed()?;
+        let base_mint: Pubkey = *market_fixed.get_base_mint();
+        let quote_mint: Pubkey = *market_fixed.get_quote_mint();
+        let base_vault: Pubkey = *market_fixed.get_base_vault();
+        let quote_vault: Pubkey = *market_fixed.get_quote_vault();
+        drop(market_fixed);
+        let mint_keys: [Pubkey; 2] = [base_mint, quote_mint];
+
+        let base_trader_token: TokenAccountInfo = TokenAccountInfo::new_with_owner(
+            next_account_info(account_iter)?,
+            &base_mint,
+            payer.key,
+        )?;
+        let base_vault: TokenAccountInfo = TokenAccountInfo::new_with_owner_and_key(
+            next_account_info(account_iter)?,
+            &base_mint,
+            &base_vault,
+            &base_vault,
+        )?;
+        let base_token_program: TokenProgram = TokenProgram::new(next_account_info(account_iter)?)?;
+        let base_mint: MintAccountInfo = MintAccountInfo::new(next_account_info(account_iter)?)?;
+
+        let quote_trader_token: TokenAccountInfo = TokenAccountInfo::new_with_owner(
+            next_account_info(account_iter)?,
+            &quote_mint,
+            payer.key,
+        )?;
+        let quote_vault: TokenAccountInfo = TokenAccountInfo::new_with_owner_and_key(
+            next_account_info(account_iter)?,
+            &quote_mint,
+            &quote_vault,
+            &quote_vault,
+        )?;
+        let quote_token_program: TokenProgram =
+            TokenProgram::new(next_account_info(account_iter)?)?;
+        let quote_mint: MintAccountInfo = MintAccountInfo::new(next_account_info(account_iter)?)?;
+
+        let mints: [MintAccountInfo; 2] = [base_mint, quote_mint];
+        let vaults: [TokenAccountInfo; 2] = [base_vault, quote_vault];
+        let token_programs: [TokenProgram; 2] = [base_token_program, quote_token_program];
+
+        // Optional global and global vault for each side. The mint, market
+        // vault and token program are already above. Anything not owned by
+        // manifest starts the transfer hook accounts instead.
+        let mut global_trade_accounts_opts: [Option<GlobalTradeAccounts<'a, 'info>>; 2] =
+            [None, None];
+        while account_iter
+            .as_slice()
+            .first()
+            .is_some_and(|f| *f.owner == crate::ID && !f.data_is_empty())
+        {
+            let global: ManifestAccountInfo<'a, 'info, GlobalFixed> =
+                ManifestAccountInfo::<GlobalFixed>::new(next_account_info(account_iter)?)?;
+            let global_data: Ref<&mut [u8]> = global.data.borrow();
+            let global_fixed: &GlobalFixed = get_helper::<GlobalFixed>(&global_data, 0_u32);
+            let global_mint_key: Pubkey = *global_fixed.get_mint();
+            let expected_global_vault_address: Pubkey = *global_fixed.get_vault();
+            drop(global_data);
+
+            // Same check as in swap, the global has to be the PDA.
+            let (expected_global_key, _global_bump) = get_global_address(&global_mint_key);
+            require!(
+                expected_global_key == *global.info.key,
+                ManifestError::MissingGlobal,
+                "Unexpected global accounts",
+            )?;
+            let index: usize = if global_mint_key == mint_keys[0] {
+                0
+            } else {
+                require!(
+                    global_mint_key == mint_keys[1],
+                    ManifestError::MissingGlobal,
+                    "Unexpected global mint",
+                )?;
+                1
+            };
+            require!(
+                global_trade_accounts_opts[index].is_none(),
+                ManifestError::MissingGlobal,
+                "Duplicate global accounts",
+            )?;
+
+            let global_vault: TokenAccountInfo<'a, 'info> =
+                TokenAccountInfo::new_with_owner_and_key(
+                    next_account_info(account_iter)?,
+                    &global_mint_key,
+                    &expected_global_vault_address,
+                    &expected_global_vault_address,
+                )?;
+            global_trade_accounts_opts[index] = Some(GlobalTradeAccounts {
+                mint_opt: Some(mints[index].clone()),
+                global,
+                global_vault_opt: Some(global_vault),
+                market_vault_opt: Some(vaults[index].clone()),
+                token_program_opt: Some(token_programs[index].clone()),
+                system_program: Some(system_program.clone()),
+                gas_payer_opt: Some(payer.clone()),
+                gas_receiver_opt: Some(payer.clone()),
+                market: *market.info.key,
+            });
+        }
+        let transfer_hook_accounts: &'a [AccountInfo<'info>] = account_iter.as_slice();
+
         Ok(Self {
             payer,
             market,
             _system_program: system_program,
+            trader_tokens: [base_trader_token, quote_trader_token],
+            vaults,
+            token_programs,
+            mints,
             global_trade_accounts_opts,
+            transfer_hook_accounts,
+            event_queue_opt,
         })
     }
+
+    /// Accounts for depositing one side.
+    pub fn deposit_context(&self, is_base: bool) -> DepositContext<'a, 'info> {
+        let index: usize = if is_base { 0 } else { 1 };
+        DepositContext {
+            payer: self.payer.clone(),
+            market: self.market.clone(),
+            trader_token: self.trader_tokens[index].clone(),
+            vault: self.vaults[index].clone(),
+            token_program: self.token_programs[index].clone(),
+            mint: self.mints[index].clone(),
+            transfer_hook_accounts: self.transfer_hook_accounts,
+        }
+    }
+
+    /// Accounts for withdrawing one side.
+    pub fn withdraw_context(&self, is_base: bool) -> WithdrawContext<'a, 'info> {
+        let index: usize = if is_base { 0 } else { 1 };
+        WithdrawContext {
+            payer: self.payer.clone(),
+            market: self.market.clone(),
+            trader_token: self.trader_tokens[index].clone(),
+            vault: self.vaults[index].clone(),
+            token_program: self.token_programs[index].clone(),
+            mint: self.mints[index].clone(),
+            transfer_hook_accounts: self.transfer_hook_accounts,
+        }
+    }
 }
 
 /// Global create
@@ -864,10 +1512,12 @@ pub(crate) struct GlobalCleanContext<'a, 'info> {
     pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
     pub system_program: Program<'a, 'info>,
     pub global: ManifestAccountInfo<'a, 'info, GlobalFixed>,
+    pub event_queue_opt: Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
 }
 
 impl<'a, 'info> GlobalCleanContext<'a, 'info> {
     pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let (accounts, event_queue_opt) = split_event_queue(accounts)?;
         let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
 
         let payer: Signer = Signer::new_payer(next_account_info(account_iter)?)?;
@@ -894,6 +1544,113 @@ impl<'a, 'info> GlobalCleanContext<'a, 'info> {
             market,
             system_program,
             global,
+            event_queue_opt,
         })
     }
 }
+
+/// CleanExpired account infos
+pub(crate) struct CleanExpiredContext<'a, 'info> {
+    // Anyone can crank expired orders off the book.
+    pub _payer: Signer<'a, 'info>,
+    pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+    pub event_queue_opt: Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
+}
+
+impl<'a, 'info> CleanExpiredContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let (accounts, event_queue_opt) = split_event_queue(accounts)?;
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let _payer: Signer = Signer::new(next_account_info(account_iter)?)?;
+        let market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        Ok(Self {
+            _payer,
+            market,
+            event_queue_opt,
+        })
+    }
+}
+
+/// GetBook account infos
+pub(crate) struct GetBookContext<'a, 'info> {
+    pub market: ManifestAccountInfo<'a, 'info, MarketFixed>,
+}
+
+impl<'a, 'info> GetBookContext<'a, 'info> {
+    pub fn load(accounts: &'a [AccountInfo<'info>]) -> Result<Self, ProgramError> {
+        let account_iter: &mut Iter<AccountInfo<'info>> = &mut accounts.iter();
+
+        let market: ManifestAccountInfo<MarketFixed> =
+            ManifestAccountInfo::<MarketFixed>::new(next_account_info(account_iter)?)?;
+        Ok(Self { market })
+    }
+}
+
+/// Event queues are passed after every other account so that they do not
+/// shift the optional accounts before them. Returns the rest of the accounts
+/// and the event queue if the last account is one.
+fn split_event_queue<'a, 'info>(
+    accounts: &'a [AccountInfo<'info>],
+) -> Result<
+    (
+        &'a [AccountInfo<'info>],
+        Option<ManifestAccountInfo<'a, 'info, EventQueueFixed>>,
+    ),
+    ProgramError,
+> {
+    // Event queues are not modeled for formal verification.
+    #[cfg(not(feature = "certora"))]
+    {
+        if let Some((last, rest)) = accounts.split_last() {
+            if is_event_queue(last) {
+                return Ok((
+                    rest,
+                    Some(ManifestAccountInfo::<EventQueueFixed>::new(last)?),
+                ));
+            }
+        }
+    }
+    Ok((accounts, None))
+}
+
+/// Wallets without a wrapped SOL account pass their native sol address for
+/// the native mint so that the swap can wrap and unwrap for them.
+fn load_trader_token_account<'a, 'info>(
+    info: &'a AccountInfo<'info>,
+    mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<TokenAccountInfo<'a, 'info>, ProgramError> {
+    if *mint == spl_token::native_mint::id() && *info.key == get_native_sol_address(owner).0 {
+        return Ok(TokenAccountInfo { info });
+    }
+    TokenAccountInfo::new(info, mint)
+}
+
+/// Gate credentials go right before the event queue. The caller says whether
+/// one was included since the optional swap accounts before it, like transfer
+/// hook metas, can be signers or memberships too.
+fn split_gate_credential<'a, 'info>(
+    accounts: &'a [AccountInfo<'info>],
+    has_gate_credential: bool,
+) -> Result<(&'a [AccountInfo<'info>], Option<&'a AccountInfo<'info>>), ProgramError> {
+    if !has_gate_credential {
+        return Ok((accounts, None));
+    }
+    let (last, rest) = accounts
+        .split_last()
+        .ok_or(ProgramError::NotEnoughAccountKeys)?;
+    Ok((rest, Some(last)))
+}
+
+// Checked before ManifestAccountInfo::new so that the common case of no event
+// queue does not log an error.
+#[cfg(not(feature = "certora"))]
+fn is_event_queue(info: &AccountInfo) -> bool {
+    *info.owner == crate::ID
+        && info.try_borrow_data().is_ok_and(|data| {
+            data.len() >= size_of::<EventQueueFixed>()
+                && data[..size_of::<u64>()] == EVENT_QUEUE_FIXED_DISCRIMINANT.to_le_bytes()
+        })
+}
@@ -48,6 +48,19 @@ impl<'a, 'info, T: ManifestAccount + Get + Clone> ManifestAccountInfo<'a, 'info,
         })
     }
 
+    /// Same as new_init but for accounts that are sized when they are
+    /// created, so they can be bigger than the fixed header.
+    pub fn new_init_sized(
+        info: &'a AccountInfo<'info>,
+    ) -> Result<ManifestAccountInfo<'a, 'info, T>, ProgramError> {
+        verify_owned_by_manifest(info.owner)?;
+        verify_uninitialized_sized::<T>(info)?;
+        Ok(Self {
+            info,
+            phantom: std::marker::PhantomData,
+        })
+    }
+
     pub fn get_fixed(&self) -> Result<Ref<'_, T>, ProgramError> {
         let data: Ref<&mut [u8]> = self.info.try_borrow_data()?;
         Ok(Ref::map(data, |data| {
@@ -102,10 +115,29 @@ fn verify_uninitialized<T: Pod + ManifestAccount>(info: &AccountInfo) -> Program
     Ok(())
 }
 
+fn verify_uninitialized_sized<T: Pod + ManifestAccount>(info: &AccountInfo) -> ProgramResult {
+    let bytes: Ref<&mut [u8]> = info.try_borrow_data()?;
+    require!(
+        size_of::<T>() <= bytes.len(),
+        ProgramError::InvalidAccountData,
+        "Account too small for header expected at least: {} actual: {}",
+        size_of::<T>(),
+        bytes.len()
+    )?;
+    require!(
+        bytes.iter().all(|&byte| byte == 0),
+        ProgramError::InvalidAccountData,
+        "Expected zeroed",
+    )?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use crate::state::{
-        GlobalFixed, MarketFixed, GLOBAL_FIXED_DISCRIMINANT, MARKET_FIXED_DISCRIMINANT,
+        EventQueueFixed, GlobalFixed, MarketFixed, MarketMembership,
+        EVENT_QUEUE_FIXED_DISCRIMINANT, GLOBAL_FIXED_DISCRIMINANT, MARKET_FIXED_DISCRIMINANT,
+        MARKET_MEMBERSHIP_DISCRIMINANT,
     };
 
     #[test]
@@ -119,6 +151,18 @@ mod test {
         let discriminant: u64 = crate::utils::get_discriminant::<GlobalFixed>().unwrap();
         assert_eq!(discriminant, GLOBAL_FIXED_DISCRIMINANT);
     }
+
+    #[test]
+    fn test_event_queue_fixed_discriminant() {
+        let discriminant: u64 = crate::utils::get_discriminant::<EventQueueFixed>().unwrap();
+        assert_eq!(discriminant, EVENT_QUEUE_FIXED_DISCRIMINANT);
+    }
+
+    #[test]
+    fn test_market_membership_discriminant() {
+        let discriminant: u64 = crate::utils::get_discriminant::<MarketMembership>().unwrap();
+        assert_eq!(discriminant, MARKET_MEMBERSHIP_DISCRIMINANT);
+    }
 }
 
 macro_rules! global_seeds {
@@ -137,3 +181,40 @@ macro_rules! global_seeds_with_bump {
 pub fn get_global_address(mint: &Pubkey) -> (Pubkey, u8) {
     Pubkey::find_program_address(global_seeds!(mint), &crate::ID)
 }
+
+macro_rules! membership_seeds {
+    ( $market:expr, $trader:expr ) => {
+        &[b"membership", $market.as_ref(), $trader.as_ref()]
+    };
+}
+
+#[macro_export]
+macro_rules! membership_seeds_with_bump {
+    ( $market:expr, $trader:expr, $bump:expr ) => {
+        &[&[b"membership", $market.as_ref(), $trader.as_ref(), &[$bump]]]
+    };
+}
+
+pub fn get_membership_address(market: &Pubkey, trader: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(membership_seeds!(market, trader), &crate::ID)
+}
+
+macro_rules! native_sol_seeds {
+    ( $owner:expr ) => {
+        &[b"native-sol", $owner.as_ref()]
+    };
+}
+
+#[macro_export]
+macro_rules! native_sol_seeds_with_bump {
+    ( $owner:expr, $bump:expr ) => {
+        &[&[b"native-sol", $owner.as_ref(), &[$bump]]]
+    };
+}
+
+/// Address of the temporary wrapped SOL account used to unwrap swap proceeds
+/// for a trader without a wrapped SOL account. It only exists for the duration
+/// of a swap.
+pub fn get_native_sol_address(owner: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(native_sol_seeds!(owner), &crate::ID)
+}
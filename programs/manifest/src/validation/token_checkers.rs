@@ -1,7 +1,9 @@
 use crate::require;
 use solana_program::{account_info::AccountInfo, program_error::ProgramError, pubkey::Pubkey};
 use spl_token_2022::{
-    check_spl_token_program_account, extension::StateWithExtensions, state::Mint,
+    check_spl_token_program_account,
+    extension::{transfer_hook::TransferHook, BaseStateWithExtensions, StateWithExtensions},
+    state::Mint,
 };
 use std::ops::Deref;
 
@@ -19,6 +21,18 @@ impl<'a, 'info> MintAccountInfo<'a, 'info> {
 
         Ok(Self { mint, info })
     }
+
+    /// Whether the mint has a transfer hook program set, so transfers need
+    /// the extra account metas.
+    pub fn has_transfer_hook(&self) -> bool {
+        self.info.try_borrow_data().is_ok_and(|data| {
+            StateWithExtensions::<Mint>::unpack(&data).is_ok_and(|state| {
+                state
+                    .get_extension::<TransferHook>()
+                    .is_ok_and(|f| f.program_id.0 != Pubkey::default())
+            })
+        })
+    }
 }
 
 impl<'a, 'info> AsRef<AccountInfo<'info>> for MintAccountInfo<'a, 'info> {
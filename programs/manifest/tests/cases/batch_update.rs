@@ -1,11 +1,17 @@
+use std::rc::Rc;
+
 use hypertree::DataIndex;
 use manifest::{
-    program::batch_update::{CancelOrderParams, PlaceOrderParams},
+    program::{
+        batch_update::{CancelOrderParams, PlaceOrderParams},
+        batch_update_instruction, batch_update_v2_instruction, ManifestInstruction,
+    },
     state::{OrderType, MARKET_BLOCK_SIZE, NO_EXPIRATION_LAST_VALID_SLOT},
 };
 use solana_program_test::tokio;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
 
-use crate::{TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
+use crate::{send_tx_with_retry, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
 
 #[tokio::test]
 async fn batch_update_test() -> anyhow::Result<()> {
@@ -198,3 +204,160 @@ async fn batch_update_partial_fill_then_cancel_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn batch_update_v2_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    let second: Pubkey = second_keypair.pubkey();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::SOL, 10 * SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+
+    // Enough orders that the market has to grow, which the payer covers.
+    let orders: Vec<PlaceOrderParams> = (0..10)
+        .map(|i| {
+            PlaceOrderParams::new(
+                SOL_UNIT_SIZE,
+                10 + i,
+                0,
+                false,
+                OrderType::Limit,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )
+        })
+        .collect();
+    let second_lamports_before: u64 = test_fixture
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_balance(second)
+        .await?;
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[batch_update_v2_instruction(
+            &test_fixture.market_fixture.key,
+            &payer_keypair.pubkey(),
+            &second,
+            None,
+            vec![],
+            orders,
+            None,
+            None,
+            None,
+            None,
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair, &second_keypair],
+    )
+    .await?;
+
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&second)
+            .await,
+        0
+    );
+    assert_eq!(
+        test_fixture
+            .context
+            .borrow_mut()
+            .banks_client
+            .get_balance(second)
+            .await?,
+        second_lamports_before
+    );
+
+    // Owner has to sign for its seat.
+    let mut unsigned_owner_ix: Instruction = batch_update_v2_instruction(
+        &test_fixture.market_fixture.key,
+        &payer_keypair.pubkey(),
+        &second,
+        None,
+        vec![CancelOrderParams::new(0)],
+        vec![],
+        None,
+        None,
+        None,
+        None,
+    );
+    unsigned_owner_ix.accounts[1].is_signer = false;
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[unsigned_owner_ix],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // The account layout comes from the instruction. BatchUpdate does not
+    // accept a separate owner and BatchUpdateV2 requires one.
+    let mut v1_with_owner_ix: Instruction = batch_update_v2_instruction(
+        &test_fixture.market_fixture.key,
+        &payer_keypair.pubkey(),
+        &second,
+        None,
+        vec![CancelOrderParams::new(0)],
+        vec![],
+        None,
+        None,
+        None,
+        None,
+    );
+    v1_with_owner_ix.data[0] = ManifestInstruction::BatchUpdate as u8;
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[v1_with_owner_ix],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair, &second_keypair],
+    )
+    .await
+    .is_err());
+
+    let mut v2_without_owner_ix: Instruction = batch_update_instruction(
+        &test_fixture.market_fixture.key,
+        &second,
+        None,
+        vec![CancelOrderParams::new(0)],
+        vec![],
+        None,
+        None,
+        None,
+        None,
+    );
+    v2_without_owner_ix.data[0] = ManifestInstruction::BatchUpdateV2 as u8;
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[v2_without_owner_ix],
+        Some(&second),
+        &[&second_keypair],
+    )
+    .await
+    .is_err());
+
+    // The order is still there for the owner to cancel through BatchUpdateV2.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[batch_update_v2_instruction(
+            &test_fixture.market_fixture.key,
+            &payer_keypair.pubkey(),
+            &second,
+            None,
+            vec![CancelOrderParams::new(0)],
+            vec![],
+            None,
+            None,
+            None,
+            None,
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair, &second_keypair],
+    )
+    .await?;
+
+    Ok(())
+}
@@ -2,8 +2,11 @@ use std::rc::Rc;
 
 use hypertree::DataIndex;
 use manifest::{
-    program::{batch_update::CancelOrderParams, batch_update_instruction},
-    state::{OrderType, MARKET_BLOCK_SIZE},
+    program::{
+        batch_update::{CancelOrderParams, PlaceOrderParams},
+        batch_update_instruction, cancel_all_instruction,
+    },
+    state::{OrderType, RestingOrder, MARKET_BLOCK_SIZE, NO_EXPIRATION_LAST_VALID_SLOT},
 };
 use solana_program_test::tokio;
 use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
@@ -180,3 +183,164 @@ async fn cancel_order_with_hint_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn cancel_order_by_client_order_id_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, SOL_UNIT_SIZE).await?;
+
+    test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![],
+            vec![
+                PlaceOrderParams::new(
+                    1,
+                    1,
+                    0,
+                    false,
+                    OrderType::Limit,
+                    NO_EXPIRATION_LAST_VALID_SLOT,
+                )
+                .with_client_order_id(7),
+                PlaceOrderParams::new(
+                    1,
+                    2,
+                    0,
+                    false,
+                    OrderType::Limit,
+                    NO_EXPIRATION_LAST_VALID_SLOT,
+                )
+                .with_client_order_id(8),
+            ],
+            &payer_keypair,
+        )
+        .await?;
+
+    test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![CancelOrderParams::new_with_client_order_id(8, None)],
+            vec![],
+            &payer_keypair,
+        )
+        .await?;
+
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(resting_orders[0].get_client_order_id(), 7);
+
+    // Unknown client order ids fail like unknown sequence numbers.
+    assert!(test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![CancelOrderParams::new_with_client_order_id(8, None)],
+            vec![],
+            &payer_keypair,
+        )
+        .await
+        .is_err());
+
+    // Hint is checked against the client order id.
+    test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![CancelOrderParams::new_with_client_order_id(
+                7,
+                Some((1 * MARKET_BLOCK_SIZE).try_into().unwrap()),
+            )],
+            vec![],
+            &payer_keypair,
+        )
+        .await?;
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cancel_all_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, SOL_UNIT_SIZE).await?;
+    test_fixture.deposit(Token::USDC, USDC_UNIT_SIZE).await?;
+
+    test_fixture
+        .place_order(Side::Ask, 1, 2, 0, u32::MAX, OrderType::Limit)
+        .await?;
+    test_fixture
+        .place_order(Side::Ask, 1, 3, 0, u32::MAX, OrderType::PostOnly)
+        .await?;
+    test_fixture
+        .place_order(Side::Bid, 1, 1, 0, u32::MAX, OrderType::Limit)
+        .await?;
+
+    // Only the post only ask.
+    let cancel_all_ix: Instruction = cancel_all_instruction(
+        &test_fixture.market_fixture.key,
+        &payer,
+        None,
+        Some(false),
+        Some(OrderType::PostOnly),
+        None,
+        None,
+    );
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[cancel_all_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        2
+    );
+
+    // Everything else.
+    let cancel_all_ix: Instruction = cancel_all_instruction(
+        &test_fixture.market_fixture.key,
+        &payer,
+        None,
+        None,
+        None,
+        None,
+        None,
+    );
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[cancel_all_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        0
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&payer)
+            .await,
+        USDC_UNIT_SIZE
+    );
+
+    Ok(())
+}
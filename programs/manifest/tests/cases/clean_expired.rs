@@ -0,0 +1,120 @@
+use std::rc::Rc;
+
+use hypertree::{DataIndex, HyperTreeValueIteratorTrait};
+use manifest::{
+    program::clean_expired_instruction,
+    state::{OrderType, RestingOrder, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{send_tx_with_retry, Side, TestFixture, Token, SOL_UNIT_SIZE};
+
+#[tokio::test]
+async fn clean_expired_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 3 * SOL_UNIT_SIZE).await?;
+
+    // Slots may advance during tests, so expiration is set pretty far out.
+    test_fixture
+        .place_order(Side::Ask, 1 * SOL_UNIT_SIZE, 2, 0, 1_000, OrderType::Limit)
+        .await?;
+    test_fixture
+        .place_order(Side::Ask, 1 * SOL_UNIT_SIZE, 3, 0, 1_000, OrderType::Limit)
+        .await?;
+    test_fixture
+        .place_order(
+            Side::Ask,
+            1 * SOL_UNIT_SIZE,
+            4,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await?;
+
+    test_fixture.market_fixture.reload().await;
+    let order_index_at_3: DataIndex = test_fixture
+        .market_fixture
+        .market
+        .get_asks()
+        .iter::<RestingOrder>()
+        .find(|(_, resting_order)| resting_order.get_sequence_number() == 1)
+        .unwrap()
+        .0;
+
+    // Nothing is expired yet, so this is a no-op.
+    let cranker: Keypair = test_fixture.second_keypair.insecure_clone();
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[clean_expired_instruction(
+            &test_fixture.market_fixture.key,
+            &cranker.pubkey(),
+            vec![order_index_at_3],
+            10,
+        )],
+        Some(&cranker.pubkey()),
+        &[&cranker],
+    )
+    .await?;
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        3
+    );
+
+    test_fixture.advance_time_seconds(10_000).await;
+
+    // Only the given index.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[clean_expired_instruction(
+            &test_fixture.market_fixture.key,
+            &cranker.pubkey(),
+            vec![order_index_at_3],
+            0,
+        )],
+        Some(&cranker.pubkey()),
+        &[&cranker],
+    )
+    .await?;
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        2
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        1 * SOL_UNIT_SIZE
+    );
+
+    // Scanning finds the other expired order. The already removed index is
+    // skipped rather than failing.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[clean_expired_instruction(
+            &test_fixture.market_fixture.key,
+            &cranker.pubkey(),
+            vec![order_index_at_3],
+            10,
+        )],
+        Some(&cranker.pubkey()),
+        &[&cranker],
+    )
+    .await?;
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(resting_orders[0].get_sequence_number(), 2);
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        2 * SOL_UNIT_SIZE
+    );
+
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use std::rc::Rc;
 
 use borsh::ser::BorshSerialize;
-use manifest::program::{deposit::DepositParams, deposit_instruction, ManifestInstruction};
+use manifest::program::{
+    deposit::DepositParams, deposit_for_instruction, deposit_instruction, ManifestInstruction,
+};
 use solana_program_test::tokio;
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
@@ -153,3 +155,59 @@ async fn global_deposit_fail_incorrect_vault_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn deposit_for_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    let second: Pubkey = second_keypair.pubkey();
+
+    test_fixture
+        .sol_mint_fixture
+        .mint_to(&test_fixture.payer_sol_fixture.key, 2 * SOL_UNIT_SIZE)
+        .await;
+    let deposit_for_ix: Instruction = deposit_for_instruction(
+        &test_fixture.market_fixture.key,
+        &payer,
+        &second,
+        &test_fixture.sol_mint_fixture.key,
+        SOL_UNIT_SIZE,
+        &test_fixture.payer_sol_fixture.key,
+        spl_token::id(),
+    );
+
+    // The trader needs a seat to be funded.
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[deposit_for_ix.clone()],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[deposit_for_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&second)
+            .await,
+        SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.payer_sol_fixture.balance_atoms().await,
+        SOL_UNIT_SIZE
+    );
+
+    Ok(())
+}
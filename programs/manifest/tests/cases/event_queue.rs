@@ -0,0 +1,415 @@
+use std::rc::Rc;
+
+use manifest::{
+    program::{
+        batch_update::CancelOrderParams, batch_update_instruction, consume_events_instruction,
+        create_event_queue_instructions, create_market::CreateMarketParams, get_dynamic_value,
+        swap_instruction,
+    },
+    quantities::WrapperU64,
+    state::{
+        EventQueueFixed, EventQueueValue, MarketEvent, MarketEventType, OrderType,
+        NO_EXPIRATION_LAST_VALID_SLOT,
+    },
+};
+use solana_program_test::tokio;
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+};
+
+use crate::{
+    send_tx_with_retry, MarketFixture, Side, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE,
+};
+
+async fn get_event_queue(test_fixture: &TestFixture, event_queue: &Pubkey) -> EventQueueValue {
+    let event_queue_account: Account = test_fixture
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(*event_queue)
+        .await
+        .unwrap()
+        .unwrap();
+    get_dynamic_value::<EventQueueFixed>(event_queue_account.data.as_slice())
+}
+
+#[tokio::test]
+async fn event_queue_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    test_fixture.market_fixture = MarketFixture::new_with_params(
+        Rc::clone(&test_fixture.context),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        Some(CreateMarketParams::default().with_market_authority(payer)),
+    )
+    .await;
+    let market: Pubkey = test_fixture.market_fixture.key;
+
+    // Only the market authority can attach a queue.
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    let wrong_event_queue_keypair: Keypair = Keypair::new();
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &create_event_queue_instructions(
+            &market,
+            &wrong_event_queue_keypair.pubkey(),
+            &second_keypair.pubkey(),
+            4,
+        ),
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair, &wrong_event_queue_keypair],
+    )
+    .await
+    .is_err());
+
+    let event_queue_keypair: Keypair = Keypair::new();
+    let event_queue: Pubkey = event_queue_keypair.pubkey();
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &create_event_queue_instructions(&market, &event_queue, &payer, 4),
+        Some(&payer),
+        &[&payer_keypair, &event_queue_keypair],
+    )
+    .await?;
+
+    let event_queue_value: EventQueueValue = get_event_queue(&test_fixture, &event_queue).await;
+    assert_eq!(*event_queue_value.fixed.get_market(), market);
+    assert_eq!(event_queue_value.fixed.get_capacity(), 4);
+    assert_eq!(event_queue_value.fixed.get_num_unconsumed_events(), 0);
+
+    // Second keypair asks 1 SOL at 10 USDC/SOL.
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::SOL, SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Ask,
+            SOL_UNIT_SIZE,
+            1,
+            -2,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await?;
+
+    test_fixture
+        .usdc_mint_fixture
+        .mint_to(&test_fixture.payer_usdc_fixture.key, 5 * USDC_UNIT_SIZE)
+        .await;
+    let mut swap_ix: Instruction = swap_instruction(
+        &market,
+        &payer,
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        &test_fixture.payer_sol_fixture.key,
+        &test_fixture.payer_usdc_fixture.key,
+        5 * USDC_UNIT_SIZE,
+        0,
+        false,
+        true,
+        spl_token::id(),
+        spl_token::id(),
+        false,
+    );
+
+    // Fills fail without the queue so that no event is missed.
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_ix.clone()],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    swap_ix.accounts.push(AccountMeta::new(event_queue, false));
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    // Maker cancels the rest.
+    let mut cancel_ix: Instruction = batch_update_instruction(
+        &market,
+        &second_keypair.pubkey(),
+        None,
+        vec![CancelOrderParams::new(0)],
+        vec![],
+        None,
+        None,
+        None,
+        None,
+    );
+    cancel_ix
+        .accounts
+        .push(AccountMeta::new(event_queue, false));
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[cancel_ix],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await?;
+
+    let event_queue_value: EventQueueValue = get_event_queue(&test_fixture, &event_queue).await;
+    let events: Vec<MarketEvent> = event_queue_value.get_unconsumed_events();
+    assert_eq!(events.len(), 2);
+
+    let fill: &MarketEvent = &events[0];
+    assert_eq!(fill.get_event_type()?, MarketEventType::Fill);
+    assert_eq!(fill.sequence_number, 0);
+    assert_eq!(fill.maker, second_keypair.pubkey());
+    assert_eq!(fill.taker, payer);
+    assert_eq!(fill.base_atoms.as_u64(), SOL_UNIT_SIZE / 2);
+    assert_eq!(fill.quote_atoms.as_u64(), 5 * USDC_UNIT_SIZE);
+    assert_eq!(fill.maker_sequence_number, 0);
+    assert!(!bool::from(fill.maker_is_bid));
+
+    let cancel: &MarketEvent = &events[1];
+    assert_eq!(cancel.get_event_type()?, MarketEventType::Cancel);
+    assert_eq!(cancel.sequence_number, 1);
+    assert_eq!(cancel.maker, second_keypair.pubkey());
+    assert_eq!(cancel.base_atoms.as_u64(), SOL_UNIT_SIZE / 2);
+    assert_eq!(cancel.maker_sequence_number, 0);
+
+    // Anyone can consume.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[consume_events_instruction(&event_queue, 1)],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await?;
+    let event_queue_value: EventQueueValue = get_event_queue(&test_fixture, &event_queue).await;
+    assert_eq!(event_queue_value.fixed.get_tail_sequence_number(), 1);
+    assert_eq!(event_queue_value.fixed.get_head_sequence_number(), 2);
+    assert_eq!(
+        event_queue_value.get_unconsumed_events()[0].sequence_number,
+        1
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn event_queue_full_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.market_fixture = MarketFixture::new_with_params(
+        Rc::clone(&test_fixture.context),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        Some(CreateMarketParams::default().with_market_authority(payer)),
+    )
+    .await;
+    let market: Pubkey = test_fixture.market_fixture.key;
+
+    // Room for one event.
+    let event_queue_keypair: Keypair = Keypair::new();
+    let event_queue: Pubkey = event_queue_keypair.pubkey();
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &create_event_queue_instructions(&market, &event_queue, &payer, 1),
+        Some(&payer),
+        &[&payer_keypair, &event_queue_keypair],
+    )
+    .await?;
+
+    // Second keypair asks 1 SOL at 10 USDC/SOL twice.
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::SOL, 2 * SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+    for _ in 0..2 {
+        test_fixture
+            .place_order_for_keypair(
+                Side::Ask,
+                SOL_UNIT_SIZE,
+                1,
+                -2,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+                OrderType::Limit,
+                &second_keypair,
+            )
+            .await?;
+    }
+
+    test_fixture
+        .usdc_mint_fixture
+        .mint_to(&test_fixture.payer_usdc_fixture.key, 10 * USDC_UNIT_SIZE)
+        .await;
+    let mut swap_ix: Instruction = swap_instruction(
+        &market,
+        &payer,
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        &test_fixture.payer_sol_fixture.key,
+        &test_fixture.payer_usdc_fixture.key,
+        5 * USDC_UNIT_SIZE,
+        0,
+        false,
+        true,
+        spl_token::id(),
+        spl_token::id(),
+        false,
+    );
+    swap_ix.accounts.push(AccountMeta::new(event_queue, false));
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    // Fills fail while the queue is full.
+    let mut second_swap_ix: Instruction = swap_instruction(
+        &market,
+        &payer,
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        &test_fixture.payer_sol_fixture.key,
+        &test_fixture.payer_usdc_fixture.key,
+        4 * USDC_UNIT_SIZE,
+        0,
+        false,
+        true,
+        spl_token::id(),
+        spl_token::id(),
+        false,
+    );
+    second_swap_ix
+        .accounts
+        .push(AccountMeta::new(event_queue, false));
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[second_swap_ix.clone()],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // Cancels still go through and are counted as dropped.
+    let mut cancel_ix: Instruction = batch_update_instruction(
+        &market,
+        &second_keypair.pubkey(),
+        None,
+        vec![CancelOrderParams::new(1)],
+        vec![],
+        None,
+        None,
+        None,
+        None,
+    );
+    cancel_ix
+        .accounts
+        .push(AccountMeta::new(event_queue, false));
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[cancel_ix],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await?;
+
+    let event_queue_value: EventQueueValue = get_event_queue(&test_fixture, &event_queue).await;
+    assert_eq!(event_queue_value.fixed.get_num_dropped_events(), 1);
+    let events: Vec<MarketEvent> = event_queue_value.get_unconsumed_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].get_event_type()?, MarketEventType::Fill);
+
+    // Trading resumes once the fill is consumed.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[consume_events_instruction(&event_queue, 1)],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[second_swap_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    let event_queue_value: EventQueueValue = get_event_queue(&test_fixture, &event_queue).await;
+    let events: Vec<MarketEvent> = event_queue_value.get_unconsumed_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].sequence_number, 1);
+    assert_eq!(events[0].get_event_type()?, MarketEventType::Fill);
+    assert_eq!(events[0].maker_sequence_number, 0);
+    assert_eq!(event_queue_value.fixed.get_num_dropped_events(), 1);
+
+    // Cancels do not need the queue at all. Without it nothing is written.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[batch_update_instruction(
+            &market,
+            &second_keypair.pubkey(),
+            None,
+            vec![CancelOrderParams::new(0)],
+            vec![],
+            None,
+            None,
+            None,
+            None,
+        )],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await?;
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        0
+    );
+    let event_queue_value: EventQueueValue = get_event_queue(&test_fixture, &event_queue).await;
+    assert_eq!(event_queue_value.fixed.get_head_sequence_number(), 2);
+    assert_eq!(event_queue_value.fixed.get_num_dropped_events(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn event_queue_requires_market_authority_test() -> anyhow::Result<()> {
+    let test_fixture: TestFixture = TestFixture::new().await;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    let market: Pubkey = test_fixture.market_fixture.key;
+
+    // The default market has no authority, so nobody can attach a queue. Not
+    // even the creator.
+    for keypair in [&second_keypair, &payer_keypair] {
+        let event_queue_keypair: Keypair = Keypair::new();
+        assert!(send_tx_with_retry(
+            Rc::clone(&test_fixture.context),
+            &create_event_queue_instructions(
+                &market,
+                &event_queue_keypair.pubkey(),
+                &keypair.pubkey(),
+                4,
+            ),
+            Some(&keypair.pubkey()),
+            &[keypair, &event_queue_keypair],
+        )
+        .await
+        .is_err());
+    }
+
+    Ok(())
+}
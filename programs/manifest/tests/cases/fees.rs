@@ -0,0 +1,229 @@
+use std::rc::Rc;
+
+use manifest::{
+    program::{
+        collect_fees_instruction, create_market::CreateMarketParams, create_market_instructions,
+        create_market_with_params_instruction,
+    },
+    quantities::WrapperU64,
+    state::{MarketFeeConfig, OrderType, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::tokio;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{
+    send_tx_with_retry, MarketFixture, Side, TestFixture, Token, TokenAccountFixture,
+    SOL_UNIT_SIZE, USDC_UNIT_SIZE,
+};
+
+// 10 bps taker fee, 2 bps maker rebate.
+const TAKER_FEE_MANTISSA: u32 = 1_000_000;
+const MAKER_REBATE_MANTISSA: u32 = 200_000;
+
+#[tokio::test]
+async fn fees_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let fee_recipient: Pubkey = Pubkey::new_unique();
+    test_fixture.market_fixture = MarketFixture::new_with_params(
+        Rc::clone(&test_fixture.context),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        Some(CreateMarketParams::default().with_fees(
+            fee_recipient,
+            TAKER_FEE_MANTISSA,
+            MAKER_REBATE_MANTISSA,
+        )),
+    )
+    .await;
+
+    // Payer rests an ask for 1 SOL at 1000 USDC.
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 1 * SOL_UNIT_SIZE).await?;
+    test_fixture
+        .place_order(
+            Side::Ask,
+            1 * SOL_UNIT_SIZE,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await?;
+
+    // Second keypair takes it.
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::USDC, 1_000 * USDC_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Bid,
+            1 * SOL_UNIT_SIZE,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await?;
+
+    // Taker pays the fee in base, maker gets the rebate in base.
+    let taker_fee_atoms: u64 = 1 * SOL_UNIT_SIZE / 1_000;
+    let maker_rebate_atoms: u64 = 1 * SOL_UNIT_SIZE / 5_000;
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&second_keypair.pubkey())
+            .await,
+        1 * SOL_UNIT_SIZE - taker_fee_atoms
+    );
+    let payer: Pubkey = test_fixture.payer();
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        maker_rebate_atoms
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&payer)
+            .await,
+        1_000 * USDC_UNIT_SIZE
+    );
+
+    let fee_config: MarketFeeConfig = *test_fixture.market_fixture.market.get_fee_config().unwrap();
+    assert_eq!(
+        fee_config.get_collected_base_atoms().as_u64(),
+        taker_fee_atoms - maker_rebate_atoms
+    );
+    assert_eq!(fee_config.get_collected_quote_atoms().as_u64(), 0);
+
+    // Anyone can send the fees to the recipient.
+    let fee_recipient_token_fixture: TokenAccountFixture = TokenAccountFixture::new(
+        Rc::clone(&test_fixture.context),
+        &test_fixture.sol_mint_fixture.key,
+        &fee_recipient,
+    )
+    .await;
+    let collect_fees_ix: Instruction = collect_fees_instruction(
+        &test_fixture.market_fixture.key,
+        &second_keypair.pubkey(),
+        &test_fixture.sol_mint_fixture.key,
+        &fee_recipient_token_fixture.key,
+        spl_token::id(),
+    );
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[collect_fees_ix],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await?;
+
+    assert_eq!(
+        fee_recipient_token_fixture.balance_atoms().await,
+        taker_fee_atoms - maker_rebate_atoms
+    );
+    test_fixture.market_fixture.reload().await;
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .market
+            .get_fee_config()
+            .unwrap()
+            .get_collected_base_atoms()
+            .as_u64(),
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn fees_swap_exact_out_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.market_fixture = MarketFixture::new_with_params(
+        Rc::clone(&test_fixture.context),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        Some(CreateMarketParams::default().with_fees(
+            Pubkey::new_unique(),
+            TAKER_FEE_MANTISSA,
+            MAKER_REBATE_MANTISSA,
+        )),
+    )
+    .await;
+
+    // Second keypair rests an ask for 2 SOL at 1000 USDC.
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::SOL, 2 * SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Ask,
+            2 * SOL_UNIT_SIZE,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await?;
+
+    // Exact out matches out_atoms before fees. The taker fee comes out of the
+    // out token, so the wallet receives out_atoms less the fee.
+    test_fixture
+        .usdc_mint_fixture
+        .mint_to(&test_fixture.payer_usdc_fixture.key, 1_000 * USDC_UNIT_SIZE)
+        .await;
+    test_fixture
+        .swap(1_000 * USDC_UNIT_SIZE, 1 * SOL_UNIT_SIZE, false, false)
+        .await?;
+
+    let taker_fee_atoms: u64 = 1 * SOL_UNIT_SIZE / 1_000;
+    assert_eq!(
+        test_fixture.payer_sol_fixture.balance_atoms().await,
+        1 * SOL_UNIT_SIZE - taker_fee_atoms
+    );
+    assert_eq!(test_fixture.payer_usdc_fixture.balance_atoms().await, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn fees_invalid_params_test() -> anyhow::Result<()> {
+    let test_fixture: TestFixture = TestFixture::new().await;
+    let market_keypair: Keypair = Keypair::new();
+    let payer: Pubkey = test_fixture.payer();
+
+    // Rebate larger than the fee is rejected.
+    let mut create_market_ixs: Vec<Instruction> = create_market_instructions(
+        &market_keypair.pubkey(),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        &payer,
+    )
+    .unwrap();
+    create_market_ixs[1] = create_market_with_params_instruction(
+        &market_keypair.pubkey(),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        &payer,
+        CreateMarketParams::default().with_fees(payer, MAKER_REBATE_MANTISSA, TAKER_FEE_MANTISSA),
+    );
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &create_market_ixs[..],
+        Some(&payer),
+        &[&test_fixture.payer_keypair(), &market_keypair],
+    )
+    .await
+    .is_err());
+
+    Ok(())
+}
@@ -0,0 +1,102 @@
+use borsh::BorshDeserialize;
+use manifest::{
+    program::{get_book::GetBookReturn, get_book_instruction},
+    quantities::QuoteAtomsPerBaseAtom,
+    state::{OrderType, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::tokio;
+use solana_sdk::{hash::Hash, signature::Keypair, signer::Signer, transaction::Transaction};
+
+use crate::{Side, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
+
+async fn get_book(test_fixture: &TestFixture, num_levels: u8) -> anyhow::Result<GetBookReturn> {
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let blockhash: Hash = test_fixture
+        .context
+        .borrow_mut()
+        .get_new_latest_blockhash()
+        .await?;
+    let tx: Transaction = Transaction::new_signed_with_payer(
+        &[get_book_instruction(
+            &test_fixture.market_fixture.key,
+            num_levels,
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair],
+        blockhash,
+    );
+    let simulation = test_fixture
+        .context
+        .borrow_mut()
+        .banks_client
+        .simulate_transaction(tx)
+        .await?;
+    let return_data: Vec<u8> = simulation
+        .simulation_details
+        .unwrap()
+        .return_data
+        .unwrap()
+        .data;
+    Ok(GetBookReturn::try_from_slice(&return_data)?)
+}
+
+#[tokio::test]
+async fn get_book_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 10 * SOL_UNIT_SIZE).await?;
+    test_fixture
+        .deposit(Token::USDC, 2_000 * USDC_UNIT_SIZE)
+        .await?;
+
+    let book: GetBookReturn = get_book(&test_fixture, 10).await?;
+    assert!(book.bids.is_empty());
+    assert!(book.asks.is_empty());
+
+    for (side, base_atoms, price_mantissa) in [
+        (Side::Ask, 1 * SOL_UNIT_SIZE, 2),
+        (Side::Ask, 2 * SOL_UNIT_SIZE, 2),
+        (Side::Ask, 1 * SOL_UNIT_SIZE, 3),
+        (Side::Bid, 1 * SOL_UNIT_SIZE, 1),
+    ] {
+        test_fixture
+            .place_order(
+                side,
+                base_atoms,
+                price_mantissa,
+                0,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+                OrderType::Limit,
+            )
+            .await?;
+    }
+
+    // Orders at the same price are merged into one level.
+    let book: GetBookReturn = get_book(&test_fixture, 1).await?;
+    assert_eq!(book.asks.len(), 1);
+    assert_eq!(
+        book.asks[0].price,
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(2, 0).unwrap()
+    );
+    assert_eq!(book.asks[0].base_atoms, 3 * SOL_UNIT_SIZE);
+    assert_eq!(book.asks[0].num_orders, 2);
+    assert_eq!(book.bids.len(), 1);
+    assert_eq!(
+        book.bids[0].price,
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(1, 0).unwrap()
+    );
+    assert_eq!(book.bids[0].base_atoms, 1 * SOL_UNIT_SIZE);
+    assert_eq!(book.bids[0].num_orders, 1);
+
+    // More levels than exist only returns what is on the book.
+    let book: GetBookReturn = get_book(&test_fixture, u8::MAX).await?;
+    assert_eq!(book.asks.len(), 2);
+    assert_eq!(
+        book.asks[1].price,
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(3, 0).unwrap()
+    );
+    assert_eq!(book.asks[1].base_atoms, 1 * SOL_UNIT_SIZE);
+    assert_eq!(book.bids.len(), 1);
+
+    Ok(())
+}
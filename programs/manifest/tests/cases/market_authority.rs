@@ -0,0 +1,156 @@
+use std::rc::Rc;
+
+use manifest::{
+    program::{
+        create_market::CreateMarketParams, renounce_market_authority_instruction,
+        set_market_status_instruction, transfer_market_authority_instruction,
+    },
+    state::{MarketStatus, OrderType, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{send_tx_with_retry, MarketFixture, Side, TestFixture, Token, SOL_UNIT_SIZE};
+
+async fn set_market_status(
+    test_fixture: &TestFixture,
+    authority: &Keypair,
+    status: MarketStatus,
+) -> anyhow::Result<(), BanksClientError> {
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[set_market_status_instruction(
+            &test_fixture.market_fixture.key,
+            &authority.pubkey(),
+            status,
+        )],
+        Some(&authority.pubkey()),
+        &[authority],
+    )
+    .await
+}
+
+async fn place_ask(
+    test_fixture: &mut TestFixture,
+    base_atoms: u64,
+) -> anyhow::Result<(), BanksClientError> {
+    test_fixture
+        .place_order(
+            Side::Ask,
+            base_atoms,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await
+}
+
+async fn setup_market_authority_test() -> anyhow::Result<TestFixture> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    test_fixture.market_fixture = MarketFixture::new_with_params(
+        Rc::clone(&test_fixture.context),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        Some(CreateMarketParams::default().with_market_authority(payer)),
+    )
+    .await;
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, SOL_UNIT_SIZE).await?;
+    Ok(test_fixture)
+}
+
+#[tokio::test]
+async fn market_status_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_market_authority_test().await?;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    place_ask(&mut test_fixture, 1_000).await?;
+    place_ask(&mut test_fixture, 2_000).await?;
+
+    // Only the authority can change the status.
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    assert!(
+        set_market_status(&test_fixture, &second_keypair, MarketStatus::Paused)
+            .await
+            .is_err()
+    );
+
+    // Cancel only allows cancels and withdraws.
+    set_market_status(&test_fixture, &payer_keypair, MarketStatus::CancelOnly).await?;
+    assert!(place_ask(&mut test_fixture, 3_000).await.is_err());
+    assert!(test_fixture.deposit(Token::SOL, 1).await.is_err());
+    test_fixture.cancel_order(0).await?;
+
+    // Paused allows only withdraws.
+    set_market_status(&test_fixture, &payer_keypair, MarketStatus::Paused).await?;
+    assert!(test_fixture.cancel_order(1).await.is_err());
+    assert!(place_ask(&mut test_fixture, 4_000).await.is_err());
+    test_fixture.withdraw(Token::SOL, 1_000).await?;
+
+    // Resume
+    set_market_status(&test_fixture, &payer_keypair, MarketStatus::Active).await?;
+    test_fixture.cancel_order(1).await?;
+    place_ask(&mut test_fixture, 5_000).await?;
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        1
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn market_authority_transfer_test() -> anyhow::Result<()> {
+    let test_fixture: TestFixture = setup_market_authority_test().await?;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[transfer_market_authority_instruction(
+            &test_fixture.market_fixture.key,
+            &payer_keypair.pubkey(),
+            &second_keypair.pubkey(),
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair],
+    )
+    .await?;
+    assert!(
+        set_market_status(&test_fixture, &payer_keypair, MarketStatus::Paused)
+            .await
+            .is_err()
+    );
+    set_market_status(&test_fixture, &second_keypair, MarketStatus::CancelOnly).await?;
+
+    // Cannot renounce unless active.
+    let renounce_ix: Instruction = renounce_market_authority_instruction(
+        &test_fixture.market_fixture.key,
+        &second_keypair.pubkey(),
+    );
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[renounce_ix.clone()],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await
+    .is_err());
+
+    set_market_status(&test_fixture, &second_keypair, MarketStatus::Active).await?;
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[renounce_ix],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await?;
+    assert!(
+        set_market_status(&test_fixture, &second_keypair, MarketStatus::Paused)
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
@@ -1,13 +1,25 @@
 pub mod batch_update;
 pub mod cancel_order;
 pub mod claim_seat;
+pub mod clean_expired;
 pub mod create_market;
 pub mod deposit;
+pub mod event_queue;
+pub mod fees;
+pub mod get_book;
 pub mod global;
 pub mod loaders;
+pub mod market_authority;
 pub mod matching;
+pub mod permissioned;
+pub mod place_grid;
 pub mod place_order;
+pub mod rebalance;
 pub mod reverse;
+pub mod seat_delegate;
+pub mod self_trade;
 pub mod swap;
+pub mod swap_route;
 pub mod token22;
+pub mod twap;
 pub mod withdraw;
@@ -0,0 +1,248 @@
+use std::rc::Rc;
+
+use hypertree::get_helper;
+use manifest::{
+    program::{
+        batch_update::CancelOrderParams, batch_update_instruction,
+        claim_seat_with_gate_credential_instruction, create_market::CreateMarketParams,
+        set_membership_instruction, swap_instruction, swap_instruction_with_params, SwapParams,
+    },
+    state::{MarketMembership, OrderType, NO_EXPIRATION_LAST_VALID_SLOT},
+    validation::get_membership_address,
+};
+use solana_program_test::tokio;
+use solana_sdk::{
+    account::Account,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+};
+
+use crate::{
+    send_tx_with_retry, MarketFixture, Side, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE,
+};
+
+async fn get_membership(test_fixture: &TestFixture, membership: &Pubkey) -> MarketMembership {
+    let membership_account: Account = test_fixture
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(*membership)
+        .await
+        .unwrap()
+        .unwrap();
+    *get_helper::<MarketMembership>(membership_account.data.as_slice(), 0_u32)
+}
+
+#[tokio::test]
+async fn permissioned_market_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    test_fixture.market_fixture = MarketFixture::new_with_params(
+        Rc::clone(&test_fixture.context),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        Some(CreateMarketParams::default().with_gate_authority(payer)),
+    )
+    .await;
+    let market: Pubkey = test_fixture.market_fixture.key;
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    let second: Pubkey = second_keypair.pubkey();
+    let (membership, _) = get_membership_address(&market, &second);
+
+    // No credential, and no membership issued yet.
+    assert!(test_fixture
+        .claim_seat_for_keypair(&second_keypair)
+        .await
+        .is_err());
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[claim_seat_with_gate_credential_instruction(
+            &market,
+            &second,
+            &membership,
+            false,
+        )],
+        Some(&second),
+        &[&second_keypair],
+    )
+    .await
+    .is_err());
+
+    // Only the gate authority issues memberships.
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[set_membership_instruction(&market, &second, &second, true)],
+        Some(&second),
+        &[&second_keypair],
+    )
+    .await
+    .is_err());
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[set_membership_instruction(&market, &payer, &second, true)],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+    let membership_value: MarketMembership = get_membership(&test_fixture, &membership).await;
+    assert_eq!(*membership_value.get_market(), market);
+    assert_eq!(*membership_value.get_trader(), second);
+    assert!(!membership_value.get_is_revoked());
+
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[claim_seat_with_gate_credential_instruction(
+            &market,
+            &second,
+            &membership,
+            false,
+        )],
+        Some(&second),
+        &[&second_keypair],
+    )
+    .await?;
+
+    // Second asks 2 SOL at 10 USDC/SOL.
+    test_fixture
+        .deposit_for_keypair(Token::SOL, 2 * SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Ask,
+            2 * SOL_UNIT_SIZE,
+            1,
+            1,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await?;
+
+    // Payer has no seat, so the temporary seat for the swap needs a
+    // credential.
+    test_fixture
+        .usdc_mint_fixture
+        .mint_to(&test_fixture.payer_usdc_fixture.key, 10 * USDC_UNIT_SIZE)
+        .await;
+    let swap_ix: Instruction = swap_instruction(
+        &market,
+        &payer,
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        &test_fixture.payer_sol_fixture.key,
+        &test_fixture.payer_usdc_fixture.key,
+        10 * USDC_UNIT_SIZE,
+        0,
+        false,
+        true,
+        spl_token::id(),
+        spl_token::id(),
+        false,
+    );
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // The credential is flagged in the params so it is not mistaken for
+    // another trailing account.
+    let credential_swap_ix: Instruction = swap_instruction_with_params(
+        &market,
+        &payer,
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        &test_fixture.payer_sol_fixture.key,
+        &test_fixture.payer_usdc_fixture.key,
+        SwapParams::new(10 * USDC_UNIT_SIZE, 0, false, true).with_gate_credential(true),
+        spl_token::id(),
+        spl_token::id(),
+        false,
+    );
+
+    // Membership of another trader does not work.
+    let mut wrong_membership_swap_ix: Instruction = credential_swap_ix.clone();
+    wrong_membership_swap_ix
+        .accounts
+        .push(AccountMeta::new_readonly(membership, false));
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[wrong_membership_swap_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // Gate authority signature works.
+    let mut gate_authority_swap_ix: Instruction = credential_swap_ix;
+    gate_authority_swap_ix
+        .accounts
+        .push(AccountMeta::new_readonly(payer, true));
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[gate_authority_swap_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&second)
+            .await,
+        10 * USDC_UNIT_SIZE
+    );
+
+    // Revoking moves the seat to cancel only.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[set_membership_instruction(&market, &payer, &second, false)],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+    assert!(get_membership(&test_fixture, &membership)
+        .await
+        .get_is_revoked());
+    assert!(test_fixture
+        .place_order_for_keypair(
+            Side::Ask,
+            SOL_UNIT_SIZE,
+            1,
+            1,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await
+        .is_err());
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[batch_update_instruction(
+            &market,
+            &second,
+            None,
+            vec![CancelOrderParams::new(0)],
+            vec![],
+            None,
+            None,
+            None,
+            None,
+        )],
+        Some(&second),
+        &[&second_keypair],
+    )
+    .await?;
+    test_fixture
+        .withdraw_for_keypair(Token::SOL, SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+
+    Ok(())
+}
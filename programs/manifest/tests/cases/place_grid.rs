@@ -0,0 +1,129 @@
+use std::rc::Rc;
+
+use manifest::{
+    program::{
+        place_grid::{GridStepType, MAX_GRID_LEVELS},
+        place_grid_instruction,
+    },
+    quantities::{QuoteAtomsPerBaseAtom, WrapperU64},
+    state::{OrderType, RestingOrder},
+};
+use solana_program_test::tokio;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair};
+
+use crate::{send_tx_with_retry, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
+
+#[tokio::test]
+async fn place_grid_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 10 * SOL_UNIT_SIZE).await?;
+    test_fixture
+        .deposit(Token::USDC, 10_000 * USDC_UNIT_SIZE)
+        .await?;
+
+    // More bid levels than levels is rejected.
+    let invalid_grid_ix: Instruction = place_grid_instruction(
+        &test_fixture.market_fixture.key,
+        &payer,
+        None,
+        1,
+        0,
+        GridStepType::Arithmetic,
+        1,
+        -1,
+        2,
+        3,
+        SOL_UNIT_SIZE,
+        5_000,
+    );
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[invalid_grid_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // More levels than a grid can hold is rejected.
+    let too_many_levels_ix: Instruction = place_grid_instruction(
+        &test_fixture.market_fixture.key,
+        &payer,
+        None,
+        1,
+        0,
+        GridStepType::Arithmetic,
+        1,
+        -1,
+        MAX_GRID_LEVELS + 1,
+        0,
+        SOL_UNIT_SIZE,
+        5_000,
+    );
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[too_many_levels_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // 1.0, 1.1 bids and 1.21, 1.331 asks.
+    let place_grid_ix: Instruction = place_grid_instruction(
+        &test_fixture.market_fixture.key,
+        &payer,
+        None,
+        1,
+        0,
+        GridStepType::Geometric,
+        10_000,
+        0,
+        4,
+        2,
+        SOL_UNIT_SIZE,
+        5_000,
+    );
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[place_grid_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    // Bids best first, then asks best first.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 4);
+    for (resting_order, (is_bid, mantissa, exponent)) in resting_orders.iter().zip([
+        (true, 11, -1),
+        (true, 1, 0),
+        (false, 121, -2),
+        (false, 1331, -3),
+    ]) {
+        assert_eq!(resting_order.get_is_bid(), is_bid);
+        assert_eq!(resting_order.get_order_type(), OrderType::Reverse);
+        assert_eq!(resting_order.get_reverse_spread(), 5_000);
+        assert_eq!(resting_order.get_num_base_atoms().as_u64(), SOL_UNIT_SIZE);
+        assert_eq!(
+            resting_order.get_price(),
+            QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(mantissa, exponent).unwrap()
+        );
+    }
+
+    // 10 - 2 asks = 8
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        8 * SOL_UNIT_SIZE
+    );
+    // Spare block is left for the first flip.
+    assert!(test_fixture.market_fixture.market.has_free_block());
+
+    Ok(())
+}
@@ -2,17 +2,19 @@ use std::u64;
 
 use hypertree::HyperTreeValueIteratorTrait;
 use manifest::{
+    program::{batch_update::PlaceOrderParams, create_market::CreateMarketParams},
     quantities::{QuoteAtomsPerBaseAtom, WrapperU64},
     state::{
         constants::{MARKET_BLOCK_SIZE, MARKET_FIXED_SIZE, NO_EXPIRATION_LAST_VALID_SLOT},
-        OrderType, RestingOrder,
+        MarketTradingConfig, OrderType, RestingOrder, ReversePriceBounds,
     },
     validation::get_vault_address,
 };
 use solana_program_test::tokio;
 use solana_sdk::signature::{Keypair, Signer};
+use std::rc::Rc;
 
-use crate::{Side, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
+use crate::{MarketFixture, Side, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
 
 #[tokio::test]
 async fn place_order_test() -> anyhow::Result<()> {
@@ -711,6 +713,183 @@ async fn post_only_fail_test() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn post_only_slide_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.claim_seat().await?;
+
+    // Ask for 2@10
+    test_fixture.deposit(Token::SOL, 20 * SOL_UNIT_SIZE).await?;
+    test_fixture
+        .place_order(
+            Side::Ask,
+            2 * SOL_UNIT_SIZE,
+            10,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await?;
+
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::USDC, 20_000 * USDC_UNIT_SIZE, &second_keypair)
+        .await?;
+
+    // Would cross at 10, so it slides to one tick below the best ask.
+    test_fixture
+        .place_order_for_keypair(
+            Side::Bid,
+            1 * SOL_UNIT_SIZE,
+            11,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::PostOnlySlide,
+            &second_keypair,
+        )
+        .await?;
+
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 2);
+    let bid: &RestingOrder = resting_orders
+        .iter()
+        .find(|order| order.get_is_bid())
+        .unwrap();
+    assert_eq!(
+        bid.get_price(),
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(10, 0)
+            .unwrap()
+            .checked_tick(false)
+            .unwrap()
+    );
+    assert_eq!(bid.get_num_base_atoms().as_u64(), 1 * SOL_UNIT_SIZE);
+
+    // Nothing traded.
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&second_keypair.pubkey())
+            .await,
+        0
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&second_keypair.pubkey())
+            .await,
+        10_000 * USDC_UNIT_SIZE
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn fill_or_kill_basic_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.claim_seat().await?;
+
+    // Ask for 2@10
+    test_fixture.deposit(Token::SOL, 2 * SOL_UNIT_SIZE).await?;
+    test_fixture
+        .place_order(
+            Side::Ask,
+            2 * SOL_UNIT_SIZE,
+            10,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await?;
+
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::USDC, 20_000 * USDC_UNIT_SIZE, &second_keypair)
+        .await?;
+
+    // Fully matched, so it succeeds and nothing rests.
+    test_fixture
+        .place_order_for_keypair(
+            Side::Bid,
+            1 * SOL_UNIT_SIZE,
+            10,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::FillOrKill,
+            &second_keypair,
+        )
+        .await?;
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        1 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&second_keypair.pubkey())
+            .await,
+        1 * SOL_UNIT_SIZE
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn fill_or_kill_fail_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.claim_seat().await?;
+
+    // Ask for 2@10
+    test_fixture.deposit(Token::SOL, 2 * SOL_UNIT_SIZE).await?;
+    test_fixture
+        .place_order(
+            Side::Ask,
+            2 * SOL_UNIT_SIZE,
+            10,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await?;
+
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::USDC, 30_000 * USDC_UNIT_SIZE, &second_keypair)
+        .await?;
+
+    // Only 2 of the 3 are available.
+    assert!(test_fixture
+        .place_order_for_keypair(
+            Side::Bid,
+            3 * SOL_UNIT_SIZE,
+            10,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::FillOrKill,
+            &second_keypair
+        )
+        .await
+        .is_err());
+
+    // Nothing traded.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        2 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&second_keypair.pubkey())
+            .await,
+        30_000 * USDC_UNIT_SIZE
+    );
+    Ok(())
+}
+
 #[tokio::test]
 async fn place_order_already_expired_test() -> anyhow::Result<()> {
     let mut test_fixture: TestFixture = TestFixture::new().await;
@@ -1149,3 +1328,300 @@ async fn reverse_order_tight_type_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn reverse_order_price_bounds_test() -> anyhow::Result<()> {
+    // Default payer places a reverse bid 3@1.0 bounded to [0.5, 1.2]. The
+    // flip would land at 2.0, so fills stay in the seat balance instead.
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 10 * SOL_UNIT_SIZE).await?;
+    test_fixture
+        .deposit(Token::USDC, 10_000 * USDC_UNIT_SIZE)
+        .await?;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+
+    // Price outside of its own bounds is rejected.
+    assert!(test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![],
+            vec![
+                PlaceOrderParams::new(3 * SOL_UNIT_SIZE, 1, 0, true, OrderType::Reverse, 50_000)
+                    .with_reverse_price_bounds(ReversePriceBounds::new(2, 0, 0, 0))
+            ],
+            &payer_keypair,
+        )
+        .await
+        .is_err());
+
+    test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![],
+            vec![
+                PlaceOrderParams::new(3 * SOL_UNIT_SIZE, 1, 0, true, OrderType::Reverse, 50_000)
+                    .with_reverse_price_bounds(ReversePriceBounds::new(5, -1, 12, -1)),
+            ],
+            &payer_keypair,
+        )
+        .await?;
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(
+        resting_orders[0].get_reverse_price_bounds(),
+        ReversePriceBounds::new(5, -1, 12, -1)
+    );
+
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::SOL, 10 * SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Ask,
+            1 * SOL_UNIT_SIZE,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await?;
+
+    // Only the remaining bid rests, no flipped ask.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(resting_orders[0].get_is_bid(), true);
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        2 * SOL_UNIT_SIZE
+    );
+    // 11 = 10 + 1 filled
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&test_fixture.payer())
+            .await,
+        11 * SOL_UNIT_SIZE
+    );
+    test_fixture
+        .withdraw(Token::SOL, 11 * SOL_UNIT_SIZE)
+        .await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn iceberg_order_test() -> anyhow::Result<()> {
+    // Default payer places an iceberg ask 5@1.0 showing 2 at a time.
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 10 * SOL_UNIT_SIZE).await?;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+
+    // Display size is required.
+    assert!(test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![],
+            vec![PlaceOrderParams::new(
+                5 * SOL_UNIT_SIZE,
+                1,
+                0,
+                false,
+                OrderType::Iceberg,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )],
+            &payer_keypair,
+        )
+        .await
+        .is_err());
+
+    test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![],
+            vec![PlaceOrderParams::new(
+                5 * SOL_UNIT_SIZE,
+                1,
+                0,
+                false,
+                OrderType::Iceberg,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )
+            .with_iceberg_display_base_atoms(2 * SOL_UNIT_SIZE)],
+            &payer_keypair,
+        )
+        .await?;
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        2 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        resting_orders[0].get_iceberg_reserve_base_atoms().as_u64(),
+        3 * SOL_UNIT_SIZE
+    );
+    let first_sequence_number: u64 = resting_orders[0].get_sequence_number();
+    // The full size is locked.
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&test_fixture.payer())
+            .await,
+        5 * SOL_UNIT_SIZE
+    );
+
+    // Second keypair joins the same price after the iceberg.
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::SOL, SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .deposit_for_keypair(Token::USDC, 10_000 * USDC_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Ask,
+            SOL_UNIT_SIZE,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await?;
+
+    // Taking the display slice refills it behind the second keypair's ask.
+    test_fixture
+        .place_order_for_keypair(
+            Side::Bid,
+            2 * SOL_UNIT_SIZE,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::ImmediateOrCancel,
+            &second_keypair,
+        )
+        .await?;
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 2);
+    assert_eq!(resting_orders[0].get_order_type(), OrderType::Limit);
+    let iceberg: &RestingOrder = &resting_orders[1];
+    assert_eq!(iceberg.get_order_type(), OrderType::Iceberg);
+    assert_eq!(iceberg.get_num_base_atoms().as_u64(), 2 * SOL_UNIT_SIZE);
+    assert_eq!(
+        iceberg.get_iceberg_reserve_base_atoms().as_u64(),
+        SOL_UNIT_SIZE
+    );
+    assert!(iceberg.get_sequence_number() > first_sequence_number);
+
+    // Cancelling returns the display and the reserve. 10 - 2 filled = 8
+    test_fixture
+        .cancel_order(iceberg.get_sequence_number())
+        .await?;
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&test_fixture.payer())
+            .await,
+        8 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&test_fixture.payer())
+            .await,
+        2_000 * USDC_UNIT_SIZE
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn place_order_trading_config_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    // Tick of 0.1 quote atoms per base atom, lots of 1_000 and a minimum of
+    // 10_000 base atoms.
+    test_fixture.market_fixture = MarketFixture::new_with_params(
+        Rc::clone(&test_fixture.context),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        Some(
+            CreateMarketParams::default()
+                .with_tick_size(1, -1)
+                .with_lot_size(1_000)
+                .with_min_base_atoms(10_000),
+        ),
+    )
+    .await;
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, SOL_UNIT_SIZE).await?;
+
+    // Off tick
+    assert!(test_fixture
+        .place_order(
+            Side::Ask,
+            10_000,
+            15,
+            -2,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await
+        .is_err());
+    // Off lot
+    assert!(test_fixture
+        .place_order(
+            Side::Ask,
+            10_500,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await
+        .is_err());
+    // Below minimum
+    assert!(test_fixture
+        .place_order(
+            Side::Ask,
+            9_000,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await
+        .is_err());
+
+    test_fixture
+        .place_order(
+            Side::Ask,
+            11_000,
+            12,
+            -1,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await?;
+
+    test_fixture.market_fixture.reload().await;
+    let trading_config: MarketTradingConfig = *test_fixture
+        .market_fixture
+        .market
+        .get_trading_config()
+        .unwrap();
+    assert_eq!(trading_config.get_lot_size().as_u64(), 1_000);
+    assert_eq!(trading_config.get_min_base_atoms().as_u64(), 10_000);
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        1
+    );
+
+    Ok(())
+}
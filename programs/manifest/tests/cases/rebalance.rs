@@ -0,0 +1,264 @@
+use std::rc::Rc;
+
+use manifest::{
+    program::{
+        batch_update::{CancelOrderParams, PlaceOrderParams},
+        rebalance::WITHDRAW_ALL_ATOMS,
+        rebalance_instruction, rebalance_with_globals_instruction,
+    },
+    quantities::GlobalAtoms,
+    state::{OrderType, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::tokio;
+use solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{send_tx_with_retry, TestFixture, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
+
+#[tokio::test]
+async fn rebalance_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    test_fixture.claim_seat().await?;
+    test_fixture
+        .sol_mint_fixture
+        .mint_to(&test_fixture.payer_sol_fixture.key, 3 * SOL_UNIT_SIZE)
+        .await;
+
+    // Deposit everything, rest 1 SOL and pull the unused 2 SOL back out.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[rebalance_instruction(
+            &test_fixture.market_fixture.key,
+            &payer,
+            &test_fixture.payer_sol_fixture.key,
+            &test_fixture.sol_mint_fixture.key,
+            spl_token::id(),
+            &test_fixture.payer_usdc_fixture.key,
+            &test_fixture.usdc_mint_fixture.key,
+            spl_token::id(),
+            3 * SOL_UNIT_SIZE,
+            0,
+            None,
+            vec![],
+            vec![PlaceOrderParams::new(
+                SOL_UNIT_SIZE,
+                10,
+                0,
+                false,
+                OrderType::Limit,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )],
+            WITHDRAW_ALL_ATOMS,
+            0,
+        )],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    assert_eq!(
+        test_fixture.payer_sol_fixture.balance_atoms().await,
+        2 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        0
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        1
+    );
+
+    // Cannot withdraw more than is left on the seat after the batch.
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[rebalance_instruction(
+            &test_fixture.market_fixture.key,
+            &payer,
+            &test_fixture.payer_sol_fixture.key,
+            &test_fixture.sol_mint_fixture.key,
+            spl_token::id(),
+            &test_fixture.payer_usdc_fixture.key,
+            &test_fixture.usdc_mint_fixture.key,
+            spl_token::id(),
+            0,
+            0,
+            None,
+            vec![],
+            vec![],
+            SOL_UNIT_SIZE,
+            0,
+        )],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // Cancel and withdraw the freed funds in the same instruction.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[rebalance_instruction(
+            &test_fixture.market_fixture.key,
+            &payer,
+            &test_fixture.payer_sol_fixture.key,
+            &test_fixture.sol_mint_fixture.key,
+            spl_token::id(),
+            &test_fixture.payer_usdc_fixture.key,
+            &test_fixture.usdc_mint_fixture.key,
+            spl_token::id(),
+            0,
+            0,
+            Some(0),
+            vec![CancelOrderParams::new(0)],
+            vec![],
+            SOL_UNIT_SIZE,
+            WITHDRAW_ALL_ATOMS,
+        )],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    assert_eq!(
+        test_fixture.payer_sol_fixture.balance_atoms().await,
+        3 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rebalance_global_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+
+    // Second keypair bids 1 SOL at 1000 USDC/SOL backed by its global USDC.
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .global_add_trader_for_keypair(&second_keypair)
+        .await?;
+    test_fixture
+        .global_deposit_for_keypair(&second_keypair, 1_000 * USDC_UNIT_SIZE)
+        .await?;
+    test_fixture
+        .batch_update_with_global_for_keypair(
+            None,
+            vec![],
+            vec![PlaceOrderParams::new(
+                SOL_UNIT_SIZE,
+                1,
+                0,
+                true,
+                OrderType::Global,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )],
+            &second_keypair,
+        )
+        .await?;
+
+    test_fixture.claim_seat().await?;
+    test_fixture
+        .sol_mint_fixture
+        .mint_to(&test_fixture.payer_sol_fixture.key, SOL_UNIT_SIZE)
+        .await;
+    let sell_orders: Vec<PlaceOrderParams> = vec![PlaceOrderParams::new(
+        SOL_UNIT_SIZE,
+        1,
+        0,
+        false,
+        OrderType::Limit,
+        NO_EXPIRATION_LAST_VALID_SLOT,
+    )];
+
+    // Crossing a global order needs its global accounts.
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[rebalance_instruction(
+            &test_fixture.market_fixture.key,
+            &payer,
+            &test_fixture.payer_sol_fixture.key,
+            &test_fixture.sol_mint_fixture.key,
+            spl_token::id(),
+            &test_fixture.payer_usdc_fixture.key,
+            &test_fixture.usdc_mint_fixture.key,
+            spl_token::id(),
+            SOL_UNIT_SIZE,
+            0,
+            None,
+            vec![],
+            sell_orders.clone(),
+            0,
+            WITHDRAW_ALL_ATOMS,
+        )],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // Deposit the SOL, sell it into the global bid and withdraw the USDC.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[rebalance_with_globals_instruction(
+            &test_fixture.market_fixture.key,
+            &payer,
+            &test_fixture.payer_sol_fixture.key,
+            &test_fixture.sol_mint_fixture.key,
+            spl_token::id(),
+            &test_fixture.payer_usdc_fixture.key,
+            &test_fixture.usdc_mint_fixture.key,
+            spl_token::id(),
+            SOL_UNIT_SIZE,
+            0,
+            None,
+            vec![],
+            sell_orders,
+            0,
+            WITHDRAW_ALL_ATOMS,
+            false,
+            true,
+        )],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    assert_eq!(test_fixture.payer_sol_fixture.balance_atoms().await, 0);
+    assert_eq!(
+        test_fixture.payer_usdc_fixture.balance_atoms().await,
+        1_000 * USDC_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        0
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&second_keypair.pubkey())
+            .await,
+        SOL_UNIT_SIZE
+    );
+
+    test_fixture.global_fixture.reload().await;
+    assert_eq!(
+        test_fixture
+            .global_fixture
+            .global
+            .get_balance_atoms(&second_keypair.pubkey()),
+        GlobalAtoms::ZERO
+    );
+
+    Ok(())
+}
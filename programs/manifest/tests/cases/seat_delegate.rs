@@ -0,0 +1,176 @@
+use std::rc::Rc;
+
+use hypertree::DataIndex;
+use manifest::{
+    program::{
+        batch_update::{CancelOrderParams, PlaceOrderParams},
+        clear_seat_delegate_instruction, set_seat_delegate_instruction, withdraw_instruction,
+    },
+    state::{OrderType, RestingOrder, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{send_tx_with_retry, TestFixture, Token, SOL_UNIT_SIZE};
+
+async fn set_seat_delegate(
+    test_fixture: &TestFixture,
+    trader_index: DataIndex,
+    delegate: &Pubkey,
+) -> anyhow::Result<(), BanksClientError> {
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[set_seat_delegate_instruction(
+            &test_fixture.market_fixture.key,
+            &payer_keypair.pubkey(),
+            Some(trader_index),
+            delegate,
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair],
+    )
+    .await
+}
+
+fn ask_params() -> Vec<PlaceOrderParams> {
+    vec![PlaceOrderParams::new(
+        SOL_UNIT_SIZE,
+        1,
+        0,
+        false,
+        OrderType::Limit,
+        NO_EXPIRATION_LAST_VALID_SLOT,
+    )]
+}
+
+#[tokio::test]
+async fn seat_delegate_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let delegate_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 10 * SOL_UNIT_SIZE).await?;
+    test_fixture.market_fixture.reload().await;
+    let trader_index: DataIndex = test_fixture.market_fixture.market.get_trader_index(&payer);
+
+    // Not a delegate yet.
+    assert!(test_fixture
+        .batch_update_for_keypair(Some(trader_index), vec![], ask_params(), &delegate_keypair)
+        .await
+        .is_err());
+
+    // Cannot delegate to yourself.
+    assert!(set_seat_delegate(&test_fixture, trader_index, &payer)
+        .await
+        .is_err());
+    set_seat_delegate(&test_fixture, trader_index, &delegate_keypair.pubkey()).await?;
+    test_fixture.market_fixture.reload().await;
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .market
+            .get_seat_delegate(trader_index),
+        Some(&delegate_keypair.pubkey())
+    );
+    // Setting a delegate took a block, but a spare one is left.
+    assert!(test_fixture.market_fixture.market.has_free_block());
+
+    // Delegates are not looked up without the hint.
+    assert!(test_fixture
+        .batch_update_for_keypair(None, vec![], ask_params(), &delegate_keypair)
+        .await
+        .is_err());
+    test_fixture
+        .batch_update_for_keypair(Some(trader_index), vec![], ask_params(), &delegate_keypair)
+        .await?;
+
+    // Order rests on the owner seat and uses the owner funds.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(resting_orders[0].get_trader_index(), trader_index);
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        9 * SOL_UNIT_SIZE
+    );
+
+    // Delegate cannot withdraw, even to the owner token account.
+    let withdraw_ix: Instruction = withdraw_instruction(
+        &test_fixture.market_fixture.key,
+        &delegate_keypair.pubkey(),
+        &test_fixture.sol_mint_fixture.key,
+        SOL_UNIT_SIZE,
+        &test_fixture.payer_sol_fixture.key,
+        spl_token::id(),
+        Some(trader_index),
+    );
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[withdraw_ix],
+        Some(&delegate_keypair.pubkey()),
+        &[&delegate_keypair],
+    )
+    .await
+    .is_err());
+
+    test_fixture
+        .batch_update_for_keypair(
+            Some(trader_index),
+            vec![CancelOrderParams::new(
+                resting_orders[0].get_sequence_number(),
+            )],
+            vec![],
+            &delegate_keypair,
+        )
+        .await?;
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        10 * SOL_UNIT_SIZE
+    );
+
+    // Only the owner can clear.
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[clear_seat_delegate_instruction(
+            &test_fixture.market_fixture.key,
+            &delegate_keypair.pubkey(),
+            Some(trader_index),
+        )],
+        Some(&delegate_keypair.pubkey()),
+        &[&delegate_keypair],
+    )
+    .await
+    .is_err());
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[clear_seat_delegate_instruction(
+            &test_fixture.market_fixture.key,
+            &payer,
+            Some(trader_index),
+        )],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+    test_fixture.market_fixture.reload().await;
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .market
+            .get_seat_delegate(trader_index),
+        None
+    );
+    assert!(test_fixture
+        .batch_update_for_keypair(Some(trader_index), vec![], ask_params(), &delegate_keypair)
+        .await
+        .is_err());
+
+    Ok(())
+}
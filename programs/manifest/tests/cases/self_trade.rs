@@ -0,0 +1,364 @@
+use manifest::{
+    program::batch_update::PlaceOrderParams,
+    quantities::WrapperU64,
+    state::{OrderType, RestingOrder, SelfTradeBehavior, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::{tokio, BanksClientError};
+use solana_sdk::{pubkey::Pubkey, signature::Keypair};
+
+use crate::{Side, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
+
+async fn setup_self_trade_test(ask_base_atoms: u64) -> anyhow::Result<TestFixture> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 2 * SOL_UNIT_SIZE).await?;
+    test_fixture
+        .deposit(Token::USDC, 2_000 * USDC_UNIT_SIZE)
+        .await?;
+    test_fixture
+        .place_order(
+            Side::Ask,
+            ask_base_atoms,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+        )
+        .await?;
+    Ok(test_fixture)
+}
+
+async fn self_trade_bid(
+    test_fixture: &mut TestFixture,
+    self_trade_behavior: SelfTradeBehavior,
+) -> anyhow::Result<(), BanksClientError> {
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![],
+            vec![PlaceOrderParams::new(
+                1 * SOL_UNIT_SIZE,
+                1,
+                0,
+                true,
+                OrderType::Limit,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )
+            .with_self_trade_behavior(self_trade_behavior)],
+            &payer_keypair,
+        )
+        .await
+}
+
+#[tokio::test]
+async fn self_trade_allow_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_test(1 * SOL_UNIT_SIZE).await?;
+    self_trade_bid(&mut test_fixture, SelfTradeBehavior::Allow).await?;
+
+    // Legacy behavior, trades against itself and records volume.
+    let payer: Pubkey = test_fixture.payer();
+    assert_eq!(
+        test_fixture.market_fixture.get_quote_volume(&payer).await,
+        2_000 * USDC_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_trade_cancel_maker_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_test(1 * SOL_UNIT_SIZE).await?;
+    self_trade_bid(&mut test_fixture, SelfTradeBehavior::CancelMaker).await?;
+
+    // The ask was cancelled and the bid rests in its place.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert!(resting_orders[0].get_is_bid());
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        1 * SOL_UNIT_SIZE
+    );
+
+    let payer: Pubkey = test_fixture.payer();
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        2 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&payer)
+            .await,
+        1_000 * USDC_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_quote_volume(&payer).await,
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_trade_cancel_taker_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_test(1 * SOL_UNIT_SIZE).await?;
+    self_trade_bid(&mut test_fixture, SelfTradeBehavior::CancelTaker).await?;
+
+    // The ask is untouched and nothing rests for the bid.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert!(!resting_orders[0].get_is_bid());
+
+    let payer: Pubkey = test_fixture.payer();
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&payer)
+            .await,
+        2_000 * USDC_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_quote_volume(&payer).await,
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_trade_decrement_both_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_test(2 * SOL_UNIT_SIZE).await?;
+    self_trade_bid(&mut test_fixture, SelfTradeBehavior::DecrementBoth).await?;
+
+    // Both sides shrink by 1 SOL. The ask keeps the rest and the bid is used up.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert!(!resting_orders[0].get_is_bid());
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        1 * SOL_UNIT_SIZE
+    );
+
+    let payer: Pubkey = test_fixture.payer();
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        1 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&payer)
+            .await,
+        2_000 * USDC_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_quote_volume(&payer).await,
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_trade_abort_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_test(1 * SOL_UNIT_SIZE).await?;
+    assert!(self_trade_bid(&mut test_fixture, SelfTradeBehavior::Abort)
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_trade_fill_or_kill_decrement_both_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_test(2 * SOL_UNIT_SIZE).await?;
+
+    // Decrementing is not a fill, so the fill or kill has to fail rather than
+    // succeed with nothing received.
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    assert!(test_fixture
+        .batch_update_for_keypair(
+            None,
+            vec![],
+            vec![PlaceOrderParams::new(
+                1 * SOL_UNIT_SIZE,
+                1,
+                0,
+                true,
+                OrderType::FillOrKill,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )
+            .with_self_trade_behavior(SelfTradeBehavior::DecrementBoth)],
+            &payer_keypair,
+        )
+        .await
+        .is_err());
+
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        2 * SOL_UNIT_SIZE
+    );
+
+    Ok(())
+}
+
+async fn setup_self_trade_global_test() -> anyhow::Result<TestFixture> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 1 * SOL_UNIT_SIZE).await?;
+    test_fixture.global_add_trader().await?;
+    test_fixture.global_deposit(2_000 * USDC_UNIT_SIZE).await?;
+    test_fixture
+        .batch_update_with_global_for_keypair(
+            None,
+            vec![],
+            vec![PlaceOrderParams::new(
+                2 * SOL_UNIT_SIZE,
+                1,
+                0,
+                true,
+                OrderType::Global,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )],
+            &test_fixture.payer_keypair(),
+        )
+        .await?;
+    Ok(test_fixture)
+}
+
+async fn self_trade_global_ask(
+    test_fixture: &mut TestFixture,
+    self_trade_behavior: SelfTradeBehavior,
+) -> anyhow::Result<(), BanksClientError> {
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    test_fixture
+        .batch_update_with_global_for_keypair(
+            None,
+            vec![],
+            vec![PlaceOrderParams::new(
+                1 * SOL_UNIT_SIZE,
+                1,
+                0,
+                false,
+                OrderType::Limit,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+            )
+            .with_self_trade_behavior(self_trade_behavior)],
+            &payer_keypair,
+        )
+        .await
+}
+
+#[tokio::test]
+async fn self_trade_global_cancel_maker_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_global_test().await?;
+    self_trade_global_ask(&mut test_fixture, SelfTradeBehavior::CancelMaker).await?;
+
+    // The global bid is removed and the ask rests in its place. Global funds
+    // were never moved onto the market.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert!(!resting_orders[0].get_is_bid());
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        1 * SOL_UNIT_SIZE
+    );
+
+    let payer: Pubkey = test_fixture.payer();
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&payer)
+            .await,
+        0
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_quote_volume(&payer).await,
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_trade_global_decrement_both_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_global_test().await?;
+    self_trade_global_ask(&mut test_fixture, SelfTradeBehavior::DecrementBoth).await?;
+
+    // The global bid shrinks by 1 SOL and the ask is used up. Nothing is
+    // returned to the seat since the global bid never locked funds on the
+    // market, and the ask returns its base.
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert!(resting_orders[0].get_is_bid());
+    assert!(resting_orders[0].is_global());
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        1 * SOL_UNIT_SIZE
+    );
+
+    let payer: Pubkey = test_fixture.payer();
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        1 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_quote_balance_atoms(&payer)
+            .await,
+        0
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_quote_volume(&payer).await,
+        0
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn self_trade_swap_cancel_taker_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = setup_self_trade_test(1 * SOL_UNIT_SIZE).await?;
+
+    // Swap from the wallet with a seat that has the resting ask. The swap
+    // should not touch the ask.
+    test_fixture
+        .usdc_mint_fixture
+        .mint_to(&test_fixture.payer_usdc_fixture.key, 1_000 * USDC_UNIT_SIZE)
+        .await;
+    test_fixture
+        .swap_with_self_trade_behavior(
+            1_000 * USDC_UNIT_SIZE,
+            0,
+            false,
+            true,
+            SelfTradeBehavior::CancelTaker,
+        )
+        .await?;
+
+    let resting_orders: Vec<RestingOrder> = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(resting_orders.len(), 1);
+    assert_eq!(
+        resting_orders[0].get_num_base_atoms().as_u64(),
+        1 * SOL_UNIT_SIZE
+    );
+
+    Ok(())
+}
@@ -5,22 +5,26 @@ use manifest::{
     program::{
         batch_update::PlaceOrderParams, batch_update_instruction, global_add_trader_instruction,
         global_deposit_instruction, global_withdraw_instruction, swap_instruction,
-        ManifestInstruction, SwapParams,
+        swap_instruction_with_params, ManifestInstruction, SwapParams,
     },
     quantities::{BaseAtoms, WrapperU64},
     state::{constants::NO_EXPIRATION_LAST_VALID_SLOT, OrderType},
-    validation::get_vault_address,
+    validation::{get_native_sol_address, get_vault_address},
 };
+use solana_program::program_pack::Pack;
 use solana_program_test::{tokio, ProgramTestContext};
 use solana_sdk::{
+    account::Account,
     instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
     signature::{Keypair, Signer},
     transaction::Transaction,
 };
+use spl_token::native_mint;
 
 use crate::{
-    send_tx_with_retry, Side, TestFixture, Token, TokenAccountFixture, SOL_UNIT_SIZE,
-    USDC_UNIT_SIZE,
+    send_tx_with_retry, MarketFixture, Side, TestFixture, Token, TokenAccountFixture,
+    SOL_UNIT_SIZE, USDC_UNIT_SIZE,
 };
 
 #[tokio::test]
@@ -1083,6 +1087,213 @@ async fn swap_full_match_sell_exact_in_exhaust_book() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn swap_limit_price_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::USDC, 3_000 * USDC_UNIT_SIZE, &second_keypair)
+        .await?;
+
+    // 2 bids for 1@1 and 2@.5
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[batch_update_instruction(
+            &test_fixture.market_fixture.key,
+            &second_keypair.pubkey(),
+            None,
+            vec![],
+            vec![
+                PlaceOrderParams::new(
+                    1 * SOL_UNIT_SIZE,
+                    1,
+                    0,
+                    true,
+                    OrderType::Limit,
+                    NO_EXPIRATION_LAST_VALID_SLOT,
+                ),
+                PlaceOrderParams::new(
+                    2 * SOL_UNIT_SIZE,
+                    5,
+                    -1,
+                    true,
+                    OrderType::Limit,
+                    NO_EXPIRATION_LAST_VALID_SLOT,
+                ),
+            ],
+            None,
+            None,
+            Some(*test_fixture.market_fixture.market.get_quote_mint()),
+            None,
+        )],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await?;
+    test_fixture
+        .sol_mint_fixture
+        .mint_to(&test_fixture.payer_sol_fixture.key, 4 * SOL_UNIT_SIZE)
+        .await;
+
+    // Limit of .8 with a min out above what is available at the limit fails.
+    assert!(test_fixture
+        .swap_with_limit_price(4 * SOL_UNIT_SIZE, 2_000 * USDC_UNIT_SIZE, true, true, 8, -1)
+        .await
+        .is_err());
+
+    // Limit of .8 only matches the bid at 1 and the rest is returned.
+    test_fixture
+        .swap_with_limit_price(4 * SOL_UNIT_SIZE, 1_000 * USDC_UNIT_SIZE, true, true, 8, -1)
+        .await?;
+    assert_eq!(
+        test_fixture.payer_sol_fixture.balance_atoms().await,
+        3 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.payer_usdc_fixture.balance_atoms().await,
+        1_000 * USDC_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        1
+    );
+
+    // Params without the trailing fields are still accepted and do not
+    // have a limit.
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let mut swap_ix: Instruction = swap_instruction(
+        &test_fixture.market_fixture.key,
+        &payer_keypair.pubkey(),
+        &test_fixture.sol_mint_fixture.key,
+        &test_fixture.usdc_mint_fixture.key,
+        &test_fixture.payer_sol_fixture.key,
+        &test_fixture.payer_usdc_fixture.key,
+        0,
+        0,
+        true,
+        true,
+        spl_token::id(),
+        spl_token::id(),
+        false,
+    );
+    swap_ix.data = [
+        ManifestInstruction::Swap.to_vec(),
+        (2 * SOL_UNIT_SIZE).to_le_bytes().to_vec(),
+        (1_000 * USDC_UNIT_SIZE).to_le_bytes().to_vec(),
+        vec![1, 1],
+    ]
+    .concat();
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_ix],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair],
+    )
+    .await?;
+    assert_eq!(
+        test_fixture.payer_sol_fixture.balance_atoms().await,
+        1 * SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.payer_usdc_fixture.balance_atoms().await,
+        2_000 * USDC_UNIT_SIZE
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn swap_rest_remainder_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::SOL, 1 * SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Ask,
+            1 * SOL_UNIT_SIZE,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await?;
+
+    test_fixture
+        .usdc_mint_fixture
+        .mint_to(&test_fixture.payer_usdc_fixture.key, 3_000 * USDC_UNIT_SIZE)
+        .await;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let swap_ix = |params: SwapParams| -> Instruction {
+        swap_instruction_with_params(
+            &test_fixture.market_fixture.key,
+            &payer_keypair.pubkey(),
+            &test_fixture.sol_mint_fixture.key,
+            &test_fixture.usdc_mint_fixture.key,
+            &test_fixture.payer_sol_fixture.key,
+            &test_fixture.payer_usdc_fixture.key,
+            params,
+            spl_token::id(),
+            spl_token::id(),
+            false,
+        )
+    };
+
+    // Resting requires a limit price.
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_ix(
+            SwapParams::new(3_000 * USDC_UNIT_SIZE, 0, false, true).with_rest_remainder(true)
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    // Buy up to 3 SOL at 1_000 USDC. 1 matches and 2 rest as a bid.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_ix(
+            SwapParams::new(3_000 * USDC_UNIT_SIZE, 0, false, true)
+                .with_limit_price(1, 0)
+                .with_rest_remainder(true),
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    assert_eq!(
+        test_fixture.payer_sol_fixture.balance_atoms().await,
+        1 * SOL_UNIT_SIZE
+    );
+    assert_eq!(test_fixture.payer_usdc_fixture.balance_atoms().await, 0);
+    let orders = test_fixture.market_fixture.get_resting_orders().await;
+    assert_eq!(orders.len(), 1);
+    let resting = orders.first().unwrap();
+    assert!(resting.get_is_bid());
+    assert_eq!(resting.get_num_base_atoms(), 2 * SOL_UNIT_SIZE);
+
+    // The seat was kept, so the order can be cancelled and withdrawn.
+    test_fixture.cancel_order(1).await?;
+    test_fixture
+        .withdraw(Token::USDC, 2_000 * USDC_UNIT_SIZE)
+        .await?;
+    assert_eq!(
+        test_fixture.payer_usdc_fixture.balance_atoms().await,
+        2_000 * USDC_UNIT_SIZE
+    );
+
+    Ok(())
+}
+
 // Global is on the USDC, taker is sending in SOL. Global order is not backed,
 // so the order does not get the global price.
 #[tokio::test]
@@ -1203,3 +1414,153 @@ async fn swap_global_not_backed() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+async fn get_lamports(test_fixture: &TestFixture, key: &Pubkey) -> u64 {
+    test_fixture
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_balance(*key)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn swap_native_sol_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    let second: Pubkey = second_keypair.pubkey();
+    let usdc_mint: Pubkey = test_fixture.usdc_mint_fixture.key;
+
+    // Make sure the native mint is there for the market.
+    let mut native_mint_account: Account = Account::new(
+        u32::MAX as u64,
+        spl_token::state::Mint::LEN,
+        &spl_token::id(),
+    );
+    spl_token::state::Mint {
+        decimals: 9,
+        is_initialized: true,
+        ..spl_token::state::Mint::default()
+    }
+    .pack_into_slice(&mut native_mint_account.data);
+    test_fixture
+        .context
+        .borrow_mut()
+        .set_account(&native_mint::id(), &native_mint_account.into());
+    test_fixture.market_fixture = MarketFixture::new(
+        Rc::clone(&test_fixture.context),
+        &native_mint::id(),
+        &usdc_mint,
+    )
+    .await;
+    let market: Pubkey = test_fixture.market_fixture.key;
+
+    // Payer bids 1 SOL at 1 USDC/SOL.
+    test_fixture.claim_seat_for_keypair(&payer_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::USDC, USDC_UNIT_SIZE, &payer_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Bid,
+            SOL_UNIT_SIZE,
+            1,
+            -3,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &payer_keypair,
+        )
+        .await?;
+
+    // Second has no wrapped SOL account, only a USDC account.
+    let second_usdc_fixture: TokenAccountFixture = TokenAccountFixture::new_with_keypair(
+        Rc::clone(&test_fixture.context),
+        &usdc_mint,
+        &second,
+        &Keypair::new(),
+    )
+    .await;
+    let (native_sol_account, _) = get_native_sol_address(&second);
+
+    // Sell 1 SOL paid in lamports.
+    let second_lamports_before: u64 = get_lamports(&test_fixture, &second).await;
+    let market_lamports_before: u64 = get_lamports(&test_fixture, &market).await;
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_instruction_with_params(
+            &market,
+            &second,
+            &native_mint::id(),
+            &usdc_mint,
+            &native_sol_account,
+            &second_usdc_fixture.key,
+            SwapParams::new(SOL_UNIT_SIZE, USDC_UNIT_SIZE, true, true).with_native_sol(true),
+            spl_token::id(),
+            spl_token::id(),
+            false,
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair, &second_keypair],
+    )
+    .await?;
+    // Anything the market grew by for the temporary seat is also paid by
+    // second.
+    let market_rent: u64 = get_lamports(&test_fixture, &market).await - market_lamports_before;
+    assert_eq!(
+        second_lamports_before - get_lamports(&test_fixture, &second).await,
+        SOL_UNIT_SIZE + market_rent
+    );
+    assert_eq!(second_usdc_fixture.balance_atoms().await, USDC_UNIT_SIZE);
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer_keypair.pubkey())
+            .await,
+        SOL_UNIT_SIZE
+    );
+
+    // Payer offers the SOL back and second buys it, receiving lamports.
+    test_fixture
+        .place_order_for_keypair(
+            Side::Ask,
+            SOL_UNIT_SIZE,
+            1,
+            -3,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &payer_keypair,
+        )
+        .await?;
+    let second_lamports_before: u64 = get_lamports(&test_fixture, &second).await;
+    let market_lamports_before: u64 = get_lamports(&test_fixture, &market).await;
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_instruction_with_params(
+            &market,
+            &second,
+            &native_mint::id(),
+            &usdc_mint,
+            &native_sol_account,
+            &second_usdc_fixture.key,
+            SwapParams::new(USDC_UNIT_SIZE, SOL_UNIT_SIZE, false, true).with_native_sol(true),
+            spl_token::id(),
+            spl_token::id(),
+            false,
+        )],
+        Some(&payer_keypair.pubkey()),
+        &[&payer_keypair, &second_keypair],
+    )
+    .await?;
+    let market_rent: u64 = get_lamports(&test_fixture, &market).await - market_lamports_before;
+    assert_eq!(
+        get_lamports(&test_fixture, &second).await - second_lamports_before,
+        SOL_UNIT_SIZE - market_rent
+    );
+    assert_eq!(second_usdc_fixture.balance_atoms().await, 0);
+    // The temporary wrapped SOL account is closed again.
+    assert_eq!(get_lamports(&test_fixture, &native_sol_account).await, 0);
+
+    Ok(())
+}
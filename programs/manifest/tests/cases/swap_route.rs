@@ -0,0 +1,162 @@
+use std::rc::Rc;
+
+use manifest::{
+    program::{
+        batch_update::PlaceOrderParams, batch_update_instruction, claim_seat_instruction,
+        deposit_instruction, swap_route_instruction,
+    },
+    state::{OrderType, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::tokio;
+use solana_sdk::{instruction::Instruction, pubkey::Pubkey, signature::Keypair, signer::Signer};
+
+use crate::{
+    send_tx_with_retry, MintFixture, Side, TestFixture, Token, TokenAccountFixture, SOL_UNIT_SIZE,
+    USDC_UNIT_SIZE,
+};
+
+const BONK_UNIT_SIZE: u64 = 100_000;
+
+#[tokio::test]
+async fn swap_route_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+
+    // SOL/USDC has a bid for 1 SOL at 1_000 USDC.
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::USDC, 1_000 * USDC_UNIT_SIZE, &second_keypair)
+        .await?;
+    test_fixture
+        .place_order_for_keypair(
+            Side::Bid,
+            1 * SOL_UNIT_SIZE,
+            1,
+            0,
+            NO_EXPIRATION_LAST_VALID_SLOT,
+            OrderType::Limit,
+            &second_keypair,
+        )
+        .await?;
+
+    // BONK/USDC has an ask for 500 BONK at 1 USDC.
+    let mut bonk_mint_fixture: MintFixture =
+        MintFixture::new(Rc::clone(&test_fixture.context), Some(5)).await;
+    let bonk_market: Pubkey = test_fixture
+        .create_new_market(&bonk_mint_fixture.key, &test_fixture.usdc_mint_fixture.key)
+        .await?;
+    let maker_bonk_fixture: TokenAccountFixture = TokenAccountFixture::new(
+        Rc::clone(&test_fixture.context),
+        &bonk_mint_fixture.key,
+        &second_keypair.pubkey(),
+    )
+    .await;
+    bonk_mint_fixture
+        .mint_to(&maker_bonk_fixture.key, 500 * BONK_UNIT_SIZE)
+        .await;
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[
+            claim_seat_instruction(&bonk_market, &second_keypair.pubkey()),
+            deposit_instruction(
+                &bonk_market,
+                &second_keypair.pubkey(),
+                &bonk_mint_fixture.key,
+                500 * BONK_UNIT_SIZE,
+                &maker_bonk_fixture.key,
+                spl_token::id(),
+                None,
+            ),
+            batch_update_instruction(
+                &bonk_market,
+                &second_keypair.pubkey(),
+                None,
+                vec![],
+                vec![PlaceOrderParams::new(
+                    500 * BONK_UNIT_SIZE,
+                    10,
+                    0,
+                    false,
+                    OrderType::Limit,
+                    NO_EXPIRATION_LAST_VALID_SLOT,
+                )],
+                None,
+                None,
+                None,
+                None,
+            ),
+        ],
+        Some(&second_keypair.pubkey()),
+        &[&second_keypair],
+    )
+    .await?;
+
+    test_fixture
+        .sol_mint_fixture
+        .mint_to(&test_fixture.payer_sol_fixture.key, 1 * SOL_UNIT_SIZE)
+        .await;
+    let payer_bonk_fixture: TokenAccountFixture = TokenAccountFixture::new(
+        Rc::clone(&test_fixture.context),
+        &bonk_mint_fixture.key,
+        &payer,
+    )
+    .await;
+
+    let swap_route_ix = |min_out_atoms: u64| -> Instruction {
+        swap_route_instruction(
+            &test_fixture.market_fixture.key,
+            &bonk_market,
+            &payer,
+            &payer,
+            &test_fixture.sol_mint_fixture.key,
+            &test_fixture.usdc_mint_fixture.key,
+            &bonk_mint_fixture.key,
+            &test_fixture.payer_sol_fixture.key,
+            &test_fixture.payer_usdc_fixture.key,
+            &payer_bonk_fixture.key,
+            1 * SOL_UNIT_SIZE,
+            min_out_atoms,
+            spl_token::id(),
+            spl_token::id(),
+            spl_token::id(),
+        )
+    };
+
+    // End to end min out is enforced.
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_route_ix(500 * BONK_UNIT_SIZE + 1)],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[swap_route_ix(500 * BONK_UNIT_SIZE)],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    // 1 SOL -> 1_000 USDC -> 500 BONK with 500 USDC the second market could
+    // not use sent back to the trader.
+    assert_eq!(test_fixture.payer_sol_fixture.balance_atoms().await, 0);
+    assert_eq!(
+        test_fixture.payer_usdc_fixture.balance_atoms().await,
+        500 * USDC_UNIT_SIZE
+    );
+    assert_eq!(
+        payer_bonk_fixture.balance_atoms().await,
+        500 * BONK_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture.market_fixture.get_resting_orders().await.len(),
+        0
+    );
+
+    Ok(())
+}
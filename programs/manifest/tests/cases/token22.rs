@@ -8,11 +8,22 @@ use manifest::{
     quantities::WrapperU64,
     state::{OrderType, NO_EXPIRATION_LAST_VALID_SLOT},
 };
+use solana_program::{
+    entrypoint::ProgramResult, program::invoke_signed, program_error::ProgramError,
+};
 use solana_program_test::{processor, ProgramTest, ProgramTestContext};
 use solana_sdk::{
-    instruction::Instruction, program_pack::Pack, pubkey::Pubkey, rent::Rent, signature::Keypair,
-    signer::Signer, system_instruction::create_account,
+    account_info::AccountInfo,
+    instruction::{AccountMeta, Instruction},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction::create_account,
+    system_program,
 };
+use spl_token_2022::extension::ExtensionType;
 
 use crate::{send_tx_with_retry, MintFixture, RUST_LOG_DEFAULT};
 
@@ -740,3 +751,387 @@ async fn token22_deposit_transfer_fee() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+// First 8 bytes of sha256("spl-transfer-hook-interface:execute").
+const TRANSFER_HOOK_EXECUTE_DISCRIMINATOR: [u8; 8] = [105, 37, 101, 197, 75, 251, 102, 26];
+// Local hook rejects bigger transfers so the test can tell that it ran.
+const TRANSFER_HOOK_MAX_ATOMS: u64 = 1_000_000;
+
+fn get_transfer_hook_validation_address(mint: &Pubkey, hook_program: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"extra-account-metas", mint.as_ref()], hook_program)
+}
+
+/// Minimal transfer hook. Execute checks the amount, anything else creates
+/// the validation account for the mint with no extra account metas.
+fn process_transfer_hook_instruction(
+    program_id: &Pubkey,
+    accounts: &[AccountInfo],
+    data: &[u8],
+) -> ProgramResult {
+    if data.len() >= 16 && data[..8] == TRANSFER_HOOK_EXECUTE_DISCRIMINATOR {
+        let amount: u64 = u64::from_le_bytes(data[8..16].try_into().unwrap());
+        if amount > TRANSFER_HOOK_MAX_ATOMS {
+            return Err(ProgramError::InvalidArgument);
+        }
+        return Ok(());
+    }
+
+    let payer: &AccountInfo = &accounts[0];
+    let validation: &AccountInfo = &accounts[1];
+    let mint: &AccountInfo = &accounts[2];
+    let system_program: &AccountInfo = &accounts[3];
+    let (_, bump) = get_transfer_hook_validation_address(mint.key, program_id);
+    // Type, length, then an empty list of extra account metas.
+    let mut validation_data: Vec<u8> = TRANSFER_HOOK_EXECUTE_DISCRIMINATOR.to_vec();
+    validation_data.extend_from_slice(&4_u32.to_le_bytes());
+    validation_data.extend_from_slice(&0_u32.to_le_bytes());
+    invoke_signed(
+        &create_account(
+            payer.key,
+            validation.key,
+            Rent::default().minimum_balance(validation_data.len()),
+            validation_data.len() as u64,
+            program_id,
+        ),
+        &[payer.clone(), validation.clone(), system_program.clone()],
+        &[&[b"extra-account-metas", mint.key.as_ref(), &[bump]]],
+    )?;
+    validation
+        .try_borrow_mut_data()?
+        .copy_from_slice(&validation_data);
+    Ok(())
+}
+
+#[tokio::test]
+async fn token22_transfer_hook() -> anyhow::Result<()> {
+    let hook_program: Pubkey = Pubkey::new_unique();
+    let mut program_test: ProgramTest = ProgramTest::new(
+        "manifest",
+        manifest::ID,
+        processor!(manifest::process_instruction),
+    );
+    program_test.add_program(
+        "transfer_hook",
+        hook_program,
+        processor!(process_transfer_hook_instruction),
+    );
+    solana_logger::setup_with_default(RUST_LOG_DEFAULT);
+
+    let market_keypair: Keypair = Keypair::new();
+    let market: Pubkey = market_keypair.pubkey();
+    let context: Rc<RefCell<ProgramTestContext>> =
+        Rc::new(RefCell::new(program_test.start_with_context().await));
+    let payer_keypair: Keypair = context.borrow().payer.insecure_clone();
+    let payer: &Pubkey = &payer_keypair.pubkey();
+    let rent: Rent = context.borrow_mut().banks_client.get_rent().await.unwrap();
+
+    // Hook mint is base, USDC is old token quote.
+    let usdc_mint_f: MintFixture =
+        MintFixture::new_with_version(Rc::clone(&context), Some(6), false).await;
+    let usdc_mint_key: Pubkey = usdc_mint_f.key;
+    let hook_mint_keypair: Keypair = Keypair::new();
+    let hook_mint_key: Pubkey = hook_mint_keypair.pubkey();
+    let (validation, _) = get_transfer_hook_validation_address(&hook_mint_key, &hook_program);
+
+    let mint_space: usize =
+        ExtensionType::try_calculate_account_len::<spl_token_2022::state::Mint>(&[
+            ExtensionType::TransferHook,
+        ])
+        .unwrap();
+    send_tx_with_retry(
+        Rc::clone(&context),
+        &[
+            create_account(
+                payer,
+                &hook_mint_key,
+                rent.minimum_balance(mint_space),
+                mint_space as u64,
+                &spl_token_2022::id(),
+            ),
+            spl_token_2022::extension::transfer_hook::instruction::initialize(
+                &spl_token_2022::id(),
+                &hook_mint_key,
+                Some(*payer),
+                Some(hook_program),
+            )
+            .unwrap(),
+            spl_token_2022::instruction::initialize_mint2(
+                &spl_token_2022::id(),
+                &hook_mint_key,
+                payer,
+                None,
+                9,
+            )
+            .unwrap(),
+            Instruction {
+                program_id: hook_program,
+                accounts: vec![
+                    AccountMeta::new(*payer, true),
+                    AccountMeta::new(validation, false),
+                    AccountMeta::new_readonly(hook_mint_key, false),
+                    AccountMeta::new_readonly(system_program::id(), false),
+                ],
+                data: vec![],
+            },
+        ],
+        Some(payer),
+        &[&payer_keypair, &hook_mint_keypair],
+    )
+    .await?;
+
+    send_tx_with_retry(
+        Rc::clone(&context),
+        &create_market_instructions(&market, &hook_mint_key, &usdc_mint_key, payer).unwrap(),
+        Some(payer),
+        &[&payer_keypair, &market_keypair],
+    )
+    .await?;
+    send_tx_with_retry(
+        Rc::clone(&context),
+        &[claim_seat_instruction(&market, payer)],
+        Some(payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    // Trader token accounts, hook accounts need the TransferHookAccount extension.
+    let hook_token_account_keypair: Keypair = Keypair::new();
+    let hook_token_account: Pubkey = hook_token_account_keypair.pubkey();
+    let usdc_token_account_keypair: Keypair = Keypair::new();
+    let usdc_token_account: Pubkey = usdc_token_account_keypair.pubkey();
+    let hook_token_account_space: usize = ExtensionType::try_calculate_account_len::<
+        spl_token_2022::state::Account,
+    >(&[ExtensionType::TransferHookAccount])
+    .unwrap();
+    send_tx_with_retry(
+        Rc::clone(&context),
+        &[
+            create_account(
+                payer,
+                &hook_token_account,
+                rent.minimum_balance(hook_token_account_space),
+                hook_token_account_space as u64,
+                &spl_token_2022::id(),
+            ),
+            spl_token_2022::instruction::initialize_account(
+                &spl_token_2022::id(),
+                &hook_token_account,
+                &hook_mint_key,
+                payer,
+            )
+            .unwrap(),
+            create_account(
+                payer,
+                &usdc_token_account,
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::id(),
+            ),
+            spl_token::instruction::initialize_account(
+                &spl_token::id(),
+                &usdc_token_account,
+                &usdc_mint_key,
+                payer,
+            )
+            .unwrap(),
+            spl_token_2022::instruction::mint_to(
+                &spl_token_2022::id(),
+                &hook_mint_key,
+                &hook_token_account,
+                payer,
+                &[payer],
+                1_000_000_000_000,
+            )
+            .unwrap(),
+            spl_token::instruction::mint_to(
+                &spl_token::id(),
+                &usdc_mint_key,
+                &usdc_token_account,
+                payer,
+                &[payer],
+                1_000_000_000_000,
+            )
+            .unwrap(),
+        ],
+        Some(payer),
+        &[
+            &payer_keypair,
+            &hook_token_account_keypair,
+            &usdc_token_account_keypair,
+        ],
+    )
+    .await?;
+
+    let transfer_hook_account_metas: Vec<AccountMeta> = vec![
+        AccountMeta::new_readonly(validation, false),
+        AccountMeta::new_readonly(hook_program, false),
+    ];
+
+    // Deposit needs the hook accounts, and the hook runs on it.
+    let deposit_hook_ix: Instruction = deposit_instruction(
+        &market,
+        payer,
+        &hook_mint_key,
+        TRANSFER_HOOK_MAX_ATOMS,
+        &hook_token_account,
+        spl_token_2022::id(),
+        None,
+    );
+    assert!(send_tx_with_retry(
+        Rc::clone(&context),
+        &[deposit_hook_ix.clone()],
+        Some(payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+    let mut too_big_deposit_hook_ix: Instruction = deposit_instruction(
+        &market,
+        payer,
+        &hook_mint_key,
+        TRANSFER_HOOK_MAX_ATOMS + 1,
+        &hook_token_account,
+        spl_token_2022::id(),
+        None,
+    );
+    too_big_deposit_hook_ix
+        .accounts
+        .extend(transfer_hook_account_metas.clone());
+    assert!(send_tx_with_retry(
+        Rc::clone(&context),
+        &[too_big_deposit_hook_ix],
+        Some(payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+    let mut deposit_hook_ix: Instruction = deposit_hook_ix;
+    deposit_hook_ix
+        .accounts
+        .extend(transfer_hook_account_metas.clone());
+    send_tx_with_retry(
+        Rc::clone(&context),
+        &[
+            deposit_hook_ix,
+            deposit_instruction(
+                &market,
+                payer,
+                &usdc_mint_key,
+                1_000_000_000,
+                &usdc_token_account,
+                spl_token::id(),
+                None,
+            ),
+        ],
+        Some(payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    let mut withdraw_hook_ix: Instruction = withdraw_instruction(
+        &market,
+        payer,
+        &hook_mint_key,
+        1_000,
+        &hook_token_account,
+        spl_token_2022::id(),
+        None,
+    );
+    withdraw_hook_ix
+        .accounts
+        .extend(transfer_hook_account_metas.clone());
+    send_tx_with_retry(
+        Rc::clone(&context),
+        &[withdraw_hook_ix],
+        Some(payer),
+        &[&payer_keypair],
+    )
+    .await?;
+    {
+        let market_account: solana_sdk::account::Account = context
+            .borrow_mut()
+            .banks_client
+            .get_account(market)
+            .await
+            .unwrap()
+            .unwrap();
+        let market_value: manifest::state::MarketValue =
+            manifest::program::get_dynamic_value(market_account.data.as_slice());
+        assert_eq!(
+            market_value.get_trader_balance(payer).0.as_u64(),
+            TRANSFER_HOOK_MAX_ATOMS - 1_000
+        );
+    }
+
+    send_tx_with_retry(
+        Rc::clone(&context),
+        &[batch_update_instruction(
+            &market,
+            payer,
+            None,
+            vec![],
+            vec![
+                PlaceOrderParams::new(
+                    1_000,
+                    9,
+                    -1,
+                    true,
+                    OrderType::PostOnly,
+                    NO_EXPIRATION_LAST_VALID_SLOT,
+                ),
+                PlaceOrderParams::new(
+                    1_000,
+                    11,
+                    -1,
+                    false,
+                    OrderType::PostOnly,
+                    NO_EXPIRATION_LAST_VALID_SLOT,
+                ),
+            ],
+            None,
+            None,
+            None,
+            None,
+        )],
+        Some(payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    // Swaps both ways, hook mint moves in and out of the vault.
+    for is_base_in in [true, false] {
+        let mut swap_ix: Instruction = swap_instruction(
+            &market,
+            payer,
+            &hook_mint_key,
+            &usdc_mint_key,
+            &hook_token_account,
+            &usdc_token_account,
+            100,
+            10,
+            is_base_in,
+            true,
+            spl_token_2022::id(),
+            spl_token::id(),
+            false,
+        );
+        assert!(send_tx_with_retry(
+            Rc::clone(&context),
+            &[swap_ix.clone()],
+            Some(payer),
+            &[&payer_keypair],
+        )
+        .await
+        .is_err());
+        swap_ix.accounts.extend(transfer_hook_account_metas.clone());
+        send_tx_with_retry(
+            Rc::clone(&context),
+            &[swap_ix],
+            Some(payer),
+            &[&payer_keypair],
+        )
+        .await?;
+    }
+
+    Ok(())
+}
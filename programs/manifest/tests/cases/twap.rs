@@ -0,0 +1,87 @@
+use manifest::{
+    quantities::QuoteAtomsPerBaseAtom,
+    state::{MarketFixed, OrderType, PriceObservation, NO_EXPIRATION_LAST_VALID_SLOT},
+};
+use solana_program_test::tokio;
+use solana_sdk::signature::Keypair;
+
+use crate::{Side, TestFixture, Token, SOL_UNIT_SIZE, USDC_UNIT_SIZE};
+
+#[tokio::test]
+async fn twap_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let second_keypair: Keypair = test_fixture.second_keypair.insecure_clone();
+    test_fixture.claim_seat_for_keypair(&second_keypair).await?;
+    test_fixture
+        .deposit_for_keypair(Token::SOL, 2 * SOL_UNIT_SIZE, &second_keypair)
+        .await?;
+    // Asks at 10 and 20 USDC/SOL.
+    for price_mantissa in [1, 2] {
+        test_fixture
+            .place_order_for_keypair(
+                Side::Ask,
+                SOL_UNIT_SIZE,
+                price_mantissa,
+                -2,
+                NO_EXPIRATION_LAST_VALID_SLOT,
+                OrderType::Limit,
+                &second_keypair,
+            )
+            .await?;
+    }
+    let ten: QuoteAtomsPerBaseAtom =
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(1, -2).unwrap();
+    let twenty: QuoteAtomsPerBaseAtom =
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(2, -2).unwrap();
+
+    test_fixture.market_fixture.reload().await;
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .market
+            .fixed
+            .get_last_trade_slot(),
+        0
+    );
+
+    test_fixture
+        .usdc_mint_fixture
+        .mint_to(&test_fixture.payer_usdc_fixture.key, 20 * USDC_UNIT_SIZE)
+        .await;
+    test_fixture
+        .swap(5 * USDC_UNIT_SIZE, 0, false, true)
+        .await?;
+
+    test_fixture.market_fixture.reload().await;
+    let fixed: &MarketFixed = &test_fixture.market_fixture.market.fixed;
+    assert_eq!(fixed.get_last_trade_price(), ten);
+    let first_slot: u32 = fixed.get_last_trade_slot();
+    let first_observation: PriceObservation = fixed.get_price_observation(first_slot);
+
+    // Finishes the first ask and moves the price to the second.
+    test_fixture.advance_time_seconds(20).await;
+    test_fixture
+        .swap(15 * USDC_UNIT_SIZE, 0, false, true)
+        .await?;
+
+    test_fixture.market_fixture.reload().await;
+    let fixed: &MarketFixed = &test_fixture.market_fixture.market.fixed;
+    assert_eq!(fixed.get_last_trade_price(), twenty);
+    let second_slot: u32 = fixed.get_last_trade_slot();
+    assert!(second_slot > first_slot);
+    let second_observation: PriceObservation = fixed.get_price_observation(second_slot);
+    let third_observation: PriceObservation =
+        fixed.get_price_observation(second_slot + (second_slot - first_slot));
+
+    // The second swap filled at both prices, only the last one carries
+    // forward.
+    assert_eq!(first_observation.get_twap(&second_observation)?, ten);
+    assert_eq!(second_observation.get_twap(&third_observation)?, twenty);
+    assert_eq!(
+        first_observation.get_twap(&third_observation)?,
+        QuoteAtomsPerBaseAtom::try_from_mantissa_and_exponent(15, -3).unwrap()
+    );
+    assert!(second_observation.get_twap(&first_observation).is_err());
+
+    Ok(())
+}
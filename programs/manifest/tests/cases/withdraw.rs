@@ -1,7 +1,9 @@
 use std::rc::Rc;
 
 use borsh::BorshSerialize;
-use manifest::program::{withdraw::WithdrawParams, withdraw_instruction, ManifestInstruction};
+use manifest::program::{
+    withdraw::WithdrawParams, withdraw_instruction, withdraw_to_instruction, ManifestInstruction,
+};
 use solana_program_test::{tokio, ProgramTestContext};
 use solana_sdk::{
     instruction::{AccountMeta, Instruction},
@@ -10,7 +12,9 @@ use solana_sdk::{
     transaction::Transaction,
 };
 
-use crate::{MintFixture, TestFixture, Token, TokenAccountFixture, SOL_UNIT_SIZE};
+use crate::{
+    send_tx_with_retry, MintFixture, TestFixture, Token, TokenAccountFixture, SOL_UNIT_SIZE,
+};
 
 #[tokio::test]
 async fn withdraw_test() -> anyhow::Result<()> {
@@ -173,3 +177,71 @@ async fn withdraw_fail_incorrect_vault_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn withdraw_to_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair();
+    let second: Pubkey = test_fixture.second_keypair.pubkey();
+    test_fixture.claim_seat().await?;
+    test_fixture.deposit(Token::SOL, 2 * SOL_UNIT_SIZE).await?;
+
+    let recipient_token_account_keypair: Keypair = Keypair::new();
+    let recipient_token_account_fixture: TokenAccountFixture =
+        TokenAccountFixture::new_with_keypair(
+            Rc::clone(&test_fixture.context),
+            &test_fixture.sol_mint_fixture.key,
+            &second,
+            &recipient_token_account_keypair,
+        )
+        .await;
+
+    // Plain withdraw only goes to the trader's own token accounts.
+    assert!(send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[withdraw_instruction(
+            &test_fixture.market_fixture.key,
+            &payer,
+            &test_fixture.sol_mint_fixture.key,
+            SOL_UNIT_SIZE,
+            &recipient_token_account_fixture.key,
+            spl_token::id(),
+            None,
+        )],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await
+    .is_err());
+
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[withdraw_to_instruction(
+            &test_fixture.market_fixture.key,
+            &payer,
+            &test_fixture.sol_mint_fixture.key,
+            SOL_UNIT_SIZE,
+            &recipient_token_account_fixture.key,
+            spl_token::id(),
+            None,
+        )],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    assert_eq!(
+        recipient_token_account_fixture.balance_atoms().await,
+        SOL_UNIT_SIZE
+    );
+    assert_eq!(
+        test_fixture
+            .market_fixture
+            .get_base_balance_atoms(&payer)
+            .await,
+        SOL_UNIT_SIZE
+    );
+
+    Ok(())
+}
@@ -11,14 +11,18 @@ use manifest::{
         batch_update::{CancelOrderParams, PlaceOrderParams},
         batch_update_instruction,
         claim_seat_instruction::claim_seat_instruction,
-        create_market_instructions, deposit_instruction, get_dynamic_value,
-        global_add_trader_instruction,
+        create_market::CreateMarketParams,
+        create_market_instructions, create_market_with_params_instruction, deposit_instruction,
+        get_dynamic_value, global_add_trader_instruction,
         global_create_instruction::create_global_instruction,
         global_deposit_instruction, global_withdraw_instruction, swap_instruction,
-        swap_v2_instruction, withdraw_instruction,
+        swap_instruction_with_params, swap_v2_instruction, withdraw_instruction, SwapParams,
     },
     quantities::WrapperU64,
-    state::{GlobalFixed, GlobalValue, MarketFixed, MarketValue, OrderType, RestingOrder},
+    state::{
+        GlobalFixed, GlobalValue, MarketFixed, MarketValue, OrderType, RestingOrder,
+        SelfTradeBehavior,
+    },
     validation::{get_global_address, MintAccountInfo},
 };
 use solana_program::{hash::Hash, pubkey::Pubkey, rent::Rent};
@@ -674,6 +678,73 @@ impl TestFixture {
         .await
     }
 
+    pub async fn swap_with_self_trade_behavior(
+        &mut self,
+        in_atoms: u64,
+        out_atoms: u64,
+        is_base_in: bool,
+        is_exact_in: bool,
+        self_trade_behavior: SelfTradeBehavior,
+    ) -> anyhow::Result<(), BanksClientError> {
+        let payer: Pubkey = self.context.borrow().payer.pubkey();
+        let payer_keypair: Keypair = self.context.borrow().payer.insecure_clone();
+        let swap_ix: Instruction = swap_instruction_with_params(
+            &self.market_fixture.key,
+            &payer,
+            &self.sol_mint_fixture.key,
+            &self.usdc_mint_fixture.key,
+            &self.payer_sol_fixture.key,
+            &self.payer_usdc_fixture.key,
+            SwapParams::new(in_atoms, out_atoms, is_base_in, is_exact_in)
+                .with_self_trade_behavior(self_trade_behavior),
+            spl_token::id(),
+            spl_token::id(),
+            false,
+        );
+
+        send_tx_with_retry(
+            Rc::clone(&self.context),
+            &[swap_ix],
+            Some(&payer),
+            &[&payer_keypair],
+        )
+        .await
+    }
+
+    pub async fn swap_with_limit_price(
+        &mut self,
+        in_atoms: u64,
+        out_atoms: u64,
+        is_base_in: bool,
+        is_exact_in: bool,
+        limit_price_mantissa: u32,
+        limit_price_exponent: i8,
+    ) -> anyhow::Result<(), BanksClientError> {
+        let payer: Pubkey = self.context.borrow().payer.pubkey();
+        let payer_keypair: Keypair = self.context.borrow().payer.insecure_clone();
+        let swap_ix: Instruction = swap_instruction_with_params(
+            &self.market_fixture.key,
+            &payer,
+            &self.sol_mint_fixture.key,
+            &self.usdc_mint_fixture.key,
+            &self.payer_sol_fixture.key,
+            &self.payer_usdc_fixture.key,
+            SwapParams::new(in_atoms, out_atoms, is_base_in, is_exact_in)
+                .with_limit_price(limit_price_mantissa, limit_price_exponent),
+            spl_token::id(),
+            spl_token::id(),
+            false,
+        );
+
+        send_tx_with_retry(
+            Rc::clone(&self.context),
+            &[swap_ix],
+            Some(&payer),
+            &[&payer_keypair],
+        )
+        .await
+    }
+
     pub async fn swap_with_global(
         &mut self,
         in_atoms: u64,
@@ -802,13 +873,31 @@ impl MarketFixture {
         context: Rc<RefCell<ProgramTestContext>>,
         base_mint: &Pubkey,
         quote_mint: &Pubkey,
+    ) -> Self {
+        Self::new_with_params(context, base_mint, quote_mint, None).await
+    }
+
+    pub async fn new_with_params(
+        context: Rc<RefCell<ProgramTestContext>>,
+        base_mint: &Pubkey,
+        quote_mint: &Pubkey,
+        params_opt: Option<CreateMarketParams>,
     ) -> Self {
         let market_keypair: Keypair = Keypair::new();
         let payer: Pubkey = context.borrow().payer.pubkey();
         let payer_keypair: Keypair = context.borrow().payer.insecure_clone();
-        let create_market_ixs: Vec<Instruction> =
+        let mut create_market_ixs: Vec<Instruction> =
             create_market_instructions(&market_keypair.pubkey(), base_mint, quote_mint, &payer)
                 .unwrap();
+        if let Some(params) = params_opt {
+            create_market_ixs[1] = create_market_with_params_instruction(
+                &market_keypair.pubkey(),
+                base_mint,
+                quote_mint,
+                &payer,
+                params,
+            );
+        }
 
         send_tx_with_retry(
             Rc::clone(&context),
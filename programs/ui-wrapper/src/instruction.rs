@@ -51,6 +51,7 @@ pub enum ManifestWrapperInstruction {
     #[account(17, writable, name = "quote_global_vault", desc = "Quote global vault")]
     #[account(18, writable, name = "quote_market_vault", desc = "Quote market vault")]
     #[account(19, writable, name = "quote_token_program", desc = "Quote token program")]
+    #[account(20, optional, writable, name = "event_queue", desc = "Market event queue, always the last account, required if the market has one")]
     PlaceOrder = 2,
 
     /// Edit order, deposits additional funds needed. TODO: Not implemented yet
@@ -91,6 +92,7 @@ pub enum ManifestWrapperInstruction {
     #[account(6, name = "system_program", desc = "System program")]
     #[account(7, name = "token_program", desc = "Token program owning trader token account")]
     #[account(8, name = "manifest_program", desc = "Manifest program")]
+    #[account(9, optional, writable, name = "event_queue", desc = "Market event queue, the cancel is only recorded if included")]
     CancelOrder = 4,
 
     /// Settle withdrawable funds.
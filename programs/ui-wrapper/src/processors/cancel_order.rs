@@ -17,6 +17,7 @@ use solana_program::{
     account_info::{next_account_info, AccountInfo},
     clock::Clock,
     entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
     program_error::ProgramError,
     pubkey::Pubkey,
     system_program,
@@ -62,6 +63,8 @@ pub(crate) fn process_cancel_order(
     let token_program: &AccountInfo = next_account_info(account_iter)?;
     let manifest_program: Program =
         Program::new(next_account_info(account_iter)?, &manifest::id())?;
+    // Required by manifest when the market has an event queue.
+    let event_queue_opt: Option<&AccountInfo> = account_iter.next();
 
     check_signer(&wrapper_state, owner.key);
     let market_info_index: DataIndex = get_market_info_index_for_market(&wrapper_state, market.key);
@@ -94,30 +97,33 @@ pub(crate) fn process_cancel_order(
     trace!("cancel index:{wrapper_index} order:{open_order:?} cpi:{core_cancel:?}");
     drop(wrapper_data);
 
-    invoke(
-        &batch_update_instruction(
-            market.key,
-            owner.key,
-            Some(trader_index),
-            vec![core_cancel],
-            vec![],
-            None,
-            None,
-            None,
-            None,
-        ),
-        &[
-            owner.info.clone(),
-            system_program.info.clone(),
-            manifest_program.info.clone(),
-            owner.info.clone(),
-            market.info.clone(),
-            trader_token_account.clone(),
-            vault.clone(),
-            token_program.clone(),
-            mint.clone(),
-        ],
-    )?;
+    let mut ix: Instruction = batch_update_instruction(
+        market.key,
+        owner.key,
+        Some(trader_index),
+        vec![core_cancel],
+        vec![],
+        None,
+        None,
+        None,
+        None,
+    );
+    let mut account_infos: Vec<AccountInfo> = vec![
+        owner.info.clone(),
+        system_program.info.clone(),
+        manifest_program.info.clone(),
+        owner.info.clone(),
+        market.info.clone(),
+        trader_token_account.clone(),
+        vault.clone(),
+        token_program.clone(),
+        mint.clone(),
+    ];
+    if let Some(event_queue) = event_queue_opt {
+        ix.accounts.push(AccountMeta::new(*event_queue.key, false));
+        account_infos.push(event_queue.clone());
+    }
+    invoke(&ix, &account_infos)?;
 
     // Process the order result
     let mut wrapper_data: RefMut<&mut [u8]> = wrapper_state.info.try_borrow_mut_data().unwrap();
@@ -5,8 +5,15 @@ use hypertree::{
     get_helper, DataIndex, HyperTreeReadOperations, HyperTreeValueIteratorTrait, RBNode, NIL,
 };
 use manifest::{
-    quantities::QuoteAtomsPerBaseAtom,
-    state::{constants::NO_EXPIRATION_LAST_VALID_SLOT, OrderType, RestingOrder},
+    program::{
+        create_event_queue_instructions, create_market::CreateMarketParams,
+        create_market_instructions, create_market_with_params_instruction, get_dynamic_value,
+    },
+    quantities::{QuoteAtomsPerBaseAtom, WrapperU64},
+    state::{
+        constants::NO_EXPIRATION_LAST_VALID_SLOT, EventQueueFixed, EventQueueValue, MarketEvent,
+        MarketEventType, OrderType, RestingOrder,
+    },
     validation::{get_global_address, get_global_vault_address, get_vault_address},
 };
 use solana_program::{instruction::AccountMeta, system_program};
@@ -2622,3 +2629,150 @@ async fn wrapper_self_trade_test() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn wrapper_cancel_order_with_event_queue_test() -> anyhow::Result<()> {
+    let mut test_fixture: TestFixture = TestFixture::new().await;
+
+    let payer: Pubkey = test_fixture.payer();
+    let payer_keypair: Keypair = test_fixture.payer_keypair().insecure_clone();
+    let (quote_mint, trader_token_account_quote) = test_fixture
+        .fund_trader_wallet(&payer_keypair, Token::USDC, 2)
+        .await;
+    let base_mint: Pubkey = test_fixture.sol_mint.key;
+
+    // Only markets with an authority can have a queue, so make a new one.
+    let market_keypair: Keypair = Keypair::new();
+    let market: Pubkey = market_keypair.pubkey();
+    let mut create_market_ixs: Vec<Instruction> =
+        create_market_instructions(&market, &base_mint, &quote_mint, &payer).unwrap();
+    create_market_ixs[1] = create_market_with_params_instruction(
+        &market,
+        &base_mint,
+        &quote_mint,
+        &payer,
+        CreateMarketParams::default().with_market_authority(payer),
+    );
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &create_market_ixs[..],
+        Some(&payer),
+        &[&payer_keypair, &market_keypair],
+    )
+    .await?;
+    let event_queue_keypair: Keypair = Keypair::new();
+    let event_queue: Pubkey = event_queue_keypair.pubkey();
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &create_event_queue_instructions(&market, &event_queue, &payer, 4),
+        Some(&payer),
+        &[&payer_keypair, &event_queue_keypair],
+    )
+    .await?;
+    let (quote_vault, _) = get_vault_address(&market, &quote_mint);
+
+    // Placing does not write an event, the queue is passed through anyways.
+    for client_order_id in 1..=2 {
+        let place_order_ix = Instruction {
+            program_id: ui_wrapper::id(),
+            accounts: vec![
+                AccountMeta::new(test_fixture.wrapper.key, false),
+                AccountMeta::new(payer, true),
+                AccountMeta::new(trader_token_account_quote, false),
+                AccountMeta::new(market, false),
+                AccountMeta::new(quote_vault, false),
+                AccountMeta::new_readonly(quote_mint, false),
+                AccountMeta::new_readonly(system_program::id(), false),
+                AccountMeta::new_readonly(spl_token::id(), false),
+                AccountMeta::new_readonly(manifest::id(), false),
+                AccountMeta::new(payer, true),
+                AccountMeta::new(event_queue, false),
+            ],
+            data: [
+                ManifestWrapperInstruction::PlaceOrder.to_vec(),
+                WrapperPlaceOrderParams::new(
+                    client_order_id,
+                    1,
+                    1,
+                    0,
+                    true,
+                    NO_EXPIRATION_LAST_VALID_SLOT,
+                    OrderType::Limit,
+                )
+                .try_to_vec()
+                .unwrap(),
+            ]
+            .concat(),
+        };
+        send_tx_with_retry(
+            Rc::clone(&test_fixture.context),
+            &[place_order_ix],
+            Some(&payer),
+            &[&payer_keypair],
+        )
+        .await?;
+    }
+
+    let cancel_order_ix = |client_order_id: u64| Instruction {
+        program_id: ui_wrapper::id(),
+        accounts: vec![
+            AccountMeta::new(test_fixture.wrapper.key, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new(trader_token_account_quote, false),
+            AccountMeta::new(market, false),
+            AccountMeta::new(quote_vault, false),
+            AccountMeta::new_readonly(quote_mint, false),
+            AccountMeta::new_readonly(system_program::id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new_readonly(manifest::id(), false),
+        ],
+        data: [
+            ManifestWrapperInstruction::CancelOrder.to_vec(),
+            WrapperCancelOrderParams::new(client_order_id)
+                .try_to_vec()
+                .unwrap(),
+        ]
+        .concat(),
+    };
+
+    // Cancels do not need the queue, nothing is written without it.
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[cancel_order_ix(1)],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    // With the queue the wrapper passes it on and the cancel is recorded.
+    let mut cancel_with_event_queue_ix: Instruction = cancel_order_ix(2);
+    cancel_with_event_queue_ix
+        .accounts
+        .push(AccountMeta::new(event_queue, false));
+    send_tx_with_retry(
+        Rc::clone(&test_fixture.context),
+        &[cancel_with_event_queue_ix],
+        Some(&payer),
+        &[&payer_keypair],
+    )
+    .await?;
+
+    let event_queue_account: Account = test_fixture
+        .context
+        .borrow_mut()
+        .banks_client
+        .get_account(event_queue)
+        .await
+        .unwrap()
+        .unwrap();
+    let event_queue_value: EventQueueValue =
+        get_dynamic_value::<EventQueueFixed>(event_queue_account.data.as_slice());
+    let events: Vec<MarketEvent> = event_queue_value.get_unconsumed_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].get_event_type()?, MarketEventType::Cancel);
+    assert_eq!(events[0].maker, payer);
+    assert_eq!(events[0].maker_sequence_number, 1);
+    assert_eq!(events[0].base_atoms.as_u64(), 1);
+
+    Ok(())
+}
@@ -61,6 +61,7 @@ pub enum ManifestWrapperInstruction {
     #[account(12, optional, name = "quote_global_vault", desc = "Quote global vault")]
     #[account(13, optional, name = "quote_market_vault", desc = "Quote market vault")]
     #[account(14, optional, name = "quote_token_program", desc = "Token program(22)")]
+    #[account(15, optional, writable, name = "event_queue", desc = "Market event queue, always the last account, required if the market has one")]
     BatchUpdate = 4,
 
     /// BatchUpdate base global.
@@ -74,6 +75,7 @@ pub enum ManifestWrapperInstruction {
     #[account(7, name = "base_global_vault", desc = "Base global vault")]
     #[account(8, name = "base_market_vault", desc = "Base market vault")]
     #[account(9, name = "base_token_program", desc = "Token program(22)")]
+    #[account(10, optional, writable, name = "event_queue", desc = "Market event queue, required if the market has one")]
     BatchUpdateBaseGlobal = 5,
 
     /// BatchUpdate quote global.
@@ -87,6 +89,7 @@ pub enum ManifestWrapperInstruction {
     #[account(7, name = "quote_global_vault", desc = "Quote global vault")]
     #[account(8, name = "quote_market_vault", desc = "Quote market vault")]
     #[account(9, name = "quote_token_program", desc = "Token program(22)")]
+    #[account(10, optional, writable, name = "event_queue", desc = "Market event queue, required if the market has one")]
     BatchUpdateQuoteGlobal = 6,
 
     /// Collect.